@@ -1,8 +1,8 @@
 use crate::file::File;
 use crate::metadata::Metadata;
+use crate::vfs::{FileSystem, RealFileSystem};
 use std::collections::{BTreeMap, HashSet};
 use std::ffi::{OsStr, OsString};
-use std::fs::{DirEntry, ReadDir};
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 
@@ -70,11 +70,20 @@ impl Directory {
         path: &PathBuf,
         new_directory: &mut Directory,
     ) -> std::io::Result<()> {
-        let read_dir = std::fs::read_dir(path)?;
-        let metadata = self.read_parent(path);
+        self.read_path_with(&RealFileSystem, path, new_directory)
+    }
+
+    pub fn read_path_with(
+        &mut self,
+        file_system: &dyn FileSystem,
+        path: &PathBuf,
+        new_directory: &mut Directory,
+    ) -> std::io::Result<()> {
+        let entries = file_system.read_dir(path)?;
+        let metadata = self.read_parent(file_system, path);
         let mut directories = BTreeMap::new();
         let mut files = BTreeMap::new();
-        insert_entries(&mut directories, &mut files, read_dir);
+        insert_entries(file_system, &mut directories, &mut files, entries);
 
         if let None = self.directories {
             new_directory.directories = Some(directories);
@@ -223,7 +232,7 @@ impl Directory {
         &self.metadata
     }
 
-    fn read_parent(&self, path: &PathBuf) -> Option<Metadata> {
+    fn read_parent(&self, file_system: &dyn FileSystem, path: &PathBuf) -> Option<Metadata> {
         if let Some(last) = path.iter().last() {
             let parent_path: PathBuf = path
                 .iter()
@@ -235,7 +244,7 @@ impl Directory {
                 })
                 .collect();
             if !parent_path.as_os_str().is_empty() {
-                if let Ok(metadata) = read_parent_entry(&parent_path, last) {
+                if let Ok(metadata) = read_parent_entry(file_system, &parent_path, last) {
                     return metadata;
                 }
             }
@@ -244,14 +253,75 @@ impl Directory {
     }
 }
 
-fn read_parent_entry(path: &PathBuf, last_directory: &OsStr) -> std::io::Result<Option<Metadata>> {
-    let read_dir = std::fs::read_dir(path)?;
-    for entry in read_dir {
-        if let Some(ok_entry) = entry.ok() {
-            if ok_entry.file_name() == last_directory {
-                if let Some(parent) = write_directory_entry(&ok_entry) {
-                    return Ok(parent.get_metadata().clone());
-                }
+pub fn read_directory_recursive(path: &PathBuf) -> std::io::Result<Directory> {
+    read_directory_recursive_with(&RealFileSystem, path)
+}
+
+pub fn read_directory_recursive_with(
+    file_system: &dyn FileSystem,
+    path: &PathBuf,
+) -> std::io::Result<Directory> {
+    let mut directory = Directory::new(None);
+    Directory::new(None).read_path_with(file_system, path, &mut directory)?;
+    if let Some(directories) = directory.get_mut_directories() {
+        let mut path_stack = PathBuf::from(path);
+        for (key, sub_directory) in directories.iter_mut() {
+            path_stack.push(key);
+            *sub_directory = read_directory_recursive_with(file_system, &path_stack)?;
+            path_stack.pop();
+        }
+    }
+    Ok(directory)
+}
+
+// Reads a single file the way directory entries are read, for files that
+// are selected from outside the directory tree.
+pub fn read_file(path: &Path) -> std::io::Result<File> {
+    read_file_with(&RealFileSystem, path)
+}
+
+pub fn read_file_with(file_system: &dyn FileSystem, path: &Path) -> std::io::Result<File> {
+    if !file_system.metadata(path)?.is_file() {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "Only files can be selected.",
+        ));
+    }
+    write_file_entry(file_system, path)
+        .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "Could not read file metadata."))
+}
+
+// Paths of every file below path in sorted order.
+pub fn list_files_recursive(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    list_files_recursive_with(&RealFileSystem, path)
+}
+
+pub fn list_files_recursive_with(
+    file_system: &dyn FileSystem,
+    path: &Path,
+) -> std::io::Result<Vec<PathBuf>> {
+    let mut entries = file_system.read_dir(path)?;
+    entries.sort();
+    let mut files = Vec::new();
+    for entry in entries {
+        if file_system.metadata(&entry)?.is_dir() {
+            files.extend(list_files_recursive_with(file_system, &entry)?);
+        } else {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+fn read_parent_entry(
+    file_system: &dyn FileSystem,
+    path: &Path,
+    last_directory: &OsStr,
+) -> std::io::Result<Option<Metadata>> {
+    for entry in file_system.read_dir(path)? {
+        if entry.file_name() == Some(last_directory) {
+            if let Some(parent) = write_directory_entry(file_system, &entry) {
+                return Ok(parent.get_metadata().clone());
             }
         }
     }
@@ -259,41 +329,35 @@ fn read_parent_entry(path: &PathBuf, last_directory: &OsStr) -> std::io::Result<
 }
 
 fn insert_entries(
+    file_system: &dyn FileSystem,
     directories: &mut BTreeMap<OsString, Directory>,
     files: &mut BTreeMap<OsString, File>,
-    read_dir: ReadDir,
+    entries: Vec<PathBuf>,
 ) {
-    for entry in read_dir {
-        if let Some(ok_entry) = entry.ok() {
-            let file_name = ok_entry.file_name();
-
-            if let Some(directory) = write_directory_entry(&ok_entry) {
-                directories.insert(OsString::from(file_name.as_os_str()), directory);
+    for entry in entries {
+        if let Some(file_name) = entry.file_name() {
+            if let Some(directory) = write_directory_entry(file_system, &entry) {
+                directories.insert(OsString::from(file_name), directory);
             }
-            if let Some(file) = write_file_entry(&ok_entry) {
-                files.insert(OsString::from(file_name.as_os_str()), file);
+            if let Some(file) = write_file_entry(file_system, &entry) {
+                files.insert(OsString::from(file_name), file);
             }
         }
     }
 }
 
-fn write_directory_entry(entry: &DirEntry) -> Option<Directory> {
-    let origin_path = entry.path();
-    match entry.metadata() {
+fn write_directory_entry(file_system: &dyn FileSystem, entry: &Path) -> Option<Directory> {
+    match file_system.metadata(entry) {
         Ok(metadata) => {
-            let created = metadata.created().ok().take();
-            let accessed = metadata.accessed().ok().take();
-            let modified = metadata.modified().ok().take();
-            let readonly = metadata.permissions().readonly();
             if metadata.is_dir() {
                 return Some(Directory::new(Some(Metadata::build(
-                    Some(entry.file_name()),
-                    created,
-                    accessed,
-                    modified,
+                    entry.file_name().map(OsString::from),
+                    metadata.get_created(),
+                    metadata.get_accessed(),
+                    metadata.get_modified(),
                     None,
-                    readonly,
-                    Some(origin_path),
+                    metadata.is_readonly(),
+                    Some(PathBuf::from(entry)),
                     None,
                 ))));
             }
@@ -303,26 +367,21 @@ fn write_directory_entry(entry: &DirEntry) -> Option<Directory> {
     }
 }
 
-fn write_file_entry(entry: &DirEntry) -> Option<File> {
-    let origin_path = entry.path();
-    match entry.metadata() {
+fn write_file_entry(file_system: &dyn FileSystem, entry: &Path) -> Option<File> {
+    match file_system.metadata(entry) {
         Ok(metadata) => {
-            let created = metadata.created().ok().take();
-            let accessed = metadata.accessed().ok().take();
-            let modified = metadata.modified().ok().take();
-            let size = metadata.len() as f64;
-            let readonly = metadata.permissions().readonly();
             if metadata.is_file() {
-                return Some(File::new(Metadata::build(
-                    Some(entry.file_name()),
-                    created,
-                    accessed,
-                    modified,
-                    Some(size),
-                    readonly,
-                    Some(origin_path),
+                let file_metadata = Metadata::build(
+                    entry.file_name().map(OsString::from),
+                    metadata.get_created(),
+                    metadata.get_accessed(),
+                    metadata.get_modified(),
+                    Some(metadata.get_len() as f64),
+                    metadata.is_readonly(),
+                    Some(PathBuf::from(entry)),
                     None,
-                )));
+                );
+                return Some(File::new(file_metadata));
             }
             None
         }
@@ -354,7 +413,18 @@ fn identify_prefix(path: &PathBuf) -> String {
 }
 
 pub mod system_dir {
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
+
+    const USER_DIRS_FILE_NAME: &str = "user-dirs.dirs";
+    const DEFAULT_USER_DIRECTORIES: [&str; 6] = [
+        "Desktop",
+        "Documents",
+        "Downloads",
+        "Music",
+        "Pictures",
+        "Videos",
+    ];
+
     pub fn get_home_directory() -> Option<PathBuf> {
         let environment_var = match std::env::consts::OS {
             "windows" => std::env::var_os("USERPROFILE"),
@@ -377,11 +447,67 @@ pub mod system_dir {
             }
         }
     }
+
+    // The XDG user directories, like Downloads and Pictures. They are read
+    // from user-dirs.dirs when the desktop has written one, otherwise the
+    // usual directory names in the home directory are used. Directories
+    // that do not exist are left out.
+    pub fn get_user_directories(home_directory_path: &Path) -> Vec<PathBuf> {
+        let mut path_to_user_dirs = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(config_home) => PathBuf::from(config_home),
+            None => home_directory_path.join(".config"),
+        };
+        path_to_user_dirs.push(USER_DIRS_FILE_NAME);
+        let user_directories = match std::fs::read_to_string(path_to_user_dirs) {
+            Ok(content) => parse_user_dirs(&content, home_directory_path),
+            Err(_) => DEFAULT_USER_DIRECTORIES
+                .iter()
+                .map(|directory_name| home_directory_path.join(directory_name))
+                .collect(),
+        };
+        user_directories
+            .into_iter()
+            .filter(|user_directory| user_directory.is_dir())
+            .collect()
+    }
+
+    // Lines are like XDG_DOWNLOAD_DIR="$HOME/Downloads". Entries pointing to
+    // the home directory itself mean the directory is disabled.
+    pub(crate) fn parse_user_dirs(content: &str, home_directory_path: &Path) -> Vec<PathBuf> {
+        let mut user_directories = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            if !name.starts_with("XDG_") || !name.ends_with("_DIR") {
+                continue;
+            }
+            let value = value.trim().trim_matches('"');
+            let path = match value.strip_prefix("$HOME") {
+                Some(relative) => {
+                    let relative = relative.trim_start_matches('/');
+                    if relative.is_empty() {
+                        continue;
+                    }
+                    home_directory_path.join(relative)
+                }
+                None if value.starts_with('/') => PathBuf::from(value),
+                None => continue,
+            };
+            user_directories.push(path);
+        }
+        user_directories
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::vfs::MemoryFileSystem;
 
     #[test]
     fn test_identify_prefix() {
@@ -687,4 +813,103 @@ pub mod tests {
             assert_eq!(files.contains_key(&OsString::from("file5.txt")), false);
         }
     }
+
+    #[test]
+    fn test_read_directory_recursive_with() {
+        let file_system = MemoryFileSystem::new();
+        let modified =
+            std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_647_518_400);
+        file_system.write_file(Path::new("/home/photos/photo.jpg"), b"photo");
+        file_system.write_file(Path::new("/home/photos/2022/old.jpg"), b"old photo");
+        file_system.write_file(Path::new("/home/notes.txt"), b"notes");
+        file_system
+            .set_times(Path::new("/home/notes.txt"), None, None, Some(modified))
+            .expect("Failed to set times");
+        file_system
+            .set_readonly(Path::new("/home/notes.txt"), true)
+            .expect("Failed to set readonly");
+
+        let directory = read_directory_recursive_with(&file_system, &PathBuf::from("/home"))
+            .expect("Failed to read directory");
+        let files = directory.get_files().as_ref().expect("Files missing");
+        let notes = files
+            .get(&OsString::from("notes.txt"))
+            .and_then(|file| file.get_metadata().as_ref())
+            .expect("notes.txt missing");
+        assert_eq!(notes.get_size(), Some(5.0));
+        assert!(notes.get_readonly());
+        assert_eq!(
+            notes.get_modified(),
+            Some(chrono::DateTime::<chrono::Local>::from(modified))
+        );
+        assert_eq!(
+            notes.get_origin_path(),
+            Some(PathBuf::from("/home/notes.txt"))
+        );
+
+        let photos = directory.get_directory_by_path(&PathBuf::from("photos"));
+        assert!(photos
+            .get_files()
+            .as_ref()
+            .is_some_and(|files| files.contains_key(&OsString::from("photo.jpg"))));
+        assert_eq!(
+            directory
+                .get_directory_by_path(&PathBuf::from("photos/2022"))
+                .get_file_count(),
+            1
+        );
+
+        file_system.inject_failure(Path::new("/home/photos/2022"), ErrorKind::PermissionDenied);
+        let error = read_directory_recursive_with(&file_system, &PathBuf::from("/home"))
+            .expect_err("Reading should fail");
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn test_read_files_outside_tree() {
+        let file_system = MemoryFileSystem::new();
+        file_system.write_file(Path::new("/home/photos/b.jpg"), b"b");
+        file_system.write_file(Path::new("/home/photos/2022/a.jpg"), b"a");
+        file_system.write_file(Path::new("/home/notes.txt"), b"notes");
+
+        assert_eq!(
+            list_files_recursive_with(&file_system, Path::new("/home"))
+                .expect("Failed to list files"),
+            vec![
+                PathBuf::from("/home/notes.txt"),
+                PathBuf::from("/home/photos/2022/a.jpg"),
+                PathBuf::from("/home/photos/b.jpg"),
+            ]
+        );
+        let file = read_file_with(&file_system, Path::new("/home/photos/b.jpg"))
+            .expect("Failed to read file");
+        let metadata = file.get_metadata().as_ref().expect("Metadata missing");
+        assert_eq!(metadata.get_size(), Some(1.0));
+        assert_eq!(
+            metadata.get_origin_path(),
+            Some(PathBuf::from("/home/photos/b.jpg"))
+        );
+        let error = read_file_with(&file_system, Path::new("/home/photos"))
+            .expect_err("Directories are not files");
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_parse_user_dirs() {
+        let home_directory_path = PathBuf::from("/home/verneri");
+        let content = "# written by xdg-user-dirs-update\n\
+                       XDG_DESKTOP_DIR=\"$HOME/Työpöytä\"\n\
+                       XDG_DOWNLOAD_DIR=\"$HOME/Downloads\"\n\
+                       XDG_TEMPLATES_DIR=\"$HOME/\"\n\
+                       XDG_PICTURES_DIR=\"/mnt/photos\"\n\
+                       NOT_A_DIR=\"$HOME/other\"\n";
+        assert_eq!(
+            system_dir::parse_user_dirs(content, &home_directory_path),
+            vec![
+                PathBuf::from("/home/verneri/Työpöytä"),
+                PathBuf::from("/home/verneri/Downloads"),
+                PathBuf::from("/mnt/photos"),
+            ]
+        );
+    }
 }
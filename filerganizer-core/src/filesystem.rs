@@ -0,0 +1,956 @@
+use crate::file::File;
+use crate::plan::{Plan, PlannedMove};
+use crate::trash::{Trash, TrashedItem};
+use crate::vfs::{FileSystem, RealFileSystem};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// How files get from their origin to the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransferMode {
+    #[default]
+    Move,
+    Copy,
+    HardLink,
+    Symlink,
+    Reflink,
+}
+
+pub const TRANSFER_MODES: [TransferMode; 5] = [
+    TransferMode::Move,
+    TransferMode::Copy,
+    TransferMode::HardLink,
+    TransferMode::Symlink,
+    TransferMode::Reflink,
+];
+
+impl std::fmt::Display for TransferMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TransferMode::Move => "Move",
+            TransferMode::Copy => "Copy",
+            TransferMode::HardLink => "Hard link",
+            TransferMode::Symlink => "Symbolic link",
+            TransferMode::Reflink => "Reflink",
+        })
+    }
+}
+
+impl TransferMode {
+    // Single lowercase word used on the command line.
+    pub fn get_argument_name(&self) -> &'static str {
+        match self {
+            TransferMode::Move => "move",
+            TransferMode::Copy => "copy",
+            TransferMode::HardLink => "hardlink",
+            TransferMode::Symlink => "symlink",
+            TransferMode::Reflink => "reflink",
+        }
+    }
+
+    // Accepts the displayed name or the argument name.
+    pub fn parse(input: &str) -> Option<Self> {
+        TRANSFER_MODES.into_iter().find(|transfer_mode| {
+            transfer_mode.to_string() == input || transfer_mode.get_argument_name() == input
+        })
+    }
+}
+
+pub const DEFAULT_JUNK_FILE_NAMES: [&str; 2] = [".DS_Store", "Thumbs.db"];
+
+// Which directories the cleanup after a commit may remove. Directories are
+// removed only below boundary and when they hold nothing but junk files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CleanupOptions {
+    boundary: PathBuf,
+    junk_file_names: Vec<String>,
+}
+
+impl CleanupOptions {
+    pub fn new(boundary: PathBuf, junk_file_names: Vec<String>) -> Self {
+        Self {
+            boundary,
+            junk_file_names,
+        }
+    }
+
+    pub fn get_boundary(&self) -> &PathBuf {
+        &self.boundary
+    }
+
+    pub fn get_junk_file_names(&self) -> &Vec<String> {
+        &self.junk_file_names
+    }
+}
+
+// Record of what an apply changed on disk, used to undo it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Journal {
+    transfer_mode: TransferMode,
+    moves: Vec<PlannedMove>,
+    created_directories: Vec<PathBuf>,
+    removed_directories: Vec<PathBuf>,
+    trashed_files: Vec<TrashedItem>,
+}
+
+impl Journal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_transfer_mode(&self) -> TransferMode {
+        self.transfer_mode
+    }
+
+    pub fn get_moves(&self) -> &Vec<PlannedMove> {
+        &self.moves
+    }
+
+    pub fn get_created_directories(&self) -> &Vec<PathBuf> {
+        &self.created_directories
+    }
+
+    // In the order they were removed, the deepest first.
+    pub fn get_removed_directories(&self) -> &Vec<PathBuf> {
+        &self.removed_directories
+    }
+
+    pub fn get_trashed_files(&self) -> &Vec<TrashedItem> {
+        &self.trashed_files
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+            && self.created_directories.is_empty()
+            && self.removed_directories.is_empty()
+            && self.trashed_files.is_empty()
+    }
+}
+
+// How far an apply has got. Bytes are the sizes of the files transferred.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    files_done: usize,
+    files_total: usize,
+    bytes_done: u64,
+    bytes_total: u64,
+    current_file: Option<PathBuf>,
+    elapsed: Duration,
+}
+
+impl Progress {
+    pub fn get_files_done(&self) -> usize {
+        self.files_done
+    }
+
+    pub fn get_files_total(&self) -> usize {
+        self.files_total
+    }
+
+    pub fn get_bytes_done(&self) -> u64 {
+        self.bytes_done
+    }
+
+    pub fn get_bytes_total(&self) -> u64 {
+        self.bytes_total
+    }
+
+    // File being transferred, None once everything is done.
+    pub fn get_current_file(&self) -> &Option<PathBuf> {
+        &self.current_file
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    // Share of the work done between 0 and 1, by bytes when the files have
+    // any and by files otherwise.
+    pub fn get_fraction_done(&self) -> f32 {
+        if self.bytes_total > 0 {
+            return self.bytes_done as f32 / self.bytes_total as f32;
+        }
+        if self.files_total > 0 {
+            return self.files_done as f32 / self.files_total as f32;
+        }
+        1.0
+    }
+
+    // Estimated time left at the pace so far. None until something is done.
+    pub fn get_eta(&self) -> Option<Duration> {
+        let fraction_done = self.get_fraction_done() as f64;
+        if fraction_done <= 0.0 {
+            return None;
+        }
+        Some(
+            self.elapsed
+                .mul_f64((1.0 - fraction_done).max(0.0) / fraction_done),
+        )
+    }
+}
+
+pub fn move_files_organized(
+    files_organized: &BTreeMap<OsString, File>,
+    transfer_mode: TransferMode,
+) -> std::io::Result<Journal> {
+    apply(&Plan::from_files_organized(files_organized), transfer_mode)
+}
+
+// Transfers every file in the plan. If a transfer fails the ones done so far
+// are rolled back, so a failed apply leaves the filesystem as it was.
+pub fn apply(plan: &Plan, transfer_mode: TransferMode) -> std::io::Result<Journal> {
+    apply_with(&RealFileSystem, plan, transfer_mode)
+}
+
+pub fn apply_with(
+    file_system: &dyn FileSystem,
+    plan: &Plan,
+    transfer_mode: TransferMode,
+) -> std::io::Result<Journal> {
+    apply_with_progress(
+        file_system,
+        plan,
+        transfer_mode,
+        &AtomicBool::new(false),
+        &mut |_| {},
+    )
+}
+
+// Reports the progress before every file and once more when all are done.
+// Setting cancel stops the apply before the next file and rolls back the
+// files transferred so far, the same way as a failure.
+pub fn apply_with_progress(
+    file_system: &dyn FileSystem,
+    plan: &Plan,
+    transfer_mode: TransferMode,
+    cancel: &AtomicBool,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> std::io::Result<Journal> {
+    let started = Instant::now();
+    let file_sizes: Vec<u64> = plan
+        .get_moves()
+        .iter()
+        .map(|planned_move| {
+            file_system
+                .metadata(planned_move.get_origin())
+                .map_or(0, |metadata| metadata.get_len())
+        })
+        .collect();
+    let mut progress = Progress {
+        files_done: 0,
+        files_total: plan.get_moves().len(),
+        bytes_done: 0,
+        bytes_total: file_sizes.iter().sum(),
+        current_file: None,
+        elapsed: Duration::ZERO,
+    };
+    let mut journal = Journal::new();
+    journal.transfer_mode = transfer_mode;
+    for (planned_move, file_size) in plan.get_moves().iter().zip(file_sizes) {
+        progress.current_file = Some(PathBuf::from(planned_move.get_origin()));
+        progress.elapsed = started.elapsed();
+        on_progress(&progress);
+        if cancel.load(Ordering::Relaxed) {
+            let error = std::io::Error::new(ErrorKind::Interrupted, "Commit cancelled.");
+            return Err(roll_back(file_system, &journal, error));
+        }
+        if let Err(error) = transfer_file(file_system, planned_move, &mut journal) {
+            return Err(roll_back(file_system, &journal, error));
+        }
+        progress.files_done += 1;
+        progress.bytes_done += file_size;
+    }
+    progress.current_file = None;
+    progress.elapsed = started.elapsed();
+    on_progress(&progress);
+    Ok(journal)
+}
+
+fn roll_back(
+    file_system: &dyn FileSystem,
+    journal: &Journal,
+    error: std::io::Error,
+) -> std::io::Error {
+    match undo_with(file_system, journal) {
+        Ok(_) => error,
+        Err(undo_error) => std::io::Error::new(
+            error.kind(),
+            format!("{} Rolling back failed: {}", error, undo_error),
+        ),
+    }
+}
+
+// Moves files back in reverse order, or removes the copies and links, and
+// removes the directories the apply created once they are empty again.
+pub fn undo(journal: &Journal) -> std::io::Result<()> {
+    undo_with(&RealFileSystem, journal)
+}
+
+pub fn undo_with(file_system: &dyn FileSystem, journal: &Journal) -> std::io::Result<()> {
+    undo_with_progress(file_system, journal, &AtomicBool::new(false), &mut |_| {})
+}
+
+// Reports the progress before every restored file and once more when all
+// are done. Setting cancel stops the undo before the next file. Files put
+// back so far stay in place, and undoing the same journal again finishes
+// the rest.
+pub fn undo_with_progress(
+    file_system: &dyn FileSystem,
+    journal: &Journal,
+    cancel: &AtomicBool,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> std::io::Result<()> {
+    let started = Instant::now();
+    let trashed_file_sizes: Vec<u64> = journal
+        .trashed_files
+        .iter()
+        .map(|trashed_file| {
+            file_system
+                .metadata(&trashed_file.get_trashed_path())
+                .map_or(0, |metadata| metadata.get_len())
+        })
+        .collect();
+    let file_sizes: Vec<u64> = journal
+        .moves
+        .iter()
+        .map(|planned_move| {
+            file_system
+                .metadata(planned_move.get_destination())
+                .map_or(0, |metadata| metadata.get_len())
+        })
+        .collect();
+    let mut progress = Progress {
+        files_done: 0,
+        files_total: journal.trashed_files.len() + journal.moves.len(),
+        bytes_done: 0,
+        bytes_total: trashed_file_sizes.iter().chain(&file_sizes).sum(),
+        current_file: None,
+        elapsed: Duration::ZERO,
+    };
+    for directory in journal.removed_directories.iter().rev() {
+        if !file_system.exists(directory)? {
+            create_dir_all(file_system, directory)?;
+        }
+    }
+    for (trashed_file, file_size) in journal.trashed_files.iter().zip(trashed_file_sizes) {
+        report_undo_progress(
+            &mut progress,
+            trashed_file.get_original_path(),
+            started,
+            cancel,
+            on_progress,
+        )?;
+        // Already restored by an earlier undo that failed later on
+        if !file_system.exists(trashed_file.get_original_path())? {
+            file_system.restore(trashed_file)?;
+        }
+        progress.files_done += 1;
+        progress.bytes_done += file_size;
+    }
+    for (planned_move, file_size) in journal.moves.iter().zip(file_sizes).rev() {
+        report_undo_progress(
+            &mut progress,
+            planned_move.get_origin(),
+            started,
+            cancel,
+            on_progress,
+        )?;
+        match journal.transfer_mode {
+            TransferMode::Move => undo_move(file_system, planned_move)?,
+            transfer_mode => undo_transfer(file_system, planned_move, transfer_mode)?,
+        }
+        progress.files_done += 1;
+        progress.bytes_done += file_size;
+    }
+    for directory in journal.created_directories.iter().rev() {
+        if file_system.exists(directory)? && file_system.read_dir(directory)?.is_empty() {
+            file_system.remove(directory)?;
+        }
+    }
+    progress.current_file = None;
+    progress.elapsed = started.elapsed();
+    on_progress(&progress);
+    Ok(())
+}
+
+fn report_undo_progress(
+    progress: &mut Progress,
+    current_file: &Path,
+    started: Instant,
+    cancel: &AtomicBool,
+    on_progress: &mut dyn FnMut(&Progress),
+) -> std::io::Result<()> {
+    progress.current_file = Some(PathBuf::from(current_file));
+    progress.elapsed = started.elapsed();
+    on_progress(progress);
+    if cancel.load(Ordering::Relaxed) {
+        return Err(std::io::Error::new(
+            ErrorKind::Interrupted,
+            "Undo cancelled.",
+        ));
+    }
+    Ok(())
+}
+
+fn undo_move(file_system: &dyn FileSystem, planned_move: &PlannedMove) -> std::io::Result<()> {
+    let origin_path = planned_move.get_origin();
+    let destination_path = planned_move.get_destination();
+    // Already moved back by an earlier undo that failed later on
+    if !file_system.exists(destination_path)? && file_system.exists(origin_path)? {
+        return Ok(());
+    }
+    if file_system.exists(origin_path)? {
+        return Err(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists.", origin_path.display()),
+        ));
+    }
+    if let Some(parent) = origin_path.parent() {
+        create_dir_all(file_system, parent)?;
+    }
+    file_system.rename(destination_path, origin_path)
+}
+
+// Removes the directories the files of the journal were moved out of if
+// nothing else is left in them, going up towards the boundary. Junk files
+// do not keep a directory and go to trash through the filesystem. The
+// removals are added to the journal so undo brings them back.
+pub fn remove_empty_directories(
+    journal: &mut Journal,
+    options: &CleanupOptions,
+    trash: &Trash,
+) -> std::io::Result<()> {
+    remove_empty_directories_with(&RealFileSystem, journal, options, trash)
+}
+
+pub fn remove_empty_directories_with(
+    file_system: &dyn FileSystem,
+    journal: &mut Journal,
+    options: &CleanupOptions,
+    trash: &Trash,
+) -> std::io::Result<()> {
+    let boundary = options.get_boundary();
+    let mut directories = BTreeSet::new();
+    for planned_move in journal.moves.iter() {
+        for ancestor in planned_move.get_origin().ancestors().skip(1) {
+            if ancestor == boundary || !ancestor.starts_with(boundary) {
+                break;
+            }
+            directories.insert(PathBuf::from(ancestor));
+        }
+    }
+    // Deepest first, so parents are checked once their children are gone
+    let mut directories: Vec<PathBuf> = directories.into_iter().collect();
+    directories.sort_by_key(|directory| std::cmp::Reverse(directory.components().count()));
+    for directory in directories {
+        if !file_system.exists(&directory)? {
+            continue;
+        }
+        let mut junk_files = Vec::new();
+        let mut is_empty = true;
+        for entry in file_system.read_dir(&directory)? {
+            if is_junk_file(file_system, &entry, options)? {
+                junk_files.push(entry);
+            } else {
+                is_empty = false;
+                break;
+            }
+        }
+        if !is_empty {
+            continue;
+        }
+        for junk_file in junk_files {
+            journal
+                .trashed_files
+                .push(file_system.trash(trash, &junk_file)?);
+        }
+        file_system.remove(&directory)?;
+        journal.removed_directories.push(directory);
+    }
+    Ok(())
+}
+
+fn is_junk_file(
+    file_system: &dyn FileSystem,
+    path: &Path,
+    options: &CleanupOptions,
+) -> std::io::Result<bool> {
+    let is_junk_name = path.file_name().is_some_and(|file_name| {
+        options
+            .junk_file_names
+            .iter()
+            .any(|junk_file_name| file_name == junk_file_name.as_str())
+    });
+    Ok(is_junk_name && file_system.metadata(path)?.is_file())
+}
+
+// The originals stay in place, so the copies and links are removed. A copy
+// whose original has gone missing since is moved back instead so no data is
+// lost.
+fn undo_transfer(
+    file_system: &dyn FileSystem,
+    planned_move: &PlannedMove,
+    transfer_mode: TransferMode,
+) -> std::io::Result<()> {
+    let origin_path = planned_move.get_origin();
+    let destination_path = planned_move.get_destination();
+    if !file_system.exists(destination_path)? {
+        return Ok(());
+    }
+    if transfer_mode != TransferMode::Symlink && !file_system.exists(origin_path)? {
+        if let Some(parent) = origin_path.parent() {
+            create_dir_all(file_system, parent)?;
+        }
+        return file_system.rename(destination_path, origin_path);
+    }
+    file_system.remove(destination_path)
+}
+
+fn transfer_file(
+    file_system: &dyn FileSystem,
+    planned_move: &PlannedMove,
+    journal: &mut Journal,
+) -> std::io::Result<()> {
+    let destination_path = planned_move.get_destination();
+    if file_system.exists(destination_path)? {
+        return Err(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists.", destination_path.display()),
+        ));
+    }
+    create_missing_directories(file_system, destination_path, journal)?;
+    let origin_path = planned_move.get_origin();
+    match journal.transfer_mode {
+        TransferMode::Move => file_system.rename(origin_path, destination_path)?,
+        TransferMode::Copy => {
+            file_system.copy(origin_path, destination_path)?;
+        }
+        TransferMode::HardLink => file_system.hard_link(origin_path, destination_path)?,
+        TransferMode::Symlink => file_system.symlink(origin_path, destination_path)?,
+        TransferMode::Reflink => file_system.reflink(origin_path, destination_path)?,
+    }
+    journal.moves.push(planned_move.clone());
+    Ok(())
+}
+
+fn create_dir_all(file_system: &dyn FileSystem, path: &Path) -> std::io::Result<()> {
+    let mut search_path = PathBuf::new();
+    for component in path.components() {
+        search_path.push(component);
+        if !file_system.exists(&search_path)? {
+            file_system.create_dir(&search_path)?;
+        }
+    }
+    Ok(())
+}
+
+fn create_missing_directories(
+    file_system: &dyn FileSystem,
+    destination_path: &Path,
+    journal: &mut Journal,
+) -> std::io::Result<()> {
+    if let Some(parent) = destination_path.parent() {
+        let mut search_path = PathBuf::new();
+        for component in parent.components() {
+            search_path.push(component);
+            if !file_system.exists(&search_path)? {
+                file_system.create_dir(&search_path)?;
+                journal
+                    .created_directories
+                    .push(PathBuf::from(&search_path));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFileSystem;
+
+    fn create_test_file_system() -> MemoryFileSystem {
+        let file_system = MemoryFileSystem::new();
+        file_system.write_file(Path::new("/inbox/first.txt"), b"first");
+        file_system.write_file(Path::new("/inbox/second.txt"), b"second");
+        file_system.write_file(Path::new("/inbox/third.txt"), b"third");
+        file_system.write_file(Path::new("/organized/taken.txt"), b"taken");
+        file_system
+    }
+
+    fn create_test_plan(second_destination: &str) -> Plan {
+        Plan::new(vec![
+            PlannedMove::new(
+                PathBuf::from("/inbox/first.txt"),
+                PathBuf::from("/organized/txt/first.txt"),
+            ),
+            PlannedMove::new(
+                PathBuf::from("/inbox/second.txt"),
+                PathBuf::from(second_destination),
+            ),
+            PlannedMove::new(
+                PathBuf::from("/inbox/third.txt"),
+                PathBuf::from("/organized/txt/third.txt"),
+            ),
+        ])
+    }
+
+    fn assert_nothing_moved(file_system: &MemoryFileSystem) {
+        for (path, contents) in [
+            ("/inbox/first.txt", b"first".as_slice()),
+            ("/inbox/second.txt", b"second"),
+            ("/inbox/third.txt", b"third"),
+            ("/organized/taken.txt", b"taken"),
+        ] {
+            assert_eq!(
+                file_system
+                    .read_file(Path::new(path))
+                    .expect("File missing"),
+                contents
+            );
+        }
+        assert!(!file_system.exists(Path::new("/organized/txt")).unwrap());
+    }
+
+    #[test]
+    fn test_apply_and_undo() {
+        let file_system = create_test_file_system();
+        let plan = create_test_plan("/organized/txt/second.txt");
+        let journal = apply_with(&file_system, &plan, TransferMode::Move).expect("Failed to apply");
+        assert_eq!(journal.get_moves(), plan.get_moves());
+        assert_eq!(
+            journal.get_created_directories(),
+            &vec![PathBuf::from("/organized/txt")]
+        );
+        assert_eq!(
+            file_system
+                .read_file(Path::new("/organized/txt/second.txt"))
+                .expect("File missing"),
+            b"second"
+        );
+        assert!(!file_system.exists(Path::new("/inbox/second.txt")).unwrap());
+
+        undo_with(&file_system, &journal).expect("Failed to undo");
+        assert_nothing_moved(&file_system);
+        // Undoing again finds the files already restored
+        undo_with(&file_system, &journal).expect("Failed to undo twice");
+        assert_nothing_moved(&file_system);
+    }
+
+    #[test]
+    fn test_apply_rolls_back_on_failure() {
+        let file_system = create_test_file_system();
+        let plan = create_test_plan("/organized/taken.txt");
+        let error =
+            apply_with(&file_system, &plan, TransferMode::Move).expect_err("Apply should fail");
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_nothing_moved(&file_system);
+    }
+
+    #[test]
+    fn test_apply_rolls_back_on_permission_denied() {
+        let file_system = create_test_file_system();
+        file_system.inject_failure(Path::new("/inbox/second.txt"), ErrorKind::PermissionDenied);
+        let plan = create_test_plan("/organized/txt/second.txt");
+        let error =
+            apply_with(&file_system, &plan, TransferMode::Move).expect_err("Apply should fail");
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+        assert_nothing_moved(&file_system);
+    }
+
+    #[test]
+    fn test_apply_fails_when_directory_cannot_be_created() {
+        let file_system = create_test_file_system();
+        file_system.inject_failure(Path::new("/organized/txt"), ErrorKind::PermissionDenied);
+        let plan = create_test_plan("/organized/second.txt");
+        let error =
+            apply_with(&file_system, &plan, TransferMode::Move).expect_err("Apply should fail");
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+        assert_nothing_moved(&file_system);
+    }
+
+    #[test]
+    fn test_undo_does_not_overwrite_origin() {
+        let file_system = create_test_file_system();
+        let plan = create_test_plan("/organized/txt/second.txt");
+        let journal = apply_with(&file_system, &plan, TransferMode::Move).expect("Failed to apply");
+        file_system.write_file(Path::new("/inbox/first.txt"), b"new first");
+        let error = undo_with(&file_system, &journal).expect_err("Undo should fail");
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(
+            file_system
+                .read_file(Path::new("/inbox/first.txt"))
+                .expect("File missing"),
+            b"new first"
+        );
+        assert_eq!(
+            file_system
+                .read_file(Path::new("/organized/txt/first.txt"))
+                .expect("File missing"),
+            b"first"
+        );
+    }
+
+    #[test]
+    fn test_apply_and_undo_for_each_transfer_mode() {
+        for transfer_mode in [
+            TransferMode::Copy,
+            TransferMode::HardLink,
+            TransferMode::Symlink,
+            TransferMode::Reflink,
+        ] {
+            let file_system = create_test_file_system();
+            let plan = create_test_plan("/organized/txt/second.txt");
+            let journal = apply_with(&file_system, &plan, transfer_mode).expect("Failed to apply");
+            assert_eq!(journal.get_transfer_mode(), transfer_mode);
+            for planned_move in plan.get_moves() {
+                assert_eq!(
+                    file_system
+                        .read_file(planned_move.get_destination())
+                        .expect("Destination missing"),
+                    file_system
+                        .read_file(planned_move.get_origin())
+                        .expect("Original missing")
+                );
+            }
+            let metadata = file_system
+                .metadata(Path::new("/organized/txt/first.txt"))
+                .unwrap();
+            assert_eq!(
+                metadata.is_symlink(),
+                transfer_mode == TransferMode::Symlink
+            );
+
+            undo_with(&file_system, &journal).expect("Failed to undo");
+            assert_nothing_moved(&file_system);
+        }
+    }
+
+    #[test]
+    fn test_undo_copy_moves_back_when_original_is_gone() {
+        let file_system = create_test_file_system();
+        let plan = create_test_plan("/organized/txt/second.txt");
+        let journal = apply_with(&file_system, &plan, TransferMode::Copy).expect("Failed to apply");
+        file_system
+            .remove(Path::new("/inbox/first.txt"))
+            .expect("Failed to remove original");
+        undo_with(&file_system, &journal).expect("Failed to undo");
+        assert_nothing_moved(&file_system);
+    }
+
+    #[test]
+    fn test_apply_reports_progress() {
+        let file_system = create_test_file_system();
+        let plan = create_test_plan("/organized/txt/second.txt");
+        let mut reports = Vec::new();
+        apply_with_progress(
+            &file_system,
+            &plan,
+            TransferMode::Move,
+            &AtomicBool::new(false),
+            &mut |progress| reports.push(progress.clone()),
+        )
+        .expect("Failed to apply");
+        assert_eq!(reports.len(), 4);
+        assert_eq!(
+            reports[1].get_current_file(),
+            &Some(PathBuf::from("/inbox/second.txt"))
+        );
+        assert_eq!(reports[1].get_files_done(), 1);
+        assert_eq!(reports[1].get_bytes_done(), 5);
+        let last = reports.last().expect("No progress reported");
+        assert_eq!(last.get_files_done(), 3);
+        assert_eq!(last.get_bytes_total(), 16);
+        assert_eq!(last.get_bytes_done(), last.get_bytes_total());
+        assert_eq!(last.get_current_file(), &None);
+        assert_eq!(last.get_eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_cancel_rolls_back_between_files() {
+        let file_system = create_test_file_system();
+        let plan = create_test_plan("/organized/txt/second.txt");
+        let cancel = AtomicBool::new(false);
+        let error = apply_with_progress(
+            &file_system,
+            &plan,
+            TransferMode::Move,
+            &cancel,
+            &mut |progress| {
+                if progress.get_files_done() == 2 {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            },
+        )
+        .expect_err("Apply should be cancelled");
+        assert_eq!(error.kind(), ErrorKind::Interrupted);
+        assert_nothing_moved(&file_system);
+    }
+
+    #[test]
+    fn test_cancelled_undo_finishes_when_run_again() {
+        let file_system = create_test_file_system();
+        let plan = create_test_plan("/organized/txt/second.txt");
+        let journal = apply_with(&file_system, &plan, TransferMode::Move).expect("Failed to apply");
+        let cancel = AtomicBool::new(false);
+        let mut reports = Vec::new();
+        let error = undo_with_progress(&file_system, &journal, &cancel, &mut |progress| {
+            reports.push(progress.clone());
+            if progress.get_files_done() == 1 {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        })
+        .expect_err("Undo should be cancelled");
+        assert_eq!(error.kind(), ErrorKind::Interrupted);
+        assert_eq!(reports[0].get_files_total(), 3);
+        assert_eq!(
+            reports[0].get_current_file(),
+            &Some(PathBuf::from("/inbox/third.txt"))
+        );
+        assert!(file_system.exists(Path::new("/inbox/third.txt")).unwrap());
+        assert!(!file_system.exists(Path::new("/inbox/second.txt")).unwrap());
+
+        undo_with(&file_system, &journal).expect("Failed to finish undo");
+        assert_nothing_moved(&file_system);
+    }
+
+    #[test]
+    fn test_progress_eta() {
+        let progress = Progress {
+            files_done: 1,
+            files_total: 4,
+            bytes_done: 100,
+            bytes_total: 400,
+            current_file: None,
+            elapsed: Duration::from_secs(10),
+        };
+        assert_eq!(progress.get_fraction_done(), 0.25);
+        assert_eq!(progress.get_eta(), Some(Duration::from_secs(30)));
+        let progress = Progress {
+            bytes_done: 0,
+            ..progress
+        };
+        assert_eq!(progress.get_eta(), None);
+    }
+
+    #[test]
+    fn test_remove_empty_directories() {
+        let file_system = MemoryFileSystem::new();
+        file_system.write_file(Path::new("/camera/DCIM/100/a.jpg"), b"a");
+        file_system.write_file(Path::new("/camera/DCIM/101/b.jpg"), b"b");
+        file_system.write_file(Path::new("/camera/DCIM/101/keep.txt"), b"keep");
+        file_system.write_file(Path::new("/camera/DCIM/102/deep/c.jpg"), b"c");
+        let plan = Plan::new(vec![
+            PlannedMove::new(
+                PathBuf::from("/camera/DCIM/100/a.jpg"),
+                PathBuf::from("/photos/a.jpg"),
+            ),
+            PlannedMove::new(
+                PathBuf::from("/camera/DCIM/101/b.jpg"),
+                PathBuf::from("/photos/b.jpg"),
+            ),
+            PlannedMove::new(
+                PathBuf::from("/camera/DCIM/102/deep/c.jpg"),
+                PathBuf::from("/photos/c.jpg"),
+            ),
+        ]);
+        let mut journal =
+            apply_with(&file_system, &plan, TransferMode::Move).expect("Failed to apply");
+        let options = CleanupOptions::new(PathBuf::from("/camera/DCIM"), Vec::new());
+        remove_empty_directories_with(
+            &file_system,
+            &mut journal,
+            &options,
+            &Trash::new(Vec::new()),
+        )
+        .expect("Failed to clean up");
+        assert_eq!(
+            journal.get_removed_directories(),
+            &vec![
+                PathBuf::from("/camera/DCIM/102/deep"),
+                PathBuf::from("/camera/DCIM/100"),
+                PathBuf::from("/camera/DCIM/102"),
+            ]
+        );
+        assert!(file_system.exists(Path::new("/camera/DCIM")).unwrap());
+        assert!(file_system.exists(Path::new("/camera/DCIM/101")).unwrap());
+
+        undo_with(&file_system, &journal).expect("Failed to undo");
+        for path in [
+            "/camera/DCIM/100/a.jpg",
+            "/camera/DCIM/101/b.jpg",
+            "/camera/DCIM/102/deep/c.jpg",
+        ] {
+            assert!(file_system.exists(Path::new(path)).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_remove_empty_directories_trashes_junk_files() {
+        let file_system = MemoryFileSystem::new();
+        file_system.write_file(Path::new("/inbox/trip/photo.jpg"), b"photo");
+        file_system.write_file(Path::new("/inbox/trip/.DS_Store"), b"junk");
+        file_system.create_dir_all(Path::new("/photos"));
+        let plan = Plan::new(vec![PlannedMove::new(
+            PathBuf::from("/inbox/trip/photo.jpg"),
+            PathBuf::from("/photos/photo.jpg"),
+        )]);
+        let mut journal =
+            apply_with(&file_system, &plan, TransferMode::Move).expect("Failed to apply");
+        let trash = Trash::new(vec![PathBuf::from("/trash")]);
+
+        let options = CleanupOptions::new(PathBuf::from("/inbox"), Vec::new());
+        remove_empty_directories_with(&file_system, &mut journal, &options, &trash)
+            .expect("Failed to clean up");
+        assert!(file_system.exists(Path::new("/inbox/trip")).unwrap());
+
+        let junk_file_names = DEFAULT_JUNK_FILE_NAMES.map(String::from).to_vec();
+        let options = CleanupOptions::new(PathBuf::from("/inbox"), junk_file_names);
+        remove_empty_directories_with(&file_system, &mut journal, &options, &trash)
+            .expect("Failed to clean up");
+        assert!(!file_system.exists(Path::new("/inbox/trip")).unwrap());
+        assert!(file_system.exists(Path::new("/inbox")).unwrap());
+        assert_eq!(journal.get_trashed_files().len(), 1);
+        assert!(file_system
+            .exists(Path::new("/trash/files/.DS_Store"))
+            .unwrap());
+
+        undo_with(&file_system, &journal).expect("Failed to undo");
+        assert_eq!(
+            file_system
+                .read_file(Path::new("/inbox/trip/.DS_Store"))
+                .expect("Junk file missing"),
+            b"junk"
+        );
+        assert!(file_system
+            .exists(Path::new("/inbox/trip/photo.jpg"))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_parse_transfer_mode() {
+        for transfer_mode in TRANSFER_MODES {
+            assert_eq!(
+                TransferMode::parse(&transfer_mode.to_string()),
+                Some(transfer_mode)
+            );
+            assert_eq!(
+                TransferMode::parse(transfer_mode.get_argument_name()),
+                Some(transfer_mode)
+            );
+        }
+        assert_eq!(
+            TransferMode::parse("hardlink"),
+            Some(TransferMode::HardLink)
+        );
+        assert_eq!(
+            TransferMode::parse("symlink"),
+            Some(TransferMode::Symlink)
+        );
+        assert_eq!(TransferMode::parse("teleport"), None);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::organize_files;
+use crate::plan::{Plan, PlannedMove};
+use crate::sanitize;
+use crate::vfs::{FileSystem, RealFileSystem};
+use chrono::NaiveDate;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+// Plans moving every file below directory into target, reversing what
+// organizing did. Files already directly in target stay where they are and
+// names that are taken get a _N suffix. With strip_prefixes the date and
+// index prefixes of the rename rules are removed from the names, except from
+// names that are not valid Unicode, which are kept as they are.
+pub fn plan_flatten(
+    directory: &Path,
+    target: &Path,
+    strip_prefixes: bool,
+) -> std::io::Result<Plan> {
+    plan_flatten_with(&RealFileSystem, directory, target, strip_prefixes)
+}
+
+pub fn plan_flatten_with(
+    file_system: &dyn FileSystem,
+    directory: &Path,
+    target: &Path,
+    strip_prefixes: bool,
+) -> std::io::Result<Plan> {
+    if !file_system.metadata(directory)?.is_dir() {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "Only directories can be flattened.",
+        ));
+    }
+    let mut files = Vec::new();
+    collect_files(file_system, directory, target, &mut files)?;
+
+    let mut destinations = HashSet::new();
+    let mut moves = Vec::new();
+    for origin in files {
+        let Some(file_name) = origin.file_name() else {
+            continue;
+        };
+        let file_name = match (strip_prefixes, file_name.to_str()) {
+            (true, Some(file_name)) => OsString::from(strip_rename_prefixes(file_name)),
+            _ => OsString::from(file_name),
+        };
+        let mut destination = target.join(&file_name);
+        let mut suffix = 1;
+        while destinations.contains(&destination) || file_system.exists(&destination)? {
+            destination.set_file_name(sanitize::add_os_suffix(&file_name, suffix));
+            suffix += 1;
+        }
+        destinations.insert(destination.clone());
+        moves.push(PlannedMove::new(origin, destination));
+    }
+    Ok(Plan::new(moves))
+}
+
+// Files of each directory before its subdirectories, in sorted order, so the same tree always gets
+// the same suffixes. The target is not descended into.
+fn collect_files(
+    file_system: &dyn FileSystem,
+    directory: &Path,
+    target: &Path,
+    files: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    let mut entries = file_system.read_dir(directory)?;
+    entries.sort();
+    let mut subdirectories = Vec::new();
+    for entry in entries {
+        if file_system.metadata(&entry)?.is_dir() {
+            if entry != target {
+                subdirectories.push(entry);
+            }
+        } else if directory != target {
+            files.push(entry);
+        }
+    }
+    for subdirectory in subdirectories {
+        collect_files(file_system, &subdirectory, target, files)?;
+    }
+    Ok(())
+}
+
+// Removes the date and index prefixes rename rules put in front of names,
+// so 20250831_01_photo.jpg becomes photo.jpg. A prefix is kept if nothing
+// but the file type would be left.
+pub fn strip_rename_prefixes(file_name: &str) -> &str {
+    let mut stripped = file_name;
+    while let Some((prefix, rest)) = stripped.split_once('_') {
+        if !is_rename_prefix(prefix)
+            || rest.starts_with('.')
+            || organize_files::get_file_name_without_file_type(rest).is_empty()
+        {
+            break;
+        }
+        stripped = rest;
+    }
+    stripped
+}
+
+fn is_rename_prefix(prefix: &str) -> bool {
+    if prefix.is_empty() || !prefix.chars().all(|character| character.is_ascii_digit()) {
+        return false;
+    }
+    let is_date = prefix.len() == 8 && NaiveDate::parse_from_str(prefix, "%Y%m%d").is_ok();
+    let is_index = prefix.len() > 1 && prefix.starts_with('0');
+    is_date || is_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::{self, TransferMode};
+    use crate::vfs::MemoryFileSystem;
+
+    #[test]
+    fn test_strip_rename_prefixes() {
+        assert_eq!(strip_rename_prefixes("20250831_01_photo.jpg"), "photo.jpg");
+        assert_eq!(
+            strip_rename_prefixes("01_holiday_photo.jpg"),
+            "holiday_photo.jpg"
+        );
+        assert_eq!(
+            strip_rename_prefixes("20250831_photo_01.jpg"),
+            "photo_01.jpg"
+        );
+        assert_eq!(
+            strip_rename_prefixes("20251399_photo.jpg"),
+            "20251399_photo.jpg"
+        );
+        assert_eq!(strip_rename_prefixes("1_photo.jpg"), "1_photo.jpg");
+        assert_eq!(strip_rename_prefixes("20250831_.jpg"), "20250831_.jpg");
+        assert_eq!(strip_rename_prefixes("notes.txt"), "notes.txt");
+    }
+
+    #[test]
+    fn test_flatten_organized_directory() {
+        let file_system = MemoryFileSystem::new();
+        let organized = PathBuf::from("/home/photos");
+        file_system.write_file(&organized.join("kept.jpg"), b"kept");
+        file_system.write_file(&organized.join("jpg/20250831/20250831_01_a.jpg"), b"a");
+        file_system.write_file(
+            &organized.join("jpg/20250901/20250901_01_a.jpg"),
+            b"other a",
+        );
+        file_system.write_file(&organized.join("jpg/20250901/20250901_kept.jpg"), b"b");
+        file_system.write_file(&organized.join("txt/notes.txt"), b"notes");
+
+        let plan =
+            plan_flatten_with(&file_system, &organized, &organized, true).expect("Failed to plan");
+        let moves: Vec<(&Path, &Path)> = plan
+            .get_moves()
+            .iter()
+            .map(|planned_move| {
+                (
+                    planned_move.get_origin().strip_prefix(&organized).unwrap(),
+                    planned_move
+                        .get_destination()
+                        .strip_prefix(&organized)
+                        .unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            moves,
+            vec![
+                (
+                    Path::new("jpg/20250831/20250831_01_a.jpg"),
+                    Path::new("a.jpg")
+                ),
+                (
+                    Path::new("jpg/20250901/20250901_01_a.jpg"),
+                    Path::new("a_1.jpg")
+                ),
+                (
+                    Path::new("jpg/20250901/20250901_kept.jpg"),
+                    Path::new("kept_1.jpg")
+                ),
+                (Path::new("txt/notes.txt"), Path::new("notes.txt")),
+            ]
+        );
+
+        let journal = filesystem::apply_with(&file_system, &plan, TransferMode::Move)
+            .expect("Failed to apply");
+        assert_eq!(
+            file_system.read_file(&organized.join("a_1.jpg")).unwrap(),
+            b"other a"
+        );
+        filesystem::undo_with(&file_system, &journal).expect("Failed to undo");
+        assert!(file_system
+            .exists(&organized.join("jpg/20250901/20250901_01_a.jpg"))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_flatten_into_subdirectory_keeps_names() {
+        let file_system = MemoryFileSystem::new();
+        let organized = PathBuf::from("/home/photos");
+        let target = organized.join("all");
+        file_system.write_file(&organized.join("top.jpg"), b"top");
+        file_system.write_file(&organized.join("jpg/20250831_01_a.jpg"), b"a");
+        file_system.write_file(&target.join("b.jpg"), b"b");
+
+        let plan =
+            plan_flatten_with(&file_system, &organized, &target, false).expect("Failed to plan");
+        assert_eq!(
+            plan.get_moves(),
+            &vec![
+                PlannedMove::new(organized.join("top.jpg"), target.join("top.jpg")),
+                PlannedMove::new(
+                    organized.join("jpg/20250831_01_a.jpg"),
+                    target.join("20250831_01_a.jpg")
+                ),
+            ]
+        );
+        let error = plan_flatten_with(&file_system, &organized.join("top.jpg"), &target, false)
+            .expect_err("Files cannot be flattened");
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_flatten_keeps_non_unicode_names() {
+        use std::os::unix::ffi::OsStrExt;
+        let file_system = MemoryFileSystem::new();
+        let organized = PathBuf::from("/home/photos");
+        let name = std::ffi::OsStr::from_bytes(b"20250831_caf\xe9.jpg");
+        file_system.write_file(&organized.join(name), b"a");
+        file_system.write_file(&organized.join("jpg").join(name), b"b");
+
+        let plan =
+            plan_flatten_with(&file_system, &organized, &organized, true).expect("Failed to plan");
+        let mut suffixed_name = OsString::from(name);
+        suffixed_name.push("_1");
+        assert_eq!(
+            plan.get_moves(),
+            &vec![PlannedMove::new(
+                organized.join("jpg").join(name),
+                organized.join(suffixed_name)
+            )]
+        );
+    }
+}
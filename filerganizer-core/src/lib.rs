@@ -0,0 +1,26 @@
+// Organizing engine of Filerganizer, independent of the user interface.
+// Files are scanned, planned by the rules of an organized directory, applied
+// to the filesystem and undone with the journal that apply returns. The
+// _with variants take the FileSystem to work on.
+pub mod directory;
+pub mod file;
+pub mod filesystem;
+pub mod flatten;
+pub mod metadata;
+pub mod organize_files;
+pub mod plan;
+pub mod rules;
+pub mod sanitize;
+pub mod save_directory;
+pub mod trash;
+pub mod util;
+pub mod vfs;
+
+pub use filesystem::{apply, apply_with, apply_with_progress, undo, undo_with, undo_with_progress};
+pub use filesystem::{CleanupOptions, Journal, Progress, TransferMode};
+pub use plan::{
+    plan, plan_reapply, plan_reapply_with, plan_with, scan, scan_with, Plan, PlannedMove,
+};
+pub use rules::Rules;
+pub use trash::{Trash, TrashedItem};
+pub use vfs::{FileSystem, MemoryFileSystem, RealFileSystem};
@@ -0,0 +1,626 @@
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::SystemTime,
+};
+
+const DATE_FORMAT: &str = "%Y%m%d";
+pub const EXIF_FILE_TYPES: [&str; 8] =
+    ["jpg", "jpeg", "tif", "tiff", "heic", "heif", "png", "webp"];
+
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    name: Option<OsString>,
+    created: Option<DateTime<Local>>,
+    accessed: Option<DateTime<Local>>,
+    modified: Option<DateTime<Local>>,
+    size: Option<f64>,
+    readonly: bool,
+    origin_path: Option<PathBuf>,
+    destination_path: Option<PathBuf>,
+    // Read from the file the first time a date is formatted, since parsing
+    // EXIF on every directory listing is slow. Clones share it, so an offset
+    // read while previewing a clone is not read again.
+    exif_offset: Arc<OnceLock<Option<FixedOffset>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateType {
+    Created,
+    Accessed,
+    Modified,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Timezone {
+    #[default]
+    Local,
+    Utc,
+    FixedOffset(FixedOffset),
+    Named(chrono_tz::Tz),
+}
+
+impl std::fmt::Display for Timezone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Timezone::Local => f.write_str("Local"),
+            Timezone::Utc => f.write_str("UTC"),
+            Timezone::FixedOffset(offset) => write!(f, "{}", offset),
+            Timezone::Named(timezone) => f.write_str(timezone.name()),
+        }
+    }
+}
+
+impl Timezone {
+    // Accepts "Local", "UTC", offsets like "+02:00" or "-0530" and IANA names
+    // like "Europe/Helsinki".
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if input.is_empty() || input.eq_ignore_ascii_case("local") {
+            return Some(Timezone::Local);
+        }
+        if input.eq_ignore_ascii_case("utc") || input == "Z" {
+            return Some(Timezone::Utc);
+        }
+        if let Some(offset) = parse_offset(input) {
+            return Some(Timezone::FixedOffset(offset));
+        }
+        match input.parse::<chrono_tz::Tz>() {
+            Ok(timezone) => Some(Timezone::Named(timezone)),
+            Err(_) => None,
+        }
+    }
+
+    pub fn format_date(&self, date: &DateTime<Local>) -> String {
+        match self {
+            Timezone::Local => date.format(DATE_FORMAT).to_string(),
+            Timezone::Utc => date.with_timezone(&Utc).format(DATE_FORMAT).to_string(),
+            Timezone::FixedOffset(offset) => {
+                date.with_timezone(offset).format(DATE_FORMAT).to_string()
+            }
+            Timezone::Named(timezone) => {
+                date.with_timezone(timezone).format(DATE_FORMAT).to_string()
+            }
+        }
+    }
+}
+
+// Parses offsets in the form used by EXIF OffsetTime tags, "+HH:MM", and
+// the same without the colon.
+pub fn parse_offset(input: &str) -> Option<FixedOffset> {
+    let input = input.trim_end_matches('\0').trim();
+    let (sign, rest) = match input.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+pub fn is_exif_file(file_name: &str) -> bool {
+    match file_name.rsplit_once('.') {
+        Some((_, file_type)) => EXIF_FILE_TYPES.contains(&file_type.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+// Reads the offset the camera recorded next to its capture time. Files
+// without EXIF data or offset tags return None.
+pub fn read_exif_offset(path: &Path) -> Option<FixedOffset> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut reader = std::io::BufReader::new(file);
+    let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+    for tag in [
+        exif::Tag::OffsetTimeOriginal,
+        exif::Tag::OffsetTime,
+        exif::Tag::OffsetTimeDigitized,
+    ] {
+        if let Some(field) = exif.get_field(tag, exif::In::PRIMARY) {
+            if let exif::Value::Ascii(values) = &field.value {
+                for value in values {
+                    if let Some(offset) = std::str::from_utf8(value).ok().and_then(parse_offset) {
+                        return Some(offset);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+// Every field of the main image as tag name and displayed value, for
+// showing to the user. Files without EXIF data return no fields.
+pub fn read_exif_fields(path: &Path) -> Vec<(String, String)> {
+    let Ok(file) = std::fs::File::open(path) else {
+        return Vec::new();
+    };
+    let mut reader = std::io::BufReader::new(file);
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut reader) else {
+        return Vec::new();
+    };
+    exif.fields()
+        .filter(|field| field.ifd_num == exif::In::PRIMARY)
+        .map(|field| {
+            let value = field.display_value().with_unit(&exif).to_string();
+            (field.tag.to_string(), value)
+        })
+        .collect()
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            created: None,
+            accessed: None,
+            modified: None,
+            size: None,
+            readonly: false,
+            origin_path: None,
+            destination_path: None,
+            exif_offset: Arc::new(OnceLock::new()),
+        }
+    }
+
+    // Many Linux filesystems do not record a creation time, in which case
+    // created dates fall back to the modification time. With the local
+    // timezone, created and modified dates of photos use the EXIF offset of
+    // the place they were taken in, as the file times of a photo copied from
+    // a camera are close to its capture time. A timezone chosen for the rule
+    // is always kept.
+    pub fn get_formatted_date(&self, date_type: DateType, timezone: Timezone) -> Option<String> {
+        let date = match date_type {
+            DateType::Created => self.created.or(self.modified),
+            DateType::Accessed => self.accessed,
+            DateType::Modified => self.modified,
+        }?;
+        if timezone == Timezone::Local && date_type != DateType::Accessed {
+            if let Some(offset) = self.get_exif_offset() {
+                return Some(date.with_timezone(&offset).format(DATE_FORMAT).to_string());
+            }
+        }
+        Some(timezone.format_date(&date))
+    }
+
+    pub fn set_exif_offset(&mut self, exif_offset: Option<FixedOffset>) {
+        self.exif_offset = Arc::new(OnceLock::from(exif_offset));
+    }
+
+    fn get_exif_offset(&self) -> Option<FixedOffset> {
+        *self.exif_offset.get_or_init(|| {
+            let origin_path = self.origin_path.as_ref()?;
+            let file_name = origin_path.file_name()?.to_str()?;
+            match is_exif_file(file_name) {
+                true => read_exif_offset(origin_path),
+                false => None,
+            }
+        })
+    }
+
+    pub fn set_destination_path(&mut self, destination_path: PathBuf) {
+        self.destination_path = Some(destination_path);
+    }
+
+    pub fn get_name(&self) -> Option<OsString> {
+        self.name.clone()
+    }
+
+    pub fn get_created(&self) -> Option<DateTime<Local>> {
+        self.created
+    }
+
+    pub fn get_accessed(&self) -> Option<DateTime<Local>> {
+        self.accessed
+    }
+
+    pub fn get_modified(&self) -> Option<DateTime<Local>> {
+        self.modified
+    }
+
+    pub fn get_size(&self) -> Option<f64> {
+        self.size
+    }
+
+    pub fn get_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    pub fn get_origin_path(&self) -> Option<PathBuf> {
+        self.origin_path.clone()
+    }
+
+    pub fn get_destination_path(&self) -> Option<PathBuf> {
+        self.destination_path.clone()
+    }
+
+    pub fn build(
+        name: Option<OsString>,
+        created: Option<SystemTime>,
+        accessed: Option<SystemTime>,
+        modified: Option<SystemTime>,
+        size: Option<f64>,
+        readonly: bool,
+        origin_path: Option<PathBuf>,
+        destination_path: Option<PathBuf>,
+    ) -> Self {
+        Self::convert_metadata_to_datetime(
+            name,
+            created,
+            accessed,
+            modified,
+            size,
+            readonly,
+            origin_path,
+            destination_path,
+        )
+    }
+
+    fn convert_metadata_to_datetime(
+        name: Option<OsString>,
+        created: Option<SystemTime>,
+        accessed: Option<SystemTime>,
+        modified: Option<SystemTime>,
+        size: Option<f64>,
+        readonly: bool,
+        origin_path: Option<PathBuf>,
+        destination_path: Option<PathBuf>,
+    ) -> Self {
+        let mut metadata = Self::new();
+        metadata.name = name;
+        if let Some(c) = created {
+            metadata.created = Some(c.into());
+        }
+        if let Some(a) = accessed {
+            metadata.accessed = Some(a.into());
+        }
+        if let Some(m) = modified {
+            metadata.modified = Some(m.into());
+        }
+        metadata.size = size;
+        metadata.readonly = readonly;
+        metadata.origin_path = origin_path;
+        metadata.destination_path = destination_path;
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory::Directory;
+    use crate::file::File;
+    use crate::organize_files::{self, RenameData, SortData};
+    use crate::rules::CheckboxStates;
+    use crate::rules::FilenameComponents;
+    use std::collections::BTreeMap;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // Noon UTC; the expected dates are formatted in the local time zone, where
+    // the day can differ from the one noted below (UTC+12 and east).
+    const CREATED: u64 = 1_579_089_600; // 2020-01-15
+    const ACCESSED: u64 = 1_613_476_800; // 2021-02-16
+    const MODIFIED: u64 = 1_647_518_400; // 2022-03-17
+    const DATE_TYPES: [DateType; 3] = [DateType::Created, DateType::Accessed, DateType::Modified];
+
+    fn system_time(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    fn expected_date(date_type: DateType) -> String {
+        let seconds = match date_type {
+            DateType::Created => CREATED,
+            DateType::Accessed => ACCESSED,
+            DateType::Modified => MODIFIED,
+        };
+        DateTime::<Local>::from(system_time(seconds))
+            .format("%Y%m%d")
+            .to_string()
+    }
+
+    fn create_file(name: &str, created: Option<SystemTime>) -> File {
+        File::new(Metadata::build(
+            Some(OsString::from(name)),
+            created,
+            Some(system_time(ACCESSED)),
+            Some(system_time(MODIFIED)),
+            Some(10.0),
+            false,
+            Some(PathBuf::from(name)),
+            None,
+        ))
+    }
+
+    fn create_files_selected() -> BTreeMap<OsString, File> {
+        let mut files_selected = BTreeMap::new();
+        files_selected.insert(
+            OsString::from("photo.jpg"),
+            create_file("photo.jpg", Some(system_time(CREATED))),
+        );
+        files_selected
+    }
+
+    #[test]
+    fn test_get_formatted_date_for_each_date_type() {
+        let file = create_file("photo.jpg", Some(system_time(CREATED)));
+        let metadata = file.get_metadata().as_ref().expect("No metadata");
+        for date_type in DATE_TYPES {
+            assert_eq!(
+                metadata.get_formatted_date(date_type, Timezone::Local),
+                Some(expected_date(date_type))
+            );
+        }
+    }
+
+    #[test]
+    fn test_created_falls_back_to_modified() {
+        let file = create_file("photo.jpg", None);
+        let metadata = file.get_metadata().as_ref().expect("No metadata");
+        assert_eq!(metadata.get_created(), None);
+        assert_eq!(
+            metadata.get_formatted_date(DateType::Created, Timezone::Local),
+            Some(expected_date(DateType::Modified))
+        );
+        assert_eq!(
+            Metadata::new().get_formatted_date(DateType::Created, Timezone::Local),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(Timezone::parse(""), Some(Timezone::Local));
+        assert_eq!(Timezone::parse("utc"), Some(Timezone::Utc));
+        assert_eq!(
+            Timezone::parse("+05:30"),
+            FixedOffset::east_opt(19_800).map(Timezone::FixedOffset)
+        );
+        assert_eq!(
+            Timezone::parse("-0800"),
+            FixedOffset::west_opt(28_800).map(Timezone::FixedOffset)
+        );
+        assert_eq!(
+            Timezone::parse("Europe/Helsinki"),
+            Some(Timezone::Named(chrono_tz::Europe::Helsinki))
+        );
+        assert_eq!(Timezone::parse("Mars/Olympus"), None);
+        assert_eq!(Timezone::parse("+25:00"), None);
+        for timezone in ["Local", "UTC", "+05:30", "Asia/Tokyo"] {
+            let parsed = Timezone::parse(timezone).expect("Unknown timezone");
+            assert_eq!(Timezone::parse(&parsed.to_string()), Some(parsed));
+        }
+    }
+
+    #[test]
+    fn test_get_formatted_date_in_timezone() {
+        // 2022-03-17 23:30 UTC is already the next day east of UTC
+        let late_evening = system_time(1_647_559_800);
+        let metadata = Metadata::build(
+            Some(OsString::from("photo.jpg")),
+            Some(late_evening),
+            Some(late_evening),
+            Some(late_evening),
+            None,
+            false,
+            None,
+            None,
+        );
+        let expected = [
+            ("UTC", "20220317"),
+            ("+02:00", "20220318"),
+            ("-05:00", "20220317"),
+            ("Asia/Tokyo", "20220318"),
+            ("America/New_York", "20220317"),
+        ];
+        for (timezone, date) in expected {
+            let timezone = Timezone::parse(timezone).expect("Unknown timezone");
+            assert_eq!(
+                metadata.get_formatted_date(DateType::Modified, timezone),
+                Some(String::from(date))
+            );
+        }
+    }
+
+    #[test]
+    fn test_exif_offset_replaces_local_timezone() {
+        let late_evening = system_time(1_647_559_800);
+        let mut metadata = Metadata::build(
+            Some(OsString::from("photo.jpg")),
+            Some(late_evening),
+            Some(late_evening),
+            Some(late_evening),
+            None,
+            false,
+            None,
+            None,
+        );
+        metadata.set_exif_offset(parse_offset("+09:00\0"));
+        assert_eq!(
+            metadata.get_formatted_date(DateType::Created, Timezone::Local),
+            Some(String::from("20220318"))
+        );
+        assert_eq!(
+            metadata.get_formatted_date(DateType::Modified, Timezone::Local),
+            Some(String::from("20220318"))
+        );
+        assert_eq!(
+            metadata.get_formatted_date(DateType::Accessed, Timezone::Local),
+            Some(Timezone::Local.format_date(&DateTime::<Local>::from(late_evening)))
+        );
+        // A timezone chosen for the rule is not replaced by the EXIF offset.
+        assert_eq!(
+            metadata.get_formatted_date(DateType::Created, Timezone::Utc),
+            Some(String::from("20220317"))
+        );
+        assert_eq!(
+            metadata.get_formatted_date(DateType::Modified, Timezone::Utc),
+            Some(String::from("20220317"))
+        );
+        assert!(is_exif_file("IMG_0001.JPG"));
+        assert!(!is_exif_file("notes.txt"));
+    }
+
+    #[test]
+    fn test_read_exif_fields() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let path = test_directory.path().join("photo.jpg");
+        // JPEG with an APP1 segment holding one Make tag
+        let mut tiff = b"II*\0\x08\0\0\0\x01\0".to_vec();
+        tiff.extend_from_slice(b"\x0f\x01\x02\0\x04\0\0\0abc\0\0\0\0\0");
+        let mut jpeg = b"\xff\xd8\xff\xe1".to_vec();
+        jpeg.extend_from_slice(&((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend_from_slice(b"Exif\0\0");
+        jpeg.extend_from_slice(&tiff);
+        jpeg.extend_from_slice(b"\xff\xd9");
+        std::fs::write(&path, jpeg).expect("Failed to write test file");
+
+        let fields = read_exif_fields(&path);
+        assert_eq!(
+            fields,
+            vec![(String::from("Make"), String::from("\"abc\""))]
+        );
+        assert!(read_exif_fields(Path::new("/nonexistent/photo.jpg")).is_empty());
+    }
+
+    #[test]
+    fn test_read_timestamps_from_disk() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let directory_path = test_directory.path().to_path_buf();
+        let path_to_file = directory_path.join("photo.jpg");
+        std::fs::write(&path_to_file, b"photo").expect("Failed to write test file");
+        filetime::set_file_times(
+            &path_to_file,
+            filetime::FileTime::from_system_time(system_time(ACCESSED)),
+            filetime::FileTime::from_system_time(system_time(MODIFIED)),
+        )
+        .expect("Failed to set file times");
+
+        let mut directory = Directory::new(None);
+        Directory::new(None)
+            .read_path(&directory_path, &mut directory)
+            .expect("Failed to read test directory");
+        let files = directory.get_files().as_ref().expect("No files read");
+        let file = files
+            .get(&OsString::from("photo.jpg"))
+            .expect("File not read");
+        let metadata = file.get_metadata().as_ref().expect("No metadata");
+        assert_eq!(
+            metadata.get_formatted_date(DateType::Accessed, Timezone::Local),
+            Some(expected_date(DateType::Accessed))
+        );
+        assert_eq!(
+            metadata.get_formatted_date(DateType::Modified, Timezone::Local),
+            Some(expected_date(DateType::Modified))
+        );
+        assert!(metadata
+            .get_formatted_date(DateType::Created, Timezone::Local)
+            .is_some());
+    }
+
+    #[test]
+    fn test_create_file_dates_for_each_date_type() {
+        let files_selected = create_files_selected();
+        for date_type in DATE_TYPES {
+            let file_dates =
+                organize_files::create_file_dates(&files_selected, date_type, Timezone::Local);
+            let keys: Vec<&OsString> = file_dates.keys().collect();
+            assert_eq!(keys, vec![&OsString::from(expected_date(date_type))]);
+        }
+    }
+
+    #[test]
+    fn test_sort_files_by_date_for_each_date_type() {
+        let checkbox_states = CheckboxStates::default();
+        let order = vec![FilenameComponents::OriginalFilename];
+        let path = PathBuf::from("/organized");
+        for date_type in DATE_TYPES {
+            let files_selected = create_files_selected();
+            let mut date_directories =
+                organize_files::create_file_dates(&files_selected, date_type, Timezone::Local);
+            let mut files_organized = BTreeMap::new();
+            organize_files::sort_files_by_date(SortData::build(
+                &path,
+                &mut files_organized,
+                files_selected,
+                &mut date_directories,
+                &checkbox_states,
+                &Vec::new(),
+                "photos",
+                "",
+                &order,
+                Some(date_type),
+                Timezone::Local,
+                None,
+                &BTreeMap::new(),
+                true,
+                true,
+            ))
+            .expect("Failed to sort files by date");
+            let date_directory = date_directories
+                .get(&OsString::from(expected_date(date_type)))
+                .expect("Date directory not found");
+            assert_eq!(date_directory.get_file_count(), 1);
+            let file = files_organized
+                .get(&OsString::from("photo.jpg"))
+                .expect("File was not organized");
+            let destination = file
+                .get_metadata()
+                .as_ref()
+                .and_then(|metadata| metadata.get_destination_path());
+            let mut expected_destination = PathBuf::from("/organized/photos");
+            expected_destination.push(expected_date(date_type));
+            expected_destination.push("photo.jpg");
+            assert_eq!(destination, Some(expected_destination));
+        }
+    }
+
+    #[test]
+    fn test_rename_file_name_for_each_date_type() {
+        let checkbox_states = CheckboxStates {
+            insert_date_to_file_name: true,
+            ..Default::default()
+        };
+        let order = vec![
+            FilenameComponents::Date,
+            FilenameComponents::OriginalFilename,
+        ];
+        let file = create_file("photo.jpg", Some(system_time(CREATED)));
+        for date_type in DATE_TYPES {
+            let mut renamed_file_name = String::new();
+            organize_files::rename_file_name(RenameData::build(
+                &mut renamed_file_name,
+                &checkbox_states,
+                &Vec::new(),
+                "photos",
+                "",
+                0,
+                &order,
+                "photo.jpg",
+                &file,
+                Some(date_type),
+                Timezone::Local,
+                None,
+            ));
+            assert_eq!(
+                renamed_file_name,
+                format!("{}_photo.jpg", expected_date(date_type))
+            );
+        }
+    }
+}
@@ -1,14 +1,18 @@
-use crate::app::{FilenameComponents, ReplacableSelection};
-use crate::app_util;
 use crate::directory::Directory;
 use crate::file::File;
-use crate::layouts::{CheckboxStates, IndexPosition, ReplaceWith, Replaceable};
-use crate::metadata::DateType;
+use crate::metadata::{DateType, Timezone};
+use crate::rules::{CheckboxStates, IndexPosition, ReplaceWith, Replaceable};
+use crate::rules::{FilenameComponents, ReplacableSelection};
+use crate::util;
 use std::collections::BTreeMap;
 use std::ffi::OsString;
 use std::io::ErrorKind;
 use std::path::PathBuf;
 
+// Used when the rules leave nothing of the file name before the file type,
+// or only "." or "..".
+pub const FALLBACK_FILE_NAME: &str = "file";
+
 #[derive(Debug, Clone)]
 pub struct OrganizingData<'a> {
     files_selected: BTreeMap<OsString, File>,
@@ -18,7 +22,9 @@ pub struct OrganizingData<'a> {
     custom_file_name: &'a str,
     file_name_component_order: &'a Vec<FilenameComponents>,
     date_type: Option<DateType>,
+    timezone: Timezone,
     index_position: Option<IndexPosition>,
+    rename_overrides: &'a BTreeMap<OsString, String>,
 }
 
 impl<'a> OrganizingData<'a> {
@@ -30,7 +36,9 @@ impl<'a> OrganizingData<'a> {
         custom_file_name: &'a str,
         file_name_component_order: &'a Vec<FilenameComponents>,
         date_type: Option<DateType>,
+        timezone: Timezone,
         index_position: Option<IndexPosition>,
+        rename_overrides: &'a BTreeMap<OsString, String>,
     ) -> Self {
         Self {
             files_selected,
@@ -40,7 +48,9 @@ impl<'a> OrganizingData<'a> {
             custom_file_name,
             file_name_component_order,
             date_type,
+            timezone,
             index_position,
+            rename_overrides,
         }
     }
 }
@@ -79,7 +89,7 @@ pub fn apply_rules_for_directory(
             data,
         )?;
         selected_directory.insert_directory(new_directory, &new_directory_name);
-    } else if app_util::just_rename_checked(&data.checkbox_states) {
+    } else if util::just_rename_checked(&data.checkbox_states) {
         rename_files(
             data,
             &mut new_directory,
@@ -89,14 +99,18 @@ pub fn apply_rules_for_directory(
         selected_directory.insert_directory(new_directory, &new_directory_name);
     } else {
         for (key, mut file) in data.files_selected {
-            let file_name = app_util::convert_os_str_to_str(&key)?;
+            let file_name = match get_name_override(data.rename_overrides, &key) {
+                Some(name_override) => name_override,
+                None => String::from(util::convert_os_str_to_str(&key)?),
+            };
+            new_directory.file_already_exists_in_directory(&OsString::from(&file_name))?;
             create_destination_path(
                 path_to_selected_directory,
                 vec![&new_directory_name, &file_name],
                 &mut file,
             );
             files_organized.insert(OsString::from(&file_name), file.clone());
-            new_directory.insert_file(key, file);
+            new_directory.insert_file(OsString::from(file_name), file);
         }
         selected_directory.insert_directory(new_directory, &new_directory_name);
     }
@@ -130,7 +144,7 @@ pub fn move_files_to_organized_directory(
             selected_directory,
             data,
         )?;
-    } else if app_util::just_rename_checked(&data.checkbox_states) {
+    } else if util::just_rename_checked(&data.checkbox_states) {
         rename_files(
             data,
             selected_directory,
@@ -140,26 +154,166 @@ pub fn move_files_to_organized_directory(
     } else {
         selected_directory.contains_unique_files(&data.files_selected)?;
         for (key, mut file) in data.files_selected {
-            let file_name = app_util::convert_os_str_to_str(&key)?;
+            let file_name = match get_name_override(data.rename_overrides, &key) {
+                Some(name_override) => name_override,
+                None => String::from(util::convert_os_str_to_str(&key)?),
+            };
+            selected_directory.file_already_exists_in_directory(&OsString::from(&file_name))?;
             create_destination_path(
                 path_to_selected_directory,
-                vec![&data.directory_name, file_name],
+                vec![&data.directory_name, &file_name],
                 &mut file,
             );
             files_organized.insert(OsString::from(&file_name), file.clone());
-            selected_directory.insert_file(key, file);
+            selected_directory.insert_file(OsString::from(file_name), file);
         }
     }
     Ok(())
 }
 
+// What organizing the selected files into a new directory would do to one
+// file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenamePreview {
+    file_name: OsString,
+    new_file_name: String,
+    destination_path: PathBuf,
+    empty_name: bool,
+    name_overridden: bool,
+    collision: bool,
+}
+
+impl RenamePreview {
+    pub fn get_file_name(&self) -> &OsString {
+        &self.file_name
+    }
+
+    pub fn get_new_file_name(&self) -> &str {
+        &self.new_file_name
+    }
+
+    pub fn get_destination_path(&self) -> &PathBuf {
+        &self.destination_path
+    }
+
+    // The rules left nothing of the name, so it fell back to
+    // FALLBACK_FILE_NAME.
+    pub fn is_empty_name(&self) -> bool {
+        self.empty_name
+    }
+
+    pub fn is_name_overridden(&self) -> bool {
+        self.name_overridden
+    }
+
+    pub fn has_collision(&self) -> bool {
+        self.collision
+    }
+}
+
+// Computes the new names and destinations the same way
+// apply_rules_for_directory does, without touching any directory. Files that
+// would end up with the same destination are marked instead of failing.
+pub fn preview_renames(
+    path_to_selected_directory: &PathBuf,
+    data: OrganizingData,
+) -> std::io::Result<Vec<RenamePreview>> {
+    let checkbox_states = data.checkbox_states;
+    let rename = checkbox_states.organize_by_filetype
+        || checkbox_states.organize_by_date
+        || util::just_rename_checked(checkbox_states);
+    let date_type = match checkbox_states.organize_by_date {
+        true => Some(util::get_date_type(data.date_type)?),
+        false => data.date_type,
+    };
+    if rename && date_type.is_none() && checkbox_states.insert_date_to_file_name {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "Date type not specified.",
+        ));
+    }
+
+    // Indexes are counted separately in every directory the files go to
+    let mut file_counts: BTreeMap<Vec<String>, usize> = BTreeMap::new();
+    let mut previews = Vec::new();
+    for (key, file) in &data.files_selected {
+        let file_name = util::convert_os_str_to_str(key)?;
+        let mut subdirectories = Vec::new();
+        if checkbox_states.organize_by_filetype {
+            subdirectories.push(
+                get_file_type_from_file_name(file_name).unwrap_or_else(|| String::from("other")),
+            );
+        }
+        if let (true, Some(date_type)) = (checkbox_states.organize_by_date, date_type) {
+            subdirectories.push(get_formatted_date_from_file(
+                file,
+                &date_type,
+                data.timezone,
+            )?);
+        }
+        let file_count = file_counts.entry(subdirectories.clone()).or_insert(0);
+
+        let mut new_file_name = String::new();
+        let mut empty_name = false;
+        let name_override = get_name_override(data.rename_overrides, key);
+        match &name_override {
+            Some(name_override) => new_file_name.push_str(name_override),
+            None if rename => {
+                let rename_data = RenameData::build(
+                    &mut new_file_name,
+                    checkbox_states,
+                    data.replaceables,
+                    data.directory_name,
+                    data.custom_file_name,
+                    *file_count,
+                    data.file_name_component_order,
+                    file_name,
+                    file,
+                    date_type,
+                    data.timezone,
+                    data.index_position,
+                );
+                empty_name = is_empty_file_name(&compose_file_name(&rename_data).0);
+                rename_file_name(rename_data);
+            }
+            None => new_file_name.push_str(file_name),
+        }
+        *file_count += 1;
+
+        let mut path_components = vec![data.directory_name];
+        path_components.extend(subdirectories.iter().map(String::as_str));
+        path_components.push(&new_file_name);
+        let mut destination_path = PathBuf::from(path_to_selected_directory);
+        destination_path.push(build_destination_path(path_components));
+        previews.push(RenamePreview {
+            file_name: key.clone(),
+            new_file_name,
+            destination_path,
+            empty_name,
+            name_overridden: name_override.is_some(),
+            collision: false,
+        });
+    }
+
+    let mut destination_counts: BTreeMap<PathBuf, usize> = BTreeMap::new();
+    for preview in &previews {
+        *destination_counts
+            .entry(preview.destination_path.clone())
+            .or_insert(0) += 1;
+    }
+    for preview in &mut previews {
+        preview.collision = destination_counts[&preview.destination_path] > 1;
+    }
+    Ok(previews)
+}
+
 fn organize_files_by_file_type_and_date(
     path_to_selected_directory: &PathBuf,
     files_organized: &mut BTreeMap<OsString, File>,
     selected_directory: &mut Directory,
     data: OrganizingData,
 ) -> std::io::Result<()> {
-    let date_type_selected = app_util::get_date_type(data.date_type)?;
+    let date_type_selected = util::get_date_type(data.date_type)?;
     let mut file_type_dirs = get_file_types(&data.files_selected);
     selected_directory.filter_duplicate_directories(&mut file_type_dirs);
     selected_directory.insert_new_directories(file_type_dirs);
@@ -176,7 +330,9 @@ fn organize_files_by_file_type_and_date(
             data.custom_file_name,
             data.file_name_component_order,
             data.date_type,
+            data.timezone,
             data.index_position,
+            data.rename_overrides,
             false,
             false,
         ))?;
@@ -192,7 +348,9 @@ fn organize_files_by_file_type_and_date(
                     data.custom_file_name,
                     data.file_name_component_order,
                     Some(date_type_selected),
+                    data.timezone,
                     data.index_position.clone(),
+                    data.rename_overrides,
                 );
                 let mut path_to_filetype_directory = PathBuf::from(&path_to_selected_directory);
                 path_to_filetype_directory.push(data.directory_name);
@@ -239,7 +397,9 @@ fn organize_files_by_file_type(
             data.custom_file_name,
             data.file_name_component_order,
             data.date_type,
+            data.timezone,
             data.index_position,
+            data.rename_overrides,
             true,
             true,
         ))?;
@@ -257,8 +417,8 @@ fn organize_files_by_date(
     selected_directory: &mut Directory,
     data: OrganizingData,
 ) -> std::io::Result<()> {
-    let date_type = app_util::get_date_type(data.date_type)?;
-    let mut file_date_dirs = create_file_dates(&data.files_selected, date_type);
+    let date_type = util::get_date_type(data.date_type)?;
+    let mut file_date_dirs = create_file_dates(&data.files_selected, date_type, data.timezone);
     selected_directory.filter_duplicate_directories(&mut file_date_dirs);
     selected_directory.insert_new_directories(file_date_dirs);
     if let Some(file_date_dirs) = selected_directory.get_mut_directories() {
@@ -273,7 +433,9 @@ fn organize_files_by_date(
             data.custom_file_name,
             data.file_name_component_order,
             Some(date_type),
+            data.timezone,
             data.index_position,
+            data.rename_overrides,
             true,
             true,
         ))?;
@@ -299,19 +461,23 @@ fn rename_files(
         if let Some(file_name) = key.to_str() {
             let mut renamed_file_name = String::new();
             let file_count = directory.get_file_count();
-            rename_file_name(RenameData::build(
-                &mut renamed_file_name,
-                &data.checkbox_states,
-                data.replaceables,
-                data.directory_name,
-                data.custom_file_name,
-                file_count,
-                data.file_name_component_order,
-                file_name,
-                &file,
-                data.date_type,
-                data.index_position,
-            ));
+            match get_name_override(data.rename_overrides, &key) {
+                Some(name_override) => renamed_file_name = name_override,
+                None => rename_file_name(RenameData::build(
+                    &mut renamed_file_name,
+                    &data.checkbox_states,
+                    data.replaceables,
+                    data.directory_name,
+                    data.custom_file_name,
+                    file_count,
+                    data.file_name_component_order,
+                    file_name,
+                    &file,
+                    data.date_type,
+                    data.timezone,
+                    data.index_position,
+                )),
+            }
             insert_renamed_files_to_dir(
                 &renamed_file_name,
                 file,
@@ -357,7 +523,9 @@ pub struct SortData<'a> {
     custom_file_name: &'a str,
     file_name_component_order: &'a Vec<FilenameComponents>,
     date_type_selected: Option<DateType>,
+    timezone: Timezone,
     index_position: Option<IndexPosition>,
+    rename_overrides: &'a BTreeMap<OsString, String>,
     rename: bool,
     mark_as_organized: bool,
 }
@@ -373,7 +541,9 @@ impl<'a> SortData<'a> {
         custom_file_name: &'a str,
         file_name_component_order: &'a Vec<FilenameComponents>,
         date_type_selected: Option<DateType>,
+        timezone: Timezone,
         index_position: Option<IndexPosition>,
+        rename_overrides: &'a BTreeMap<OsString, String>,
         rename: bool,
         mark_as_organized: bool,
     ) -> Self {
@@ -388,7 +558,9 @@ impl<'a> SortData<'a> {
             custom_file_name,
             file_name_component_order,
             date_type_selected,
+            timezone,
             index_position,
+            rename_overrides,
             rename,
             mark_as_organized,
         }
@@ -396,10 +568,12 @@ impl<'a> SortData<'a> {
 }
 pub fn sort_files_by_file_type(mut sort_data: SortData) -> std::io::Result<()> {
     for (key, file) in sort_data.files_selected {
-        let file_name = app_util::convert_os_str_to_str(&key)?;
+        let file_name = util::convert_os_str_to_str(&key)?;
         let mut renamed_file_name = String::new();
         let file_count = get_file_count_from_dir(file_name, sort_data.file_type_directories);
-        if sort_data.rename {
+        if let Some(name_override) = get_name_override(sort_data.rename_overrides, &key) {
+            renamed_file_name = name_override;
+        } else if sort_data.rename {
             rename_file_name(RenameData::build(
                 &mut renamed_file_name,
                 sort_data.checkbox_states,
@@ -411,6 +585,7 @@ pub fn sort_files_by_file_type(mut sort_data: SortData) -> std::io::Result<()> {
                 file_name,
                 &file,
                 sort_data.date_type_selected,
+                sort_data.timezone,
                 sort_data.index_position,
             ));
         } else {
@@ -431,29 +606,33 @@ pub fn sort_files_by_file_type(mut sort_data: SortData) -> std::io::Result<()> {
 }
 
 pub fn sort_files_by_date(mut sort_data: SortData) -> std::io::Result<()> {
-    let date_type = app_util::get_date_type(sort_data.date_type_selected)?;
+    let date_type = util::get_date_type(sort_data.date_type_selected)?;
     for (key, file) in sort_data.files_selected {
-        let file_name = app_util::convert_os_str_to_str(&key)?;
-        let formatted_date = get_formatted_date_from_file(&file, &date_type)?;
+        let file_name = util::convert_os_str_to_str(&key)?;
+        let formatted_date = get_formatted_date_from_file(&file, &date_type, sort_data.timezone)?;
         if let Some(date_dir) = sort_data
             .file_type_directories
             .get_mut(&OsString::from(&formatted_date))
         {
             let mut renamed_file_name = String::new();
             let file_count = date_dir.get_file_count();
-            rename_file_name(RenameData::build(
-                &mut renamed_file_name,
-                sort_data.checkbox_states,
-                sort_data.replaceables,
-                sort_data.new_directory_name,
-                sort_data.custom_file_name,
-                file_count,
-                sort_data.file_name_component_order,
-                file_name,
-                &file,
-                Some(date_type),
-                sort_data.index_position,
-            ));
+            match get_name_override(sort_data.rename_overrides, &key) {
+                Some(name_override) => renamed_file_name = name_override,
+                None => rename_file_name(RenameData::build(
+                    &mut renamed_file_name,
+                    sort_data.checkbox_states,
+                    sort_data.replaceables,
+                    sort_data.new_directory_name,
+                    sort_data.custom_file_name,
+                    file_count,
+                    sort_data.file_name_component_order,
+                    file_name,
+                    &file,
+                    Some(date_type),
+                    sort_data.timezone,
+                    sort_data.index_position,
+                )),
+            }
             let mut directory_name = Some(sort_data.new_directory_name);
             if sort_data.checkbox_states.organize_by_filetype
                 && sort_data.checkbox_states.organize_by_date
@@ -487,6 +666,7 @@ pub struct RenameData<'a> {
     file_name: &'a str,
     file: &'a File,
     date_type_selected: Option<DateType>,
+    timezone: Timezone,
     index_position: Option<IndexPosition>,
 }
 
@@ -502,6 +682,7 @@ impl<'a> RenameData<'a> {
         file_name: &'a str,
         file: &'a File,
         date_type_selected: Option<DateType>,
+        timezone: Timezone,
         index_position: Option<IndexPosition>,
     ) -> Self {
         Self {
@@ -515,6 +696,7 @@ impl<'a> RenameData<'a> {
             file_name,
             file,
             date_type_selected,
+            timezone,
             index_position,
         }
     }
@@ -541,6 +723,20 @@ impl FilenameComponentString {
 }
 
 pub fn rename_file_name(rename_data: RenameData) {
+    let (mut name, file_type) = compose_file_name(&rename_data);
+    if is_empty_file_name(&name) {
+        name = String::from(FALLBACK_FILE_NAME);
+    }
+    rename_data.renamed_file_name.push_str(name.as_str());
+    rename_data.renamed_file_name.push_str(file_type.as_str());
+}
+
+fn is_empty_file_name(name: &str) -> bool {
+    matches!(name, "" | "." | "..")
+}
+
+// Returns the name the rules give before the file type, and the file type.
+fn compose_file_name(rename_data: &RenameData) -> (String, String) {
     let FilenameComponentString {
         mut date,
         mut directory_name,
@@ -557,7 +753,9 @@ pub fn rename_file_name(rename_data: RenameData) {
     if let Some(date_type) = rename_data.date_type_selected {
         if rename_data.checkbox_states.insert_date_to_file_name {
             if let Some(metadata) = rename_data.file.get_metadata() {
-                if let Some(formatted) = metadata.get_formatted_date(date_type) {
+                if let Some(formatted) =
+                    metadata.get_formatted_date(date_type, rename_data.timezone)
+                {
                     date.push_str(formatted.as_str());
                 }
             }
@@ -610,7 +808,6 @@ pub fn rename_file_name(rename_data: RenameData) {
             &mut custom_name,
             &mut directory_name,
             &mut original_name,
-            &mut file_type,
             rename_data.replaceables,
         );
     }
@@ -633,32 +830,43 @@ pub fn rename_file_name(rename_data: RenameData) {
     }
 
     let size = rename_data.file_name_component_order.len();
+    let mut name = String::new();
 
     for (i, component) in rename_data.file_name_component_order.iter().enumerate() {
         match component {
-            FilenameComponents::Date => rename_data.renamed_file_name.push_str(date.as_str()),
-            FilenameComponents::DirectoryName => rename_data
-                .renamed_file_name
-                .push_str(directory_name.as_str()),
-            FilenameComponents::CustomFilename => {
-                rename_data.renamed_file_name.push_str(custom_name.as_str())
-            }
-            FilenameComponents::OriginalFilename => rename_data
-                .renamed_file_name
-                .push_str(original_name.as_str()),
+            FilenameComponents::Date => name.push_str(date.as_str()),
+            FilenameComponents::DirectoryName => name.push_str(directory_name.as_str()),
+            FilenameComponents::CustomFilename => name.push_str(custom_name.as_str()),
+            FilenameComponents::OriginalFilename => name.push_str(original_name.as_str()),
         }
         if i < (size - 1) {
-            rename_data.renamed_file_name.push('_');
+            name.push('_');
         }
     }
-    rename_data.renamed_file_name.push_str(file_type.as_str());
+    // Custom and directory names come from user input and may contain
+    // characters no file name can have.
+    name = name.replace(['/', '\0'], "_");
+    (name, file_type)
+}
+
+// Names typed by the user for single files take the place of the names the
+// rules give. They are cleaned up the same way.
+pub fn get_name_override(
+    rename_overrides: &BTreeMap<OsString, String>,
+    file_name: &OsString,
+) -> Option<String> {
+    let name_override = rename_overrides.get(file_name)?.replace(['/', '\0'], "_");
+    match is_empty_file_name(name_override.trim()) {
+        true => None,
+        false => Some(name_override),
+    }
 }
 
+// The file type is left as is so files keep their extension.
 fn replace_characters_by_rules(
     custom_name: &mut String,
     directory_name: &mut String,
     original_name: &mut String,
-    file_type: &mut String,
     replaceables: &Vec<ReplacableSelection>,
 ) {
     for replaceable in replaceables {
@@ -667,7 +875,6 @@ fn replace_characters_by_rules(
                 replace_character_with(custom_name, replace, replace_with);
                 replace_character_with(directory_name, replace, replace_with);
                 replace_character_with(original_name, replace, replace_with);
-                replace_character_with(file_type, replace, replace_with);
             }
         }
     }
@@ -705,6 +912,10 @@ pub fn get_file_type_from_file_name(file_name: &str) -> Option<String> {
 }
 
 pub fn get_file_name_without_file_type(file_name: &str) -> String {
+    // Hidden files like .bashrc have no file type to remove
+    if get_file_type_from_file_name(file_name).is_none() {
+        return String::from(file_name);
+    }
     let mut splitted: Vec<_> = file_name.split(".").collect();
     if splitted.len() > 1 {
         splitted.pop();
@@ -774,11 +985,12 @@ pub fn get_file_types(files_selected: &BTreeMap<OsString, File>) -> BTreeMap<OsS
 pub fn create_file_dates(
     files_selected: &BTreeMap<OsString, File>,
     date_type: DateType,
+    timezone: Timezone,
 ) -> BTreeMap<OsString, Directory> {
     let mut file_dates: BTreeMap<OsString, Directory> = BTreeMap::new();
     for (_key, file) in files_selected {
         if let Some(metadata) = file.get_metadata() {
-            if let Some(formatted) = metadata.get_formatted_date(date_type) {
+            if let Some(formatted) = metadata.get_formatted_date(date_type, timezone) {
                 file_dates.insert(OsString::from(&formatted), Directory::new(None));
             }
         }
@@ -836,10 +1048,13 @@ fn insert_file_to_file_type_dir(
     files_organized: &mut BTreeMap<OsString, File>,
     mark_as_organized: bool,
 ) -> std::io::Result<()> {
-    let file_type_dir = get_file_type_dir(file_name, file_type_directories)?;
+    // A name typed by the user may have another file type, but the file
+    // still goes with the others of its original type.
+    let original_file_name = util::convert_os_str_to_str(&key)?;
+    let file_type_dir = get_file_type_dir(original_file_name, file_type_directories)?;
     file_type_dir.file_already_exists_in_directory(&OsString::from(file_name))?;
     let mut file_type = String::new();
-    if let Some(file_type_from_file_name) = get_file_type_from_file_name(file_name) {
+    if let Some(file_type_from_file_name) = get_file_type_from_file_name(original_file_name) {
         file_type.push_str(&file_type_from_file_name);
     } else {
         file_type.push_str("other");
@@ -910,9 +1125,10 @@ fn get_file_type_dir<'a>(
 fn get_formatted_date_from_file(
     file: &File,
     date_type_selected: &DateType,
+    timezone: Timezone,
 ) -> std::io::Result<String> {
     if let Some(metadata) = file.get_metadata() {
-        if let Some(formatted_date) = metadata.get_formatted_date(*date_type_selected) {
+        if let Some(formatted_date) = metadata.get_formatted_date(*date_type_selected, timezone) {
             return Ok(formatted_date);
         }
         return Err(std::io::Error::new(
@@ -930,6 +1146,7 @@ fn get_formatted_date_from_file(
 mod tests {
     use super::*;
     use crate::metadata::Metadata;
+    use proptest::prelude::{prop_assert, prop_assert_eq, prop_oneof, Just, Strategy};
     use std::time::SystemTime;
 
     #[test]
@@ -946,6 +1163,7 @@ mod tests {
                 Some(PathBuf::new()),
             )),
             &DateType::Created,
+            Timezone::Local,
         );
         if let Ok(result) = result {
             let formatted = convert_system_time_to_string(SystemTime::UNIX_EPOCH);
@@ -1042,7 +1260,7 @@ mod tests {
     #[test]
     fn test_create_file_dates() {
         let files_selected = create_dummy_files_selected();
-        let file_date_dirs = create_file_dates(&files_selected, DateType::Created);
+        let file_date_dirs = create_file_dates(&files_selected, DateType::Created, Timezone::Local);
         for key in file_date_dirs.keys() {
             assert_eq!(
                 &OsString::from(convert_system_time_to_string(SystemTime::UNIX_EPOCH)),
@@ -1098,4 +1316,328 @@ mod tests {
             panic!("filetype extension was not in filename. Should have returned None.");
         }
     }
+
+    #[test]
+    fn test_rename_file_name_fallback() {
+        let checkbox_states = CheckboxStates {
+            remove_original_file_name: true,
+            ..Default::default()
+        };
+        let order = vec![FilenameComponents::OriginalFilename];
+        for (file_name, expected) in [
+            ("notes.TXT", "file.txt"),
+            ("README", "file"),
+            (".bashrc", "file"),
+        ] {
+            let mut renamed_file_name = String::new();
+            rename_file_name(RenameData::build(
+                &mut renamed_file_name,
+                &checkbox_states,
+                &Vec::new(),
+                "documents",
+                "",
+                0,
+                &order,
+                file_name,
+                &File::new(Metadata::new()),
+                None,
+                Timezone::Utc,
+                None,
+            ));
+            assert_eq!(renamed_file_name, expected);
+        }
+    }
+
+    #[test]
+    fn test_rename_file_name_keeps_hidden_file_name() {
+        let mut renamed_file_name = String::new();
+        rename_file_name(RenameData::build(
+            &mut renamed_file_name,
+            &CheckboxStates::default(),
+            &Vec::new(),
+            "documents",
+            "",
+            0,
+            &vec![FilenameComponents::OriginalFilename],
+            ".bashrc",
+            &File::new(Metadata::new()),
+            None,
+            Timezone::Utc,
+            None,
+        ));
+        assert_eq!(renamed_file_name, ".bashrc");
+    }
+
+    fn create_dated_files_selected(file_names: &[&str]) -> BTreeMap<OsString, File> {
+        let mut files_selected = BTreeMap::new();
+        for (i, file_name) in file_names.iter().enumerate() {
+            let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(86_400 * i as u64);
+            files_selected.insert(
+                OsString::from(file_name),
+                File::new(Metadata::build(
+                    Some(OsString::from(file_name)),
+                    Some(time),
+                    Some(time),
+                    Some(time),
+                    Some(10.0),
+                    false,
+                    Some(PathBuf::from("/photos").join(file_name)),
+                    None,
+                )),
+            );
+        }
+        files_selected
+    }
+
+    #[test]
+    fn test_preview_renames_matches_apply_rules() {
+        let files_selected =
+            create_dated_files_selected(&["b.jpg", "a.jpg", "c.JPG", "notes.txt", "README"]);
+        let path = PathBuf::from("/photos");
+        let order = vec![
+            FilenameComponents::CustomFilename,
+            FilenameComponents::OriginalFilename,
+        ];
+        let replaceables = Vec::new();
+        let overrides = BTreeMap::new();
+        for (organize_by_filetype, organize_by_date, add_custom_name) in [
+            (false, false, false),
+            (false, false, true),
+            (true, false, true),
+            (false, true, true),
+            (true, true, true),
+        ] {
+            let checkbox_states = CheckboxStates {
+                organize_by_filetype,
+                organize_by_date,
+                add_custom_name,
+                ..Default::default()
+            };
+            let data = || {
+                OrganizingData::new(
+                    files_selected.clone(),
+                    &checkbox_states,
+                    &replaceables,
+                    "trip",
+                    "holiday",
+                    &order,
+                    Some(DateType::Modified),
+                    Timezone::Utc,
+                    Some(IndexPosition::After),
+                    &overrides,
+                )
+            };
+            let previews = preview_renames(&path, data()).expect("Failed to preview renames");
+            let mut files_organized = BTreeMap::new();
+            apply_rules_for_directory(
+                &path,
+                &mut files_organized,
+                String::from("trip"),
+                &mut Directory::new(None),
+                data(),
+            )
+            .expect("Failed to apply rules");
+
+            let mut previewed: Vec<PathBuf> = previews
+                .iter()
+                .map(|preview| PathBuf::from(preview.get_destination_path()))
+                .collect();
+            let mut applied: Vec<PathBuf> = files_organized
+                .values()
+                .filter_map(|file| file.get_metadata().as_ref())
+                .filter_map(|metadata| metadata.get_destination_path())
+                .collect();
+            previewed.sort();
+            applied.sort();
+            assert_eq!(previewed, applied);
+            assert!(previews.iter().all(|preview| !preview.has_collision()));
+        }
+    }
+
+    #[test]
+    fn test_preview_renames_collisions_and_overrides() {
+        let files_selected = create_dated_files_selected(&["a.txt", "b.txt", "c.txt"]);
+        let path = PathBuf::from("/notes");
+        let checkbox_states = CheckboxStates {
+            remove_original_file_name: true,
+            ..Default::default()
+        };
+        let order = vec![FilenameComponents::OriginalFilename];
+        let replaceables = Vec::new();
+        let mut overrides = BTreeMap::new();
+        overrides.insert(OsString::from("a.txt"), String::from("first/draft.txt"));
+        overrides.insert(OsString::from("c.txt"), String::from("  "));
+        let data = |overrides| {
+            OrganizingData::new(
+                files_selected.clone(),
+                &checkbox_states,
+                &replaceables,
+                "notes",
+                "",
+                &order,
+                None,
+                Timezone::Utc,
+                None,
+                overrides,
+            )
+        };
+
+        let previews = preview_renames(&path, data(&overrides)).expect("Failed to preview");
+        let names: Vec<&str> = previews
+            .iter()
+            .map(|preview| preview.get_new_file_name())
+            .collect();
+        assert_eq!(names, vec!["first_draft.txt", "file.txt", "file.txt"]);
+        assert!(previews[0].is_name_overridden() && !previews[0].has_collision());
+        assert!(!previews[0].is_empty_name());
+        for preview in &previews[1..] {
+            assert!(!preview.is_name_overridden());
+            assert!(preview.is_empty_name() && preview.has_collision());
+        }
+        assert_eq!(
+            previews[0].get_destination_path(),
+            &PathBuf::from("/notes/notes/first_draft.txt")
+        );
+        let mut directory = Directory::new(None);
+        let result = apply_rules_for_directory(
+            &path,
+            &mut BTreeMap::new(),
+            String::from("notes"),
+            &mut directory,
+            data(&overrides),
+        );
+        assert!(result.is_err());
+
+        let mut unique_overrides = overrides.clone();
+        unique_overrides.insert(OsString::from("c.txt"), String::from("third.txt"));
+        let mut files_organized = BTreeMap::new();
+        apply_rules_for_directory(
+            &path,
+            &mut files_organized,
+            String::from("notes"),
+            &mut Directory::new(None),
+            data(&unique_overrides),
+        )
+        .expect("Failed to apply rules with overrides");
+        let new_names: Vec<&OsString> = files_organized.keys().collect();
+        assert_eq!(new_names, vec!["file.txt", "first_draft.txt", "third.txt"]);
+    }
+
+    fn create_checkbox_states(bits: u16) -> CheckboxStates {
+        let bit = |index: u16| bits & (1 << index) != 0;
+        CheckboxStates::new(
+            bit(0),
+            bit(1),
+            bit(2),
+            bit(3),
+            bit(4),
+            bit(5),
+            bit(6),
+            bit(7),
+            bit(8),
+        )
+    }
+
+    fn replaceable_strategy() -> impl Strategy<Value = ReplacableSelection> {
+        (
+            proptest::option::of(prop_oneof![
+                Just(Replaceable::Dash),
+                Just(Replaceable::Space),
+                Just(Replaceable::Comma),
+            ]),
+            proptest::option::of(prop_oneof![
+                Just(ReplaceWith::Underscore),
+                Just(ReplaceWith::Nothing),
+            ]),
+        )
+            .prop_map(|(replaceable, replace_with)| {
+                ReplacableSelection::from(replaceable, replace_with)
+            })
+    }
+
+    fn order_strategy() -> impl Strategy<Value = Vec<FilenameComponents>> {
+        (
+            Just(vec![
+                FilenameComponents::Date,
+                FilenameComponents::OriginalFilename,
+                FilenameComponents::DirectoryName,
+                FilenameComponents::CustomFilename,
+            ])
+            .prop_shuffle(),
+            0..=4usize,
+        )
+            .prop_map(|(mut order, length)| {
+                order.truncate(length);
+                order
+            })
+    }
+
+    // File names can hold anything except "/" and NUL. Custom and directory
+    // names are typed by the user, so they can hold anything at all.
+    proptest::proptest! {
+        #[test]
+        fn test_rename_file_name_invariants(
+            file_name in "[^/\\x00]{1,24}(\\.[^/\\x00.]{1,6})?",
+            directory_name in proptest::prelude::any::<String>(),
+            custom_file_name in proptest::prelude::any::<String>(),
+            replaceables in proptest::collection::vec(replaceable_strategy(), 0..3),
+            order in order_strategy(),
+            file_count in 0..1000usize,
+            date_type in proptest::option::of(prop_oneof![
+                Just(DateType::Created),
+                Just(DateType::Accessed),
+                Just(DateType::Modified),
+            ]),
+            index_position in proptest::option::of(prop_oneof![
+                Just(IndexPosition::Before),
+                Just(IndexPosition::After),
+            ]),
+            seconds in 0..4_102_444_800u64,
+        ) {
+            let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds);
+            let file = File::new(Metadata::build(
+                Some(OsString::from(&file_name)),
+                Some(time),
+                Some(time),
+                Some(time),
+                Some(10.0),
+                false,
+                None,
+                None,
+            ));
+            let file_type = get_file_type_from_file_name(&file_name);
+            for bits in 0..512 {
+                let checkbox_states = create_checkbox_states(bits);
+                let rename = || {
+                    let mut renamed_file_name = String::new();
+                    rename_file_name(RenameData::build(
+                        &mut renamed_file_name,
+                        &checkbox_states,
+                        &replaceables,
+                        &directory_name,
+                        &custom_file_name,
+                        file_count,
+                        &order,
+                        &file_name,
+                        &file,
+                        date_type,
+                        Timezone::Utc,
+                        index_position,
+                    ));
+                    renamed_file_name
+                };
+                let renamed_file_name = rename();
+                prop_assert!(!renamed_file_name.is_empty());
+                prop_assert!(renamed_file_name != "." && renamed_file_name != "..");
+                prop_assert!(!renamed_file_name.contains('/'));
+                prop_assert!(!renamed_file_name.contains('\0'));
+                if let Some(file_type) = &file_type {
+                    let extension = format!(".{}", file_type);
+                    prop_assert!(renamed_file_name.ends_with(&extension));
+                    prop_assert!(renamed_file_name.len() > extension.len());
+                }
+                prop_assert_eq!(&renamed_file_name, &rename());
+            }
+        }
+    }
 }
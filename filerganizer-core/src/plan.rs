@@ -0,0 +1,613 @@
+use crate::directory::Directory;
+use crate::file::File;
+use crate::organize_files::{self, OrganizingData};
+use crate::rules::{FilenameComponents, Rules};
+use crate::sanitize;
+use crate::util;
+use crate::vfs::{FileSystem, RealFileSystem};
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedMove {
+    origin: PathBuf,
+    destination: PathBuf,
+}
+
+impl PlannedMove {
+    pub fn new(origin: PathBuf, destination: PathBuf) -> Self {
+        Self {
+            origin,
+            destination,
+        }
+    }
+
+    pub fn get_origin(&self) -> &PathBuf {
+        &self.origin
+    }
+
+    pub fn get_destination(&self) -> &PathBuf {
+        &self.destination
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Plan {
+    moves: Vec<PlannedMove>,
+}
+
+impl Plan {
+    pub fn new(moves: Vec<PlannedMove>) -> Self {
+        Self { moves }
+    }
+
+    // Files without both an origin and a destination path are not moved.
+    pub fn from_files_organized(files_organized: &BTreeMap<OsString, File>) -> Self {
+        let moves = files_organized
+            .values()
+            .filter_map(|file| file.get_metadata().as_ref())
+            .filter_map(|metadata| {
+                match (metadata.get_origin_path(), metadata.get_destination_path()) {
+                    (Some(origin), Some(destination)) => {
+                        Some(PlannedMove::new(origin, destination))
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+        Self { moves }
+    }
+
+    pub fn get_moves(&self) -> &Vec<PlannedMove> {
+        &self.moves
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+}
+
+// Reads the files directly inside path. Subdirectories are not included.
+pub fn scan(path: &Path) -> std::io::Result<BTreeMap<OsString, File>> {
+    scan_with(&RealFileSystem, path)
+}
+
+pub fn scan_with(
+    file_system: &dyn FileSystem,
+    path: &Path,
+) -> std::io::Result<BTreeMap<OsString, File>> {
+    let mut directory = Directory::new(None);
+    Directory::new(None).read_path_with(file_system, &PathBuf::from(path), &mut directory)?;
+    Ok(directory.get_mut_files().take().unwrap_or_default())
+}
+
+// Plans where the files go inside an organized directory by its rules.
+// organized_directory holds what the directory already contains and gets the
+// planned files inserted, so following plans take them into account. The
+// names are sanitized for the target filesystem of the rules.
+pub fn plan(
+    files_selected: BTreeMap<OsString, File>,
+    rules: &Rules,
+    path_to_organized_directory: &Path,
+    organized_directory: &mut Directory,
+) -> std::io::Result<Plan> {
+    plan_with(
+        &RealFileSystem,
+        files_selected,
+        rules,
+        path_to_organized_directory,
+        organized_directory,
+    )
+}
+
+pub fn plan_with(
+    file_system: &dyn FileSystem,
+    files_selected: BTreeMap<OsString, File>,
+    rules: &Rules,
+    path_to_organized_directory: &Path,
+    organized_directory: &mut Directory,
+) -> std::io::Result<Plan> {
+    let path_to_parent = match path_to_organized_directory.parent() {
+        Some(parent) => PathBuf::from(parent),
+        None => {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Organized directory has no parent directory.",
+            ))
+        }
+    };
+    let directory_name = match path_to_organized_directory.file_name() {
+        Some(directory_name) => util::convert_os_str_to_str(directory_name)?,
+        None => {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Organized directory has no name.",
+            ))
+        }
+    };
+    let mut files_organized = BTreeMap::new();
+    organize_files::move_files_to_organized_directory(
+        &path_to_parent,
+        &mut files_organized,
+        organized_directory,
+        OrganizingData::new(
+            files_selected,
+            rules.get_checkbox_states(),
+            rules.get_replaceables(),
+            directory_name,
+            rules.get_custom_filename(),
+            rules.get_order_of_filename_components(),
+            *rules.get_date_type_selected(),
+            rules.get_timezone(),
+            *rules.get_index_position(),
+            &BTreeMap::new(),
+        ),
+    )?;
+    let (plan, _) = sanitize::sanitize_plan_with(
+        file_system,
+        &Plan::from_files_organized(&files_organized),
+        path_to_organized_directory,
+        rules.get_sanitize_options(),
+    )?;
+    Ok(plan)
+}
+
+// Plans moving the files already inside an organized directory to where its
+// rules put them now, for when the rules have changed. The names are kept, as
+// the rename rules were applied when the files were added. Files that are
+// already in place are left out.
+pub fn plan_reapply(rules: &Rules, path_to_organized_directory: &Path) -> std::io::Result<Plan> {
+    plan_reapply_with(&RealFileSystem, rules, path_to_organized_directory)
+}
+
+pub fn plan_reapply_with(
+    file_system: &dyn FileSystem,
+    rules: &Rules,
+    path_to_organized_directory: &Path,
+) -> std::io::Result<Plan> {
+    let mut checkbox_states = rules.get_checkbox_states().clone();
+    checkbox_states.insert_date_to_file_name = false;
+    checkbox_states.insert_directory_name_to_file_name = false;
+    checkbox_states.convert_uppercase_to_lowercase = false;
+    checkbox_states.replace_character = false;
+    checkbox_states.use_only_ascii = false;
+    checkbox_states.remove_original_file_name = false;
+    checkbox_states.add_custom_name = false;
+    let rules = Rules::new(
+        checkbox_states,
+        Vec::new(),
+        *rules.get_date_type_selected(),
+        vec![FilenameComponents::OriginalFilename],
+        None,
+        String::new(),
+        rules.get_timezone(),
+        rules.get_sanitize_options(),
+    );
+
+    let mut directories = vec![PathBuf::from(path_to_organized_directory)];
+    let mut index = 0;
+    while index < directories.len() {
+        let mut entries = file_system.read_dir(&directories[index])?;
+        entries.sort();
+        for entry in entries {
+            if file_system.metadata(&entry)?.is_dir() {
+                directories.push(entry);
+            }
+        }
+        index += 1;
+    }
+    // Every file is planned again, so the plan starts from an empty directory
+    let mut organized_directory = Directory::new(None);
+    let mut moves = Vec::new();
+    for directory in directories {
+        let files = scan_with(file_system, &directory)?;
+        if files.is_empty() {
+            continue;
+        }
+        let plan = plan_with(
+            file_system,
+            files,
+            &rules,
+            path_to_organized_directory,
+            &mut organized_directory,
+        )?;
+        moves.extend(
+            plan.moves
+                .into_iter()
+                .filter(|planned_move| planned_move.origin != planned_move.destination),
+        );
+    }
+    Ok(Plan::new(order_moves(moves)?))
+}
+
+// Files that other files are moved onto have to move out of the way first.
+fn order_moves(mut moves: Vec<PlannedMove>) -> std::io::Result<Vec<PlannedMove>> {
+    let mut ordered = Vec::new();
+    while !moves.is_empty() {
+        let origins: HashSet<PathBuf> = moves
+            .iter()
+            .map(|planned_move| PathBuf::from(&planned_move.origin))
+            .collect();
+        let (ready, waiting): (Vec<PlannedMove>, Vec<PlannedMove>) = moves
+            .into_iter()
+            .partition(|planned_move| !origins.contains(&planned_move.destination));
+        if ready.is_empty() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "Files would have to swap places.",
+            ));
+        }
+        ordered.extend(ready);
+        moves = waiting;
+    }
+    Ok(ordered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directory;
+    use crate::filesystem::{self, TransferMode};
+    use crate::metadata::{DateType, Timezone};
+    use crate::rules::{CheckboxStates, FilenameComponents, IndexPosition};
+    use crate::rules::{ReplacableSelection, ReplaceWith, Replaceable};
+    use crate::sanitize::{SanitizeAction, SanitizeOptions, TargetProfile};
+    use crate::vfs::MemoryFileSystem;
+
+    #[test]
+    fn test_scan_plan_apply_undo() {
+        let file_system = MemoryFileSystem::new();
+        let inbox = PathBuf::from("/home/inbox");
+        let organized = PathBuf::from("/home/organized");
+        file_system.create_dir_all(&organized.join("txt"));
+        file_system.write_file(&inbox.join("notes.txt"), b"notes");
+        file_system.write_file(&inbox.join("photo.jpg"), b"photo");
+        file_system.create_dir_all(&inbox.join("subdirectory"));
+
+        let rules = Rules::new(
+            CheckboxStates {
+                organize_by_filetype: true,
+                ..Default::default()
+            },
+            Vec::new(),
+            None,
+            vec![FilenameComponents::OriginalFilename],
+            None,
+            String::new(),
+            Timezone::Local,
+            SanitizeOptions::default(),
+        );
+        let files = scan_with(&file_system, &inbox).expect("Failed to scan inbox");
+        assert_eq!(files.len(), 2);
+        let mut organized_directory =
+            directory::read_directory_recursive_with(&file_system, &organized)
+                .expect("Failed to read organized");
+        let plan = plan_with(
+            &file_system,
+            files,
+            &rules,
+            &organized,
+            &mut organized_directory,
+        )
+        .expect("Failed to plan");
+        let mut destinations: Vec<&PathBuf> = plan
+            .get_moves()
+            .iter()
+            .map(|planned_move| planned_move.get_destination())
+            .collect();
+        destinations.sort();
+        assert_eq!(
+            destinations,
+            vec![
+                &organized.join("jpg").join("photo.jpg"),
+                &organized.join("txt").join("notes.txt")
+            ]
+        );
+
+        let journal = filesystem::apply_with(&file_system, &plan, TransferMode::Move)
+            .expect("Failed to apply");
+        assert!(file_system
+            .exists(&organized.join("jpg").join("photo.jpg"))
+            .unwrap());
+        assert!(!file_system.exists(&inbox.join("notes.txt")).unwrap());
+        assert_eq!(
+            journal.get_created_directories(),
+            &vec![organized.join("jpg")]
+        );
+
+        filesystem::undo_with(&file_system, &journal).expect("Failed to undo");
+        assert!(file_system.exists(&inbox.join("notes.txt")).unwrap());
+        assert!(file_system.exists(&inbox.join("photo.jpg")).unwrap());
+        assert!(!file_system.exists(&organized.join("jpg")).unwrap());
+        assert!(file_system.exists(&organized.join("txt")).unwrap());
+    }
+
+    #[test]
+    fn test_scan_fails_on_unreadable_directory() {
+        let file_system = MemoryFileSystem::new();
+        file_system.write_file(Path::new("/home/inbox/notes.txt"), b"notes");
+        file_system.inject_failure(Path::new("/home/inbox"), ErrorKind::PermissionDenied);
+        let error =
+            scan_with(&file_system, Path::new("/home/inbox")).expect_err("Scan should fail");
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn test_reapply_rules_to_organized_directory() {
+        let file_system = MemoryFileSystem::new();
+        let organized = PathBuf::from("/home/photos");
+        for (relative_path, seconds) in [
+            ("jpg/20250831_a.jpg", 1756641600),
+            ("jpg/b.jpg", 1756728000),
+            ("jpg/20250901/c.jpg", 1756728000),
+            ("d.jpg", 1756641600),
+        ] {
+            let path = organized.join(relative_path);
+            file_system.write_file(&path, relative_path.as_bytes());
+            let modified =
+                std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds);
+            file_system
+                .set_times(&path, Some(modified), Some(modified), Some(modified))
+                .expect("Failed to set times");
+        }
+        let rules = Rules::new(
+            CheckboxStates {
+                organize_by_filetype: true,
+                organize_by_date: true,
+                insert_date_to_file_name: true,
+                ..Default::default()
+            },
+            Vec::new(),
+            Some(DateType::Modified),
+            vec![
+                FilenameComponents::Date,
+                FilenameComponents::OriginalFilename,
+            ],
+            None,
+            String::new(),
+            Timezone::Utc,
+            SanitizeOptions::default(),
+        );
+
+        let plan = plan_reapply_with(&file_system, &rules, &organized).expect("Failed to plan");
+        let mut moves: Vec<(PathBuf, PathBuf)> = plan
+            .get_moves()
+            .iter()
+            .map(|planned_move| {
+                (
+                    planned_move
+                        .get_origin()
+                        .strip_prefix(&organized)
+                        .unwrap()
+                        .into(),
+                    planned_move
+                        .get_destination()
+                        .strip_prefix(&organized)
+                        .unwrap()
+                        .into(),
+                )
+            })
+            .collect();
+        moves.sort();
+        assert_eq!(
+            moves,
+            vec![
+                ("d.jpg".into(), "jpg/20250831/d.jpg".into()),
+                (
+                    "jpg/20250831_a.jpg".into(),
+                    "jpg/20250831/20250831_a.jpg".into()
+                ),
+                ("jpg/b.jpg".into(), "jpg/20250901/b.jpg".into()),
+            ]
+        );
+
+        filesystem::apply_with(&file_system, &plan, TransferMode::Move).expect("Failed to apply");
+        let plan = plan_reapply_with(&file_system, &rules, &organized).expect("Failed to plan");
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn test_order_moves() {
+        let planned_move = |origin: &str, destination: &str| {
+            PlannedMove::new(PathBuf::from(origin), PathBuf::from(destination))
+        };
+        let ordered = order_moves(vec![
+            planned_move("/a", "/b"),
+            planned_move("/b", "/c"),
+            planned_move("/d", "/e"),
+        ])
+        .expect("Failed to order");
+        assert_eq!(
+            ordered,
+            vec![
+                planned_move("/b", "/c"),
+                planned_move("/d", "/e"),
+                planned_move("/a", "/b"),
+            ]
+        );
+        let error = order_moves(vec![planned_move("/a", "/b"), planned_move("/b", "/a")])
+            .expect_err("Swaps cannot be ordered");
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    const FIXTURE_ROOT: &str = "/fixture";
+
+    // Builds the tree described by testdata/fixtures/<name>.txt under
+    // FIXTURE_ROOT.
+    fn read_fixture(name: &str) -> MemoryFileSystem {
+        let path_to_fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join("fixtures")
+            .join(format!("{}.txt", name));
+        let fixture = std::fs::read_to_string(&path_to_fixture).expect("Fixture missing");
+        let file_system = MemoryFileSystem::new();
+        for line in fixture.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (seconds, relative_path) = line.split_once(' ').expect("Invalid fixture line");
+            let path = Path::new(FIXTURE_ROOT).join(relative_path);
+            if relative_path.ends_with('/') {
+                file_system.create_dir_all(&path);
+                continue;
+            }
+            file_system.write_file(&path, relative_path.as_bytes());
+            let modified = std::time::SystemTime::UNIX_EPOCH
+                + std::time::Duration::from_secs(seconds.parse().expect("Invalid time"));
+            file_system
+                .set_times(&path, Some(modified), Some(modified), Some(modified))
+                .expect("Failed to set times");
+        }
+        file_system
+    }
+
+    fn format_path(path: &Path) -> String {
+        let relative_path = path.strip_prefix(FIXTURE_ROOT).unwrap_or(path);
+        relative_path
+            .iter()
+            .map(|component| component.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn format_plan(plan: &Plan) -> String {
+        let mut lines: Vec<String> = plan
+            .get_moves()
+            .iter()
+            .map(|planned_move| {
+                format!(
+                    "{} -> {}\n",
+                    format_path(planned_move.get_origin()),
+                    format_path(planned_move.get_destination())
+                )
+            })
+            .collect();
+        lines.sort();
+        lines.concat()
+    }
+
+    // Compares against testdata/snapshots/<name>.snap. Running the tests with
+    // UPDATE_SNAPSHOTS set writes the snapshots instead.
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path_to_snapshot = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join("snapshots")
+            .join(format!("{}.snap", name));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&path_to_snapshot, actual).expect("Failed to write snapshot");
+            return;
+        }
+        let expected = std::fs::read_to_string(&path_to_snapshot)
+            .expect("Snapshot missing, run the tests with UPDATE_SNAPSHOTS=1");
+        assert_eq!(actual, expected, "Plan differs from snapshot {}", name);
+    }
+
+    fn assert_plan_snapshot(name: &str, rules: &Rules) {
+        let file_system = read_fixture(name);
+        let inbox = Path::new(FIXTURE_ROOT).join("inbox");
+        let organized = PathBuf::from(FIXTURE_ROOT).join("organized");
+        let files = scan_with(&file_system, &inbox).expect("Failed to scan inbox");
+        let mut organized_directory =
+            directory::read_directory_recursive_with(&file_system, &organized)
+                .expect("Failed to read organized");
+        let plan = plan_with(
+            &file_system,
+            files,
+            rules,
+            &organized,
+            &mut organized_directory,
+        )
+        .expect("Failed to plan");
+        assert_snapshot(name, &format_plan(&plan));
+
+        // The plan has to apply cleanly to the tree it was made from
+        let journal = filesystem::apply_with(&file_system, &plan, TransferMode::Move)
+            .expect("Failed to apply");
+        filesystem::undo_with(&file_system, &journal).expect("Failed to undo");
+    }
+
+    #[test]
+    fn test_camera_roll_plan_snapshot() {
+        let rules = Rules::new(
+            CheckboxStates {
+                organize_by_filetype: true,
+                organize_by_date: true,
+                insert_date_to_file_name: true,
+                convert_uppercase_to_lowercase: true,
+                replace_character: true,
+                use_only_ascii: true,
+                ..Default::default()
+            },
+            vec![
+                ReplacableSelection::from(Some(Replaceable::Space), Some(ReplaceWith::Underscore)),
+                ReplacableSelection::from(Some(Replaceable::Dash), Some(ReplaceWith::Nothing)),
+                ReplacableSelection::from(Some(Replaceable::Comma), Some(ReplaceWith::Underscore)),
+            ],
+            Some(DateType::Modified),
+            vec![
+                FilenameComponents::Date,
+                FilenameComponents::OriginalFilename,
+            ],
+            None,
+            String::new(),
+            Timezone::Utc,
+            SanitizeOptions::default(),
+        );
+        assert_plan_snapshot("camera_roll", &rules);
+    }
+
+    #[test]
+    fn test_documents_plan_snapshot() {
+        let rules = Rules::new(
+            CheckboxStates {
+                insert_directory_name_to_file_name: true,
+                remove_original_file_name: true,
+                add_custom_name: true,
+                ..Default::default()
+            },
+            Vec::new(),
+            None,
+            vec![
+                FilenameComponents::DirectoryName,
+                FilenameComponents::CustomFilename,
+            ],
+            Some(IndexPosition::After),
+            String::from("report"),
+            Timezone::Utc,
+            SanitizeOptions::default(),
+        );
+        assert_plan_snapshot("documents", &rules);
+    }
+
+    #[test]
+    fn test_plain_move_plan_snapshot() {
+        let rules = Rules::new(
+            CheckboxStates::default(),
+            Vec::new(),
+            None,
+            vec![FilenameComponents::OriginalFilename],
+            None,
+            String::new(),
+            Timezone::Utc,
+            SanitizeOptions::default(),
+        );
+        assert_plan_snapshot("plain_move", &rules);
+    }
+
+    #[test]
+    fn test_usb_stick_plan_snapshot() {
+        let rules = Rules::new(
+            CheckboxStates::default(),
+            Vec::new(),
+            None,
+            vec![FilenameComponents::OriginalFilename],
+            None,
+            String::new(),
+            Timezone::Utc,
+            SanitizeOptions::new(TargetProfile::Fat, SanitizeAction::Rewrite),
+        );
+        assert_plan_snapshot("usb_stick", &rules);
+    }
+}
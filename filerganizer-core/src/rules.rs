@@ -0,0 +1,254 @@
+use crate::metadata::{DateType, Timezone};
+use crate::sanitize::SanitizeOptions;
+use crate::save_directory;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Copy, Eq)]
+pub enum Replaceable {
+    Dash,
+    Space,
+    Comma,
+}
+
+impl std::fmt::Display for Replaceable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Replaceable::Dash => "Dash",
+            Replaceable::Space => "Space",
+            Replaceable::Comma => "Comma",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Copy, Eq)]
+pub enum ReplaceWith {
+    Underscore,
+    Nothing,
+}
+
+impl std::fmt::Display for ReplaceWith {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ReplaceWith::Nothing => "Nothing",
+            ReplaceWith::Underscore => "Underscore",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckboxStates {
+    pub organize_by_filetype: bool,
+    pub organize_by_date: bool,
+    pub insert_date_to_file_name: bool,
+    pub insert_directory_name_to_file_name: bool,
+    pub convert_uppercase_to_lowercase: bool,
+    pub replace_character: bool,
+    pub use_only_ascii: bool,
+    pub remove_original_file_name: bool,
+    pub add_custom_name: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexPosition {
+    Before,
+    After,
+}
+
+impl Default for CheckboxStates {
+    fn default() -> Self {
+        Self {
+            organize_by_filetype: false,
+            organize_by_date: false,
+            insert_date_to_file_name: false,
+            insert_directory_name_to_file_name: false,
+            convert_uppercase_to_lowercase: false,
+            replace_character: false,
+            use_only_ascii: false,
+            remove_original_file_name: false,
+            add_custom_name: false,
+        }
+    }
+}
+
+impl CheckboxStates {
+    pub fn new(
+        organize_by_filetype: bool,
+        organize_by_date: bool,
+        insert_date_to_file_name: bool,
+        insert_directory_name_to_file_name: bool,
+        convert_uppercase_to_lowercase: bool,
+        replace_character: bool,
+        use_only_ascii: bool,
+        remove_original_file_name: bool,
+        add_custom_name: bool,
+    ) -> Self {
+        Self {
+            organize_by_filetype,
+            organize_by_date,
+            insert_date_to_file_name,
+            insert_directory_name_to_file_name,
+            convert_uppercase_to_lowercase,
+            replace_character,
+            use_only_ascii,
+            remove_original_file_name,
+            add_custom_name,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    checkbox_states: CheckboxStates,
+    replaceables: Vec<ReplacableSelection>,
+    date_type_selected: Option<DateType>,
+    order_of_filename_components: Vec<FilenameComponents>,
+    index_position: Option<IndexPosition>,
+    filename_input: String,
+    timezone: Timezone,
+    sanitize_options: SanitizeOptions,
+}
+
+impl Rules {
+    pub fn new(
+        checkbox_states: CheckboxStates,
+        replaceables: Vec<ReplacableSelection>,
+        date_type_selected: Option<DateType>,
+        order_of_filename_components: Vec<FilenameComponents>,
+        index_position: Option<IndexPosition>,
+        filename_input: String,
+        timezone: Timezone,
+        sanitize_options: SanitizeOptions,
+    ) -> Self {
+        Self {
+            checkbox_states,
+            replaceables,
+            date_type_selected,
+            order_of_filename_components,
+            index_position,
+            filename_input,
+            timezone,
+            sanitize_options,
+        }
+    }
+
+    pub fn read_from_save_file(
+        home_directory_path: &PathBuf,
+        directory_path: &PathBuf,
+    ) -> std::io::Result<Self> {
+        let (
+            checkbox_states,
+            date_type,
+            index_position,
+            replaceables,
+            order_of_filename_components,
+            custom_filename,
+            timezone,
+            sanitize_options,
+        ) = save_directory::read_directory_rules_from_file(home_directory_path, directory_path)?;
+        Ok(Self::new(
+            checkbox_states,
+            replaceables,
+            date_type,
+            order_of_filename_components,
+            index_position,
+            custom_filename,
+            timezone,
+            sanitize_options,
+        ))
+    }
+
+    pub fn get_checkbox_states(&self) -> &CheckboxStates {
+        &self.checkbox_states
+    }
+
+    pub fn get_replaceables(&self) -> &Vec<ReplacableSelection> {
+        &self.replaceables
+    }
+
+    pub fn get_date_type_selected(&self) -> &Option<DateType> {
+        &self.date_type_selected
+    }
+
+    pub fn get_order_of_filename_components(&self) -> &Vec<FilenameComponents> {
+        &self.order_of_filename_components
+    }
+
+    pub fn get_index_position(&self) -> &Option<IndexPosition> {
+        &self.index_position
+    }
+
+    pub fn get_custom_filename(&self) -> &str {
+        &self.filename_input.as_str()
+    }
+
+    pub fn get_timezone(&self) -> Timezone {
+        self.timezone
+    }
+
+    pub fn get_sanitize_options(&self) -> SanitizeOptions {
+        self.sanitize_options
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplacableSelection {
+    replaceable_selected: Option<Replaceable>,
+    replace_with_selected: Option<ReplaceWith>,
+}
+
+impl Default for ReplacableSelection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReplacableSelection {
+    pub fn new() -> Self {
+        Self {
+            replace_with_selected: Some(ReplaceWith::Nothing),
+            replaceable_selected: None,
+        }
+    }
+
+    pub fn from(replaceable: Option<Replaceable>, replace_with: Option<ReplaceWith>) -> Self {
+        Self {
+            replaceable_selected: replaceable,
+            replace_with_selected: replace_with,
+        }
+    }
+
+    pub fn get_replaceable_selected(&self) -> Option<Replaceable> {
+        self.replaceable_selected
+    }
+
+    pub fn get_replace_with_selected(&self) -> Option<ReplaceWith> {
+        self.replace_with_selected
+    }
+
+    pub fn set_replaceable_selected(&mut self, replaceable: Option<Replaceable>) {
+        self.replaceable_selected = replaceable;
+    }
+
+    pub fn set_replace_with_selected(&mut self, replace_with: Option<ReplaceWith>) {
+        self.replace_with_selected = replace_with;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilenameComponents {
+    Date,
+    OriginalFilename,
+    DirectoryName,
+    CustomFilename,
+}
+
+impl FilenameComponents {
+    pub fn convert_to_text(&self) -> &str {
+        match self {
+            FilenameComponents::CustomFilename => "Custom filename",
+            FilenameComponents::Date => "Date",
+            FilenameComponents::DirectoryName => "Directory name",
+            FilenameComponents::OriginalFilename => "Original_filename",
+        }
+    }
+}
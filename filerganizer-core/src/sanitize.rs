@@ -0,0 +1,584 @@
+use crate::organize_files;
+use crate::plan::{Plan, PlannedMove};
+use crate::vfs::{FileSystem, RealFileSystem};
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+const MAX_NAME_LENGTH: usize = 255;
+const WINDOWS_INVALID_CHARACTERS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Filesystem the organized files are meant for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TargetProfile {
+    #[default]
+    Posix,
+    Windows,
+    Fat,
+    MacOs,
+}
+
+pub const TARGET_PROFILES: [TargetProfile; 4] = [
+    TargetProfile::Posix,
+    TargetProfile::Windows,
+    TargetProfile::Fat,
+    TargetProfile::MacOs,
+];
+
+impl std::fmt::Display for TargetProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TargetProfile::Posix => "POSIX",
+            TargetProfile::Windows => "Windows",
+            TargetProfile::Fat => "FAT32/exFAT",
+            TargetProfile::MacOs => "macOS",
+        })
+    }
+}
+
+impl TargetProfile {
+    pub fn parse(input: &str) -> Option<Self> {
+        TARGET_PROFILES
+            .into_iter()
+            .find(|profile| profile.to_string() == input)
+    }
+
+    fn is_invalid_character(&self, character: char) -> bool {
+        match self {
+            TargetProfile::Posix => character == '/' || character == '\0',
+            TargetProfile::MacOs => character == '/' || character == ':' || character == '\0',
+            TargetProfile::Windows | TargetProfile::Fat => {
+                (character.is_ascii_control() && character != '\x7f')
+                    || WINDOWS_INVALID_CHARACTERS.contains(&character)
+            }
+        }
+    }
+
+    fn follows_windows_rules(&self) -> bool {
+        matches!(self, TargetProfile::Windows | TargetProfile::Fat)
+    }
+
+    // POSIX names are bytes, the others have to be valid Unicode.
+    fn allows_non_unicode(&self) -> bool {
+        *self == TargetProfile::Posix
+    }
+
+    // Windows and FAT count UTF-16 code units, the others bytes.
+    fn name_length(&self, name: &str) -> usize {
+        if self.follows_windows_rules() {
+            name.encode_utf16().count()
+        } else {
+            name.len()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SanitizeAction {
+    #[default]
+    Rewrite,
+    Reject,
+}
+
+impl std::fmt::Display for SanitizeAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SanitizeAction::Rewrite => "Rewrite",
+            SanitizeAction::Reject => "Reject",
+        })
+    }
+}
+
+impl SanitizeAction {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "Rewrite" => Some(SanitizeAction::Rewrite),
+            "Reject" => Some(SanitizeAction::Reject),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SanitizeOptions {
+    pub profile: TargetProfile,
+    pub action: SanitizeAction,
+}
+
+impl SanitizeOptions {
+    pub fn new(profile: TargetProfile, action: SanitizeAction) -> Self {
+        Self { profile, action }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameProblem {
+    InvalidCharacter(char),
+    ReservedName,
+    TrailingDotOrSpace,
+    TooLong,
+    Duplicate,
+    NotUnicode,
+}
+
+impl std::fmt::Display for NameProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NameProblem::InvalidCharacter(character) => {
+                write!(f, "invalid character {:?}", character)
+            }
+            NameProblem::ReservedName => f.write_str("reserved name"),
+            NameProblem::TrailingDotOrSpace => f.write_str("trailing dot or space"),
+            NameProblem::TooLong => f.write_str("name too long"),
+            NameProblem::Duplicate => f.write_str("same name as another file"),
+            NameProblem::NotUnicode => f.write_str("not valid Unicode"),
+        }
+    }
+}
+
+// A planned destination that had to be changed to suit the target profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanitizedName {
+    origin: PathBuf,
+    planned_destination: PathBuf,
+    destination: PathBuf,
+    problems: Vec<NameProblem>,
+}
+
+impl SanitizedName {
+    pub fn get_origin(&self) -> &PathBuf {
+        &self.origin
+    }
+
+    pub fn get_planned_destination(&self) -> &PathBuf {
+        &self.planned_destination
+    }
+
+    pub fn get_destination(&self) -> &PathBuf {
+        &self.destination
+    }
+
+    pub fn get_problems(&self) -> &Vec<NameProblem> {
+        &self.problems
+    }
+}
+
+impl std::fmt::Display for SanitizedName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let problems: Vec<String> = self
+            .problems
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect();
+        write!(
+            f,
+            "{} -> {} ({})",
+            self.planned_destination.display(),
+            self.destination.display(),
+            problems.join(", ")
+        )
+    }
+}
+
+pub fn find_problems(name: &str, profile: TargetProfile) -> Vec<NameProblem> {
+    let mut problems = Vec::new();
+    for character in name.chars() {
+        let problem = NameProblem::InvalidCharacter(character);
+        if profile.is_invalid_character(character) && !problems.contains(&problem) {
+            problems.push(problem);
+        }
+    }
+    if profile.follows_windows_rules() {
+        if is_reserved_name(name) {
+            problems.push(NameProblem::ReservedName);
+        }
+        if name.ends_with(['.', ' ']) {
+            problems.push(NameProblem::TrailingDotOrSpace);
+        }
+    }
+    if profile.name_length(name) > MAX_NAME_LENGTH {
+        problems.push(NameProblem::TooLong);
+    }
+    problems
+}
+
+// Reserved names stay reserved with any file type, like CON.txt.
+fn is_reserved_name(name: &str) -> bool {
+    let stem = match name.split_once('.') {
+        Some((stem, _)) => stem,
+        None => name,
+    };
+    let stem = stem.trim_end_matches(' ');
+    WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+// Rewrites name so that it is valid for the profile and returns what was
+// wrong with it. Invalid characters become underscores, reserved names get an
+// underscore appended, trailing dots and spaces are removed and long names are
+// shortened before the file type.
+pub fn sanitize_name(name: &str, profile: TargetProfile) -> (String, Vec<NameProblem>) {
+    let problems = find_problems(name, profile);
+    if problems.is_empty() {
+        return (String::from(name), problems);
+    }
+    let mut sanitized: String = name
+        .chars()
+        .map(|character| {
+            if profile.is_invalid_character(character) {
+                '_'
+            } else {
+                character
+            }
+        })
+        .collect();
+    if profile.follows_windows_rules() {
+        sanitized = String::from(sanitized.trim_end_matches(['.', ' ']));
+        if is_reserved_name(&sanitized) {
+            sanitized = match sanitized.split_once('.') {
+                Some((stem, rest)) => format!("{}_.{}", stem, rest),
+                None => format!("{}_", sanitized),
+            };
+        }
+    }
+    if sanitized.is_empty() {
+        sanitized = String::from(organize_files::FALLBACK_FILE_NAME);
+    }
+    (shorten_name(&sanitized, profile), problems)
+}
+
+fn shorten_name(name: &str, profile: TargetProfile) -> String {
+    if profile.name_length(name) <= MAX_NAME_LENGTH {
+        return String::from(name);
+    }
+    let file_type = match organize_files::get_file_type_from_file_name(name) {
+        Some(_) => name.rsplit_once('.').map_or("", |(_, file_type)| file_type),
+        None => "",
+    };
+    let mut ending = String::new();
+    if !file_type.is_empty() && profile.name_length(file_type) < MAX_NAME_LENGTH / 2 {
+        ending = format!(".{}", file_type);
+    }
+    let stem = &name[..name.len() - ending.len()];
+    let mut shortened = String::new();
+    for character in stem.chars() {
+        let mut candidate = shortened.clone();
+        candidate.push(character);
+        if profile.name_length(&candidate) + profile.name_length(&ending) > MAX_NAME_LENGTH {
+            break;
+        }
+        shortened = candidate;
+    }
+    // Shortening may leave a trailing dot or space behind
+    if profile.follows_windows_rules() {
+        shortened = String::from(shortened.trim_end_matches(['.', ' ']));
+    }
+    if shortened.is_empty() {
+        shortened.push_str(organize_files::FALLBACK_FILE_NAME);
+    }
+    shortened.push_str(&ending);
+    shortened
+}
+
+fn add_suffix(name: &str, suffix: usize) -> String {
+    match organize_files::get_file_type_from_file_name(name) {
+        Some(_) => match name.rsplit_once('.') {
+            Some((stem, file_type)) => format!("{}_{}.{}", stem, suffix, file_type),
+            None => format!("{}_{}", name, suffix),
+        },
+        None => format!("{}_{}", name, suffix),
+    }
+}
+
+// Works on the name as an OsStr, so names that are not valid Unicode keep
+// their bytes.
+pub(crate) fn add_os_suffix(name: &OsStr, suffix: usize) -> OsString {
+    if let Some(name) = name.to_str() {
+        return OsString::from(add_suffix(name, suffix));
+    }
+    let mut name = OsString::from(name);
+    name.push(format!("_{}", suffix));
+    name
+}
+
+fn sanitize_destination(
+    destination: &Path,
+    base: &Path,
+    options: SanitizeOptions,
+) -> std::io::Result<(PathBuf, Vec<NameProblem>)> {
+    let relative_path = match destination.strip_prefix(base) {
+        Ok(relative_path) => relative_path,
+        Err(_) => return Ok((PathBuf::from(destination), Vec::new())),
+    };
+    let mut sanitized_destination = PathBuf::from(base);
+    let mut problems = Vec::new();
+    for component in relative_path.iter() {
+        let name = component.to_string_lossy();
+        let (sanitized, component_problems) = sanitize_component(component, options.profile);
+        if !component_problems.is_empty() && options.action == SanitizeAction::Reject {
+            let component_problems: Vec<String> = component_problems
+                .iter()
+                .map(|problem| problem.to_string())
+                .collect();
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} is not a valid name for {}: {}.",
+                    name,
+                    options.profile,
+                    component_problems.join(", ")
+                ),
+            ));
+        }
+        if component_problems.is_empty() {
+            sanitized_destination.push(component);
+        } else {
+            sanitized_destination.push(OsString::from(sanitized));
+        }
+        for problem in component_problems {
+            if !problems.contains(&problem) {
+                problems.push(problem);
+            }
+        }
+    }
+    Ok((sanitized_destination, problems))
+}
+
+// Names that are not valid Unicode are kept as they are where the profile
+// allows it, unless they are too long. Elsewhere they can only be rewritten
+// with the invalid bytes replaced, which is reported.
+fn sanitize_component(component: &OsStr, profile: TargetProfile) -> (String, Vec<NameProblem>) {
+    if let Some(name) = component.to_str() {
+        return sanitize_name(name, profile);
+    }
+    // Only the length can be wrong, since components never hold '/' or '\0'
+    if profile.allows_non_unicode() && component.len() <= MAX_NAME_LENGTH {
+        return (String::new(), Vec::new());
+    }
+    let name = component.to_string_lossy();
+    let (sanitized, mut problems) = sanitize_name(&name, profile);
+    if profile.allows_non_unicode() {
+        return (sanitized, problems);
+    }
+    problems.insert(0, NameProblem::NotUnicode);
+    (sanitized, problems)
+}
+
+// Checks the part of every destination below base, which is where the
+// organizing rules decide the names. With SanitizeAction::Reject the first
+// offending name is returned as an error, otherwise the names are rewritten
+// and the changes are returned next to the new plan. Rewritten names that
+// end up the same as another destination or an existing file get a number
+// appended.
+pub fn sanitize_plan(
+    plan: &Plan,
+    base: &Path,
+    options: SanitizeOptions,
+) -> std::io::Result<(Plan, Vec<SanitizedName>)> {
+    sanitize_plan_with(&RealFileSystem, plan, base, options)
+}
+
+pub fn sanitize_plan_with(
+    file_system: &dyn FileSystem,
+    plan: &Plan,
+    base: &Path,
+    options: SanitizeOptions,
+) -> std::io::Result<(Plan, Vec<SanitizedName>)> {
+    let mut sanitized_destinations = Vec::new();
+    for planned_move in plan.get_moves() {
+        sanitized_destinations.push(sanitize_destination(
+            planned_move.get_destination(),
+            base,
+            options,
+        )?);
+    }
+    let mut destinations: HashSet<PathBuf> = sanitized_destinations
+        .iter()
+        .filter(|(_, problems)| problems.is_empty())
+        .map(|(destination, _)| destination.clone())
+        .collect();
+
+    let mut moves = Vec::new();
+    let mut sanitized_names = Vec::new();
+    for (planned_move, (mut destination, mut problems)) in
+        plan.get_moves().iter().zip(sanitized_destinations)
+    {
+        if !problems.is_empty() {
+            if destinations.contains(&destination) || file_system.exists(&destination)? {
+                if let Some(file_name) = destination.file_name().map(OsString::from) {
+                    let mut suffix = 1;
+                    while destinations.contains(&destination) || file_system.exists(&destination)? {
+                        destination.set_file_name(add_os_suffix(&file_name, suffix));
+                        suffix += 1;
+                    }
+                    problems.push(NameProblem::Duplicate);
+                }
+            }
+            destinations.insert(destination.clone());
+            sanitized_names.push(SanitizedName {
+                origin: PathBuf::from(planned_move.get_origin()),
+                planned_destination: PathBuf::from(planned_move.get_destination()),
+                destination: destination.clone(),
+                problems,
+            });
+        }
+        moves.push(PlannedMove::new(
+            PathBuf::from(planned_move.get_origin()),
+            destination,
+        ));
+    }
+    Ok((Plan::new(moves), sanitized_names))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFileSystem;
+
+    #[test]
+    fn test_sanitize_name_for_each_profile() {
+        let name = "Meeting: notes?.txt";
+        assert_eq!(sanitize_name(name, TargetProfile::Posix).0, name);
+        assert_eq!(
+            sanitize_name(name, TargetProfile::MacOs).0,
+            "Meeting_ notes?.txt"
+        );
+        for profile in [TargetProfile::Windows, TargetProfile::Fat] {
+            let (sanitized, problems) = sanitize_name(name, profile);
+            assert_eq!(sanitized, "Meeting_ notes_.txt");
+            assert_eq!(
+                problems,
+                vec![
+                    NameProblem::InvalidCharacter(':'),
+                    NameProblem::InvalidCharacter('?')
+                ]
+            );
+        }
+        assert_eq!(
+            sanitize_name("a<b>c\"d|e*f\\g\u{1}.txt", TargetProfile::Windows).0,
+            "a_b_c_d_e_f_g_.txt"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_reserved_names_and_trailing_characters() {
+        for (name, expected) in [
+            ("CON", "CON_"),
+            ("con.txt", "con_.txt"),
+            ("LPT1.tar.gz", "LPT1_.tar.gz"),
+            ("CONSOLE.txt", "CONSOLE.txt"),
+            ("notes. ", "notes"),
+            ("report.txt.", "report.txt"),
+            ("...", "file"),
+            ("NUL.", "NUL_"),
+        ] {
+            assert_eq!(sanitize_name(name, TargetProfile::Fat).0, expected);
+        }
+        assert_eq!(sanitize_name("CON.txt", TargetProfile::Posix).0, "CON.txt");
+        assert_eq!(sanitize_name("notes.", TargetProfile::MacOs).0, "notes.");
+    }
+
+    #[test]
+    fn test_shorten_long_names() {
+        let name = format!("{}.jpg", "ä".repeat(200));
+        // 400 bytes but only 200 UTF-16 code units
+        assert!(find_problems(&name, TargetProfile::Windows).is_empty());
+        let (sanitized, problems) = sanitize_name(&name, TargetProfile::Posix);
+        assert_eq!(problems, vec![NameProblem::TooLong]);
+        assert_eq!(sanitized, format!("{}.jpg", "ä".repeat(125)));
+        assert!(sanitized.len() <= MAX_NAME_LENGTH);
+
+        let (sanitized, _) = sanitize_name(&"a".repeat(300), TargetProfile::Windows);
+        assert_eq!(sanitized, "a".repeat(MAX_NAME_LENGTH));
+    }
+
+    #[test]
+    fn test_sanitize_plan() {
+        let plan = Plan::new(vec![
+            PlannedMove::new(
+                PathBuf::from("/inbox/a:b.txt"),
+                PathBuf::from("/media/usb:stick/photos/a:b.txt"),
+            ),
+            PlannedMove::new(
+                PathBuf::from("/inbox/a?b.txt"),
+                PathBuf::from("/media/usb:stick/photos/a?b.txt"),
+            ),
+            PlannedMove::new(
+                PathBuf::from("/inbox/fine.txt"),
+                PathBuf::from("/media/usb:stick/photos/fine.txt"),
+            ),
+        ]);
+        let base = Path::new("/media/usb:stick");
+        let file_system = MemoryFileSystem::new();
+        file_system.write_file(Path::new("/media/usb:stick/photos/a_b_1.txt"), b"");
+        let options = SanitizeOptions::new(TargetProfile::Fat, SanitizeAction::Rewrite);
+        let (sanitized_plan, sanitized_names) =
+            sanitize_plan_with(&file_system, &plan, base, options).expect("Failed to sanitize");
+        let destinations: Vec<&PathBuf> = sanitized_plan
+            .get_moves()
+            .iter()
+            .map(|planned_move| planned_move.get_destination())
+            .collect();
+        assert_eq!(
+            destinations,
+            vec![
+                &PathBuf::from("/media/usb:stick/photos/a_b.txt"),
+                &PathBuf::from("/media/usb:stick/photos/a_b_2.txt"),
+                &PathBuf::from("/media/usb:stick/photos/fine.txt"),
+            ]
+        );
+        assert_eq!(sanitized_names.len(), 2);
+        assert_eq!(
+            sanitized_names[1].get_problems(),
+            &vec![NameProblem::InvalidCharacter('?'), NameProblem::Duplicate]
+        );
+
+        let options = SanitizeOptions::new(TargetProfile::Fat, SanitizeAction::Reject);
+        let error = sanitize_plan_with(&file_system, &plan, base, options)
+            .expect_err("Plan should be rejected");
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        let options = SanitizeOptions::new(TargetProfile::Posix, SanitizeAction::Reject);
+        let (sanitized_plan, sanitized_names) =
+            sanitize_plan_with(&file_system, &plan, base, options)
+                .expect("Plan is valid for POSIX");
+        assert_eq!(sanitized_plan, plan);
+        assert!(sanitized_names.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sanitize_plan_keeps_non_unicode_names() {
+        use std::os::unix::ffi::OsStrExt;
+        let name = OsStr::from_bytes(b"caf\xe9 a?b.txt");
+        let plan = Plan::new(vec![PlannedMove::new(
+            Path::new("/inbox").join(name),
+            Path::new("/photos").join(name),
+        )]);
+        let base = Path::new("/photos");
+        let file_system = MemoryFileSystem::new();
+
+        let options = SanitizeOptions::new(TargetProfile::Posix, SanitizeAction::Reject);
+        let (sanitized_plan, sanitized_names) =
+            sanitize_plan_with(&file_system, &plan, base, options).expect("Name is valid");
+        assert_eq!(sanitized_plan, plan);
+        assert!(sanitized_names.is_empty());
+
+        let options = SanitizeOptions::new(TargetProfile::Fat, SanitizeAction::Rewrite);
+        let (_, sanitized_names) =
+            sanitize_plan_with(&file_system, &plan, base, options).expect("Failed to sanitize");
+        assert_eq!(
+            sanitized_names[0].get_problems(),
+            &vec![NameProblem::NotUnicode, NameProblem::InvalidCharacter('?')]
+        );
+        assert_eq!(
+            sanitized_names[0].get_destination(),
+            &PathBuf::from("/photos/caf\u{fffd} a_b.txt")
+        );
+    }
+}
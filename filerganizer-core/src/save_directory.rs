@@ -1,13 +1,18 @@
-use crate::app::{FilenameComponents, ReplacableSelection};
-use crate::app_util;
-use crate::layouts::{IndexPosition, ReplaceWith, Replaceable};
-use crate::{layouts::CheckboxStates, metadata::DateType};
+use crate::metadata::{DateType, Timezone};
+use crate::rules::CheckboxStates;
+use crate::rules::{FilenameComponents, ReplacableSelection};
+use crate::rules::{IndexPosition, ReplaceWith, Replaceable};
+use crate::sanitize::{SanitizeAction, SanitizeOptions, TargetProfile};
+use crate::util;
 use std::{
     io::{ErrorKind, Read, Write},
     path::PathBuf,
 };
 
-const CSV_FILE_HEADER: &str = "path, organize_by_file_type, organize_by_date, convert_uppercase_to_lowercase, replace_character, use_only_ascii, insert_directory_name_to_file_name, insert_date_to_file_name, remove_original_file_name, add_custom_name, date_type, component_order\n";
+const TIMEZONE_PREFIX: &str = "timezone=";
+const TARGET_PREFIX: &str = "target=";
+const INVALID_NAMES_PREFIX: &str = "invalid_names=";
+const CSV_FILE_HEADER: &str = "path, organize_by_file_type, organize_by_date, convert_uppercase_to_lowercase, replace_character, use_only_ascii, insert_directory_name_to_file_name, insert_date_to_file_name, remove_original_file_name, add_custom_name, date_type, timezone, target, invalid_names, component_order\n";
 
 pub const SAVE_FILE_NAME: &str = ".save_file.csv";
 
@@ -23,6 +28,8 @@ pub fn write_created_directory_to_save_file(
     checkbox_states: CheckboxStates,
     replaceables: &Vec<ReplacableSelection>,
     date_type: Option<DateType>,
+    timezone: Timezone,
+    sanitize_options: SanitizeOptions,
     index_position: Option<IndexPosition>,
     order_of_filename_components: &Vec<FilenameComponents>,
     custom_filename: &str,
@@ -33,7 +40,7 @@ pub fn write_created_directory_to_save_file(
     {
         Ok(mut file) => {
             // Append to existing file
-            let dir_path = app_util::convert_path_to_str(&directory_path)?;
+            let dir_path = util::convert_path_to_str(&directory_path)?;
             let mut new_directory_data = String::new();
             write_directory_data_to_string(
                 &mut new_directory_data,
@@ -41,6 +48,8 @@ pub fn write_created_directory_to_save_file(
                 checkbox_states,
                 replaceables,
                 date_type,
+                timezone,
+                sanitize_options,
                 index_position,
                 order_of_filename_components,
                 custom_filename,
@@ -50,7 +59,7 @@ pub fn write_created_directory_to_save_file(
         Err(_) => {
             // Create new file
             let mut save_file = create_save_file(home_directory_path, SAVE_FILE_NAME)?;
-            let dir_path = app_util::convert_path_to_str(&directory_path)?;
+            let dir_path = util::convert_path_to_str(&directory_path)?;
             let mut file_content = String::from(CSV_FILE_HEADER);
             write_directory_data_to_string(
                 &mut file_content,
@@ -58,6 +67,8 @@ pub fn write_created_directory_to_save_file(
                 checkbox_states,
                 replaceables,
                 date_type,
+                timezone,
+                sanitize_options,
                 index_position,
                 order_of_filename_components,
                 custom_filename,
@@ -78,6 +89,8 @@ pub fn read_directory_rules_from_file(
     Vec<ReplacableSelection>,
     Vec<FilenameComponents>,
     String,
+    Timezone,
+    SanitizeOptions,
 )> {
     match std::fs::File::options()
         .read(true)
@@ -93,6 +106,8 @@ pub fn read_directory_rules_from_file(
                 let replaceables = parse_replace_rules(&list_of_rules);
                 let order_of_filename_components = parse_filename_components(&list_of_rules);
                 let custom_filename = parse_custom_filename(&list_of_rules);
+                let timezone = parse_timezone(&list_of_rules);
+                let sanitize_options = parse_sanitize_options(&list_of_rules);
                 return Ok((
                     checkbox_states,
                     date_type,
@@ -100,6 +115,8 @@ pub fn read_directory_rules_from_file(
                     replaceables,
                     order_of_filename_components,
                     custom_filename,
+                    timezone,
+                    sanitize_options,
                 ));
             }
             Err(std::io::Error::new(
@@ -121,7 +138,7 @@ pub fn read_save_file_content(
         .open(get_save_file_location(home_directory_path, save_file_name))?;
     let mut file_content = String::new();
     file.read_to_string(&mut file_content)?;
-    let dir_path = app_util::convert_path_to_str(directory_path)?;
+    let dir_path = util::convert_path_to_str(directory_path)?;
     for line in file_content.lines() {
         if let Some((path, _checkbox_states)) = line.split_once(",") {
             if path == dir_path {
@@ -135,6 +152,44 @@ pub fn read_save_file_content(
     Ok(())
 }
 
+pub fn remove_directory_from_save_file(
+    home_directory_path: &PathBuf,
+    directory_path: &PathBuf,
+) -> std::io::Result<()> {
+    let path_to_save_file = get_save_file_location(home_directory_path, SAVE_FILE_NAME);
+    let file_content = std::fs::read_to_string(&path_to_save_file)?;
+    let dir_path = util::convert_path_to_str(directory_path)?;
+    let mut new_file_content = String::new();
+    for line in file_content.lines() {
+        if let Some((path, _rules)) = line.split_once(",") {
+            if path == dir_path {
+                continue;
+            }
+        }
+        new_file_content.push_str(line);
+        new_file_content.push('\n');
+    }
+    std::fs::write(path_to_save_file, new_file_content)
+}
+
+// Directories are listed in the order they were organized. A missing save
+// file means nothing has been organized yet.
+pub fn read_organized_directories(home_directory_path: &PathBuf) -> std::io::Result<Vec<PathBuf>> {
+    let path_to_save_file = get_save_file_location(home_directory_path, SAVE_FILE_NAME);
+    let file_content = match std::fs::read_to_string(&path_to_save_file) {
+        Ok(file_content) => file_content,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut organized_directories = Vec::new();
+    for line in file_content.lines().skip(1) {
+        if let Some((path, _rules)) = line.split_once(",") {
+            organized_directories.push(PathBuf::from(path));
+        }
+    }
+    Ok(organized_directories)
+}
+
 fn parse_index_position_rules(list_of_rules: &Vec<&str>) -> Option<IndexPosition> {
     let mut index_position = None;
     for rule in list_of_rules {
@@ -212,6 +267,37 @@ fn parse_date_type(list_of_rules: &Vec<&str>) -> Option<DateType> {
     };
 }
 
+// Save files written before timezones were added organize by local time.
+fn parse_timezone(list_of_rules: &Vec<&str>) -> Timezone {
+    for rule in list_of_rules {
+        if let Some(timezone) = rule.strip_prefix(TIMEZONE_PREFIX) {
+            if let Some(timezone) = Timezone::parse(timezone) {
+                return timezone;
+            }
+        }
+    }
+    Timezone::Local
+}
+
+// Save files written before sanitizing was added are not sanitized for any
+// other filesystem than the one they were organized on.
+fn parse_sanitize_options(list_of_rules: &Vec<&str>) -> SanitizeOptions {
+    let mut sanitize_options = SanitizeOptions::default();
+    for rule in list_of_rules {
+        if let Some(profile) = rule.strip_prefix(TARGET_PREFIX) {
+            if let Some(profile) = TargetProfile::parse(profile) {
+                sanitize_options.profile = profile;
+            }
+        }
+        if let Some(action) = rule.strip_prefix(INVALID_NAMES_PREFIX) {
+            if let Some(action) = SanitizeAction::parse(action) {
+                sanitize_options.action = action;
+            }
+        }
+    }
+    sanitize_options
+}
+
 fn parse_filename_components(list_of_rules: &Vec<&str>) -> Vec<FilenameComponents> {
     let mut order_of_filename_components = Vec::new();
     for rule in list_of_rules {
@@ -270,6 +356,8 @@ fn write_directory_data_to_string(
     checkbox_states: CheckboxStates,
     replaceables: &Vec<ReplacableSelection>,
     date_type: Option<DateType>,
+    timezone: Timezone,
+    sanitize_options: SanitizeOptions,
     index_position: Option<IndexPosition>,
     order_of_filename_components: &Vec<FilenameComponents>,
     custom_filename: &str,
@@ -299,6 +387,15 @@ fn write_directory_data_to_string(
     } else {
         file_content.push_str("None");
     }
+    file_content.push(',');
+    file_content.push_str(TIMEZONE_PREFIX);
+    file_content.push_str(&timezone.to_string());
+    file_content.push(',');
+    file_content.push_str(TARGET_PREFIX);
+    file_content.push_str(&sanitize_options.profile.to_string());
+    file_content.push(',');
+    file_content.push_str(INVALID_NAMES_PREFIX);
+    file_content.push_str(&sanitize_options.action.to_string());
     write_index_position(file_content, index_position);
     write_replace_rules_to_file(file_content, replaceables);
     write_order_of_filename_components(file_content, order_of_filename_components);
@@ -439,4 +536,118 @@ mod tests {
             CheckboxStates::new(false, false, true, true, true, true, true, false, false)
         );
     }
+
+    #[test]
+    fn test_remove_directory_from_save_file() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let home_directory_path = test_directory.path().to_path_buf();
+        for directory in ["/home/verneri/photos", "/home/verneri/music"] {
+            write_created_directory_to_save_file(
+                &home_directory_path,
+                PathBuf::from(directory),
+                CheckboxStates::default(),
+                &Vec::new(),
+                None,
+                Timezone::Local,
+                SanitizeOptions::default(),
+                None,
+                &Vec::new(),
+                "",
+            )
+            .expect("Failed to write save file");
+        }
+        remove_directory_from_save_file(
+            &home_directory_path,
+            &PathBuf::from("/home/verneri/photos"),
+        )
+        .expect("Failed to remove directory");
+        assert!(read_directory_rules_from_file(
+            &home_directory_path,
+            &PathBuf::from("/home/verneri/photos")
+        )
+        .is_err());
+        assert!(read_directory_rules_from_file(
+            &home_directory_path,
+            &PathBuf::from("/home/verneri/music")
+        )
+        .is_ok());
+        assert_eq!(
+            read_organized_directories(&home_directory_path).expect("Failed to read save file"),
+            vec![PathBuf::from("/home/verneri/music")]
+        );
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        let mut file_content = String::new();
+        write_directory_data_to_string(
+            &mut file_content,
+            "/home/verneri/photos",
+            CheckboxStates::default(),
+            &Vec::new(),
+            Some(DateType::Created),
+            Timezone::parse("Europe/Helsinki").expect("Unknown timezone"),
+            SanitizeOptions::default(),
+            None,
+            &vec![FilenameComponents::OriginalFilename],
+            "",
+        );
+        let path = PathBuf::from("/home/verneri/photos");
+        let list_of_rules = parse_file_result(&file_content, &path).expect("Rules not found");
+        assert_eq!(
+            parse_timezone(&list_of_rules).to_string(),
+            String::from("Europe/Helsinki")
+        );
+        assert_eq!(parse_date_type(&list_of_rules), Some(DateType::Created));
+
+        let old_rules = vec![
+            "/home/verneri/photos",
+            "0",
+            "0",
+            "0",
+            "0",
+            "0",
+            "0",
+            "0",
+            "0",
+            "0",
+            "Created",
+        ];
+        assert_eq!(parse_timezone(&old_rules), Timezone::Local);
+        assert_eq!(
+            parse_sanitize_options(&old_rules),
+            SanitizeOptions::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_sanitize_options() {
+        let mut file_content = String::new();
+        write_directory_data_to_string(
+            &mut file_content,
+            "/media/usb/photos",
+            CheckboxStates::default(),
+            &Vec::new(),
+            None,
+            Timezone::Local,
+            SanitizeOptions::new(TargetProfile::Fat, SanitizeAction::Reject),
+            Some(IndexPosition::After),
+            &vec![
+                FilenameComponents::OriginalFilename,
+                FilenameComponents::CustomFilename,
+            ],
+            "trip",
+        );
+        let path = PathBuf::from("/media/usb/photos");
+        let list_of_rules = parse_file_result(&file_content, &path).expect("Rules not found");
+        assert_eq!(
+            parse_sanitize_options(&list_of_rules),
+            SanitizeOptions::new(TargetProfile::Fat, SanitizeAction::Reject)
+        );
+        assert_eq!(
+            parse_index_position_rules(&list_of_rules),
+            Some(IndexPosition::After)
+        );
+        assert_eq!(parse_custom_filename(&list_of_rules), "trip");
+    }
 }
@@ -0,0 +1,423 @@
+use chrono::{Local, NaiveDateTime, Timelike};
+use std::ffi::{OsStr, OsString};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+pub const APP_TRASH_DIRECTORY_NAME: &str = ".filerganizer_trash";
+const FILES_DIRECTORY_NAME: &str = "files";
+const INFO_DIRECTORY_NAME: &str = "info";
+const INFO_FILE_TYPE: &str = ".trashinfo";
+const INFO_HEADER: &str = "[Trash Info]";
+const PATH_KEY: &str = "Path=";
+const DELETION_DATE_KEY: &str = "DeletionDate=";
+const DELETION_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+// A file or directory in the trash and where it was trashed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrashedItem {
+    trash_directory: PathBuf,
+    name: OsString,
+    original_path: PathBuf,
+    deletion_date: Option<NaiveDateTime>,
+}
+
+impl TrashedItem {
+    pub(crate) fn new(
+        trash_directory: PathBuf,
+        name: OsString,
+        original_path: PathBuf,
+        deletion_date: Option<NaiveDateTime>,
+    ) -> Self {
+        Self {
+            trash_directory,
+            name,
+            original_path,
+            deletion_date,
+        }
+    }
+
+    pub fn get_trash_directory(&self) -> &PathBuf {
+        &self.trash_directory
+    }
+
+    pub fn get_name(&self) -> &OsString {
+        &self.name
+    }
+
+    pub fn get_original_path(&self) -> &PathBuf {
+        &self.original_path
+    }
+
+    pub fn get_deletion_date(&self) -> Option<NaiveDateTime> {
+        self.deletion_date
+    }
+
+    // Where the trashed file is kept until it is restored.
+    pub fn get_trashed_path(&self) -> PathBuf {
+        self.trash_directory
+            .join(FILES_DIRECTORY_NAME)
+            .join(&self.name)
+    }
+
+    fn get_info_path(&self) -> PathBuf {
+        get_info_path(&self.trash_directory, &self.name)
+    }
+}
+
+// Trash directories laid out as in the freedesktop.org Trash specification:
+// the trashed files are in files and a .trashinfo file with the original path
+// and deletion date for each of them is in info. Files go to the first
+// directory that takes them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trash {
+    directories: Vec<PathBuf>,
+}
+
+impl Trash {
+    pub fn new(directories: Vec<PathBuf>) -> Self {
+        Self { directories }
+    }
+
+    // The home trash of the desktop on Linux, then a trash directory of
+    // Filerganizer in the home directory for other systems and for when the
+    // home trash cannot be used.
+    pub fn open(home_directory_path: &Path) -> Self {
+        let mut directories = Vec::new();
+        if let Some(home_trash) = get_home_trash_directory(home_directory_path) {
+            directories.push(home_trash);
+        }
+        directories.push(home_directory_path.join(APP_TRASH_DIRECTORY_NAME));
+        Self::new(directories)
+    }
+
+    pub fn get_directories(&self) -> &Vec<PathBuf> {
+        &self.directories
+    }
+
+    pub fn trash(&self, path: &Path) -> std::io::Result<TrashedItem> {
+        let original_path = std::path::absolute(path)?;
+        std::fs::symlink_metadata(&original_path)?;
+        let mut last_error = std::io::Error::new(ErrorKind::NotFound, "No trash directory found.");
+        for trash_directory in &self.directories {
+            match trash_to_directory(trash_directory, &original_path) {
+                Ok(trashed_item) => return Ok(trashed_item),
+                Err(error) => last_error = error,
+            }
+        }
+        Err(last_error)
+    }
+
+    // Items of every trash directory, the latest deleted first. Info files
+    // that cannot be read or whose file is missing are skipped.
+    pub fn list(&self) -> std::io::Result<Vec<TrashedItem>> {
+        let mut trashed_items = Vec::new();
+        for trash_directory in &self.directories {
+            let info_directory = trash_directory.join(INFO_DIRECTORY_NAME);
+            let entries = match std::fs::read_dir(&info_directory) {
+                Ok(entries) => entries,
+                Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                Err(error) => return Err(error),
+            };
+            for entry in entries.flatten() {
+                let info_file_name = entry.file_name();
+                let name = match info_file_name.to_str() {
+                    Some(name) => match name.strip_suffix(INFO_FILE_TYPE) {
+                        Some(name) => OsString::from(name),
+                        None => continue,
+                    },
+                    None => continue,
+                };
+                let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                    continue;
+                };
+                let Some((original_path, deletion_date)) = parse_info(&content) else {
+                    continue;
+                };
+                let trashed_item = TrashedItem {
+                    trash_directory: PathBuf::from(trash_directory),
+                    name,
+                    original_path,
+                    deletion_date,
+                };
+                if std::fs::symlink_metadata(trashed_item.get_trashed_path()).is_ok() {
+                    trashed_items.push(trashed_item);
+                }
+            }
+        }
+        trashed_items.sort_by_key(|trashed_item| std::cmp::Reverse(trashed_item.deletion_date));
+        Ok(trashed_items)
+    }
+
+    // Puts the item back to its original path. Nothing is overwritten, a file
+    // that has taken the original path since has to be moved away first.
+    pub fn restore(&self, trashed_item: &TrashedItem) -> std::io::Result<PathBuf> {
+        let original_path = trashed_item.get_original_path();
+        if std::fs::symlink_metadata(original_path).is_ok() {
+            return Err(std::io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists.", original_path.display()),
+            ));
+        }
+        if let Some(parent) = original_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        transfer(&trashed_item.get_trashed_path(), original_path)?;
+        std::fs::remove_file(trashed_item.get_info_path())?;
+        Ok(PathBuf::from(original_path))
+    }
+}
+
+// $XDG_DATA_HOME/Trash, by default ~/.local/share/Trash. Only used on Linux,
+// the other systems keep their trash elsewhere.
+fn get_home_trash_directory(home_directory_path: &Path) -> Option<PathBuf> {
+    if std::env::consts::OS != "linux" {
+        return None;
+    }
+    let data_home = match std::env::var_os("XDG_DATA_HOME") {
+        Some(data_home) if Path::new(&data_home).is_absolute() => PathBuf::from(data_home),
+        _ => home_directory_path.join(".local").join("share"),
+    };
+    Some(data_home.join("Trash"))
+}
+
+// Names tried for a trashed file until a free one is found: the file name,
+// then 1_name, 2_name and so on.
+pub(crate) fn get_trashed_name(file_name: &OsStr, counter: usize) -> OsString {
+    if counter == 0 {
+        return OsString::from(file_name);
+    }
+    let mut name = OsString::from(counter.to_string());
+    name.push("_");
+    name.push(file_name);
+    name
+}
+
+fn get_info_path(trash_directory: &Path, name: &OsStr) -> PathBuf {
+    let mut info_file_name = OsString::from(name);
+    info_file_name.push(INFO_FILE_TYPE);
+    trash_directory
+        .join(INFO_DIRECTORY_NAME)
+        .join(info_file_name)
+}
+
+fn trash_to_directory(
+    trash_directory: &Path,
+    original_path: &Path,
+) -> std::io::Result<TrashedItem> {
+    let files_directory = trash_directory.join(FILES_DIRECTORY_NAME);
+    std::fs::create_dir_all(&files_directory)?;
+    std::fs::create_dir_all(trash_directory.join(INFO_DIRECTORY_NAME))?;
+    let file_name = original_path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "Path has no file name."))?;
+    let deletion_date = Local::now().naive_local();
+    let info = format_info(original_path, deletion_date);
+
+    // Creating the info file reserves the name, as the specification asks
+    let mut counter = 0;
+    let mut name = get_trashed_name(file_name, counter);
+    let mut info_file = loop {
+        let name_taken = std::fs::symlink_metadata(files_directory.join(&name)).is_ok();
+        if !name_taken {
+            match std::fs::File::options()
+                .write(true)
+                .create_new(true)
+                .open(get_info_path(trash_directory, &name))
+            {
+                Ok(info_file) => break info_file,
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => {}
+                Err(error) => return Err(error),
+            }
+        }
+        counter += 1;
+        name = get_trashed_name(file_name, counter);
+    };
+    let trashed_item = TrashedItem {
+        trash_directory: PathBuf::from(trash_directory),
+        name,
+        original_path: PathBuf::from(original_path),
+        deletion_date: Some(deletion_date.with_nanosecond(0).unwrap_or(deletion_date)),
+    };
+    let result = info_file
+        .write_all(info.as_bytes())
+        .and_then(|_| transfer(original_path, &trashed_item.get_trashed_path()));
+    if let Err(error) = result {
+        let _ = std::fs::remove_file(trashed_item.get_info_path());
+        return Err(error);
+    }
+    Ok(trashed_item)
+}
+
+// Files on another filesystem than the destination, such as the trash, are
+// copied over and removed.
+pub fn transfer(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(error) if error.kind() == ErrorKind::CrossesDevices => {
+            if std::fs::symlink_metadata(from)?.is_dir() {
+                return Err(error);
+            }
+            std::fs::copy(from, to)?;
+            if let Err(error) = std::fs::remove_file(from) {
+                let _ = std::fs::remove_file(to);
+                return Err(error);
+            }
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn format_info(original_path: &Path, deletion_date: NaiveDateTime) -> String {
+    format!(
+        "{}\n{}{}\n{}{}\n",
+        INFO_HEADER,
+        PATH_KEY,
+        encode_path(original_path),
+        DELETION_DATE_KEY,
+        deletion_date.format(DELETION_DATE_FORMAT)
+    )
+}
+
+fn parse_info(content: &str) -> Option<(PathBuf, Option<NaiveDateTime>)> {
+    let mut lines = content.lines();
+    if lines.next()?.trim() != INFO_HEADER {
+        return None;
+    }
+    let mut original_path = None;
+    let mut deletion_date = None;
+    for line in lines {
+        if let Some(path) = line.strip_prefix(PATH_KEY) {
+            original_path = Some(decode_path(path)?);
+        }
+        if let Some(date) = line.strip_prefix(DELETION_DATE_KEY) {
+            deletion_date = NaiveDateTime::parse_from_str(date, DELETION_DATE_FORMAT).ok();
+        }
+    }
+    Some((original_path?, deletion_date))
+}
+
+// Paths are written as escaped URLs. Everything except unreserved characters
+// and the separator is percent encoded.
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path_to_bytes(path) {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn decode_path(encoded: &str) -> Option<PathBuf> {
+    let mut bytes = Vec::new();
+    let mut encoded_bytes = encoded.bytes();
+    while let Some(byte) = encoded_bytes.next() {
+        if byte == b'%' {
+            let high = (encoded_bytes.next()? as char).to_digit(16)?;
+            let low = (encoded_bytes.next()? as char).to_digit(16)?;
+            bytes.push((high * 16 + low) as u8);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    Some(bytes_to_path(bytes))
+}
+
+#[cfg(unix)]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash_and_restore() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let directory = test_directory.path();
+        let trash = Trash::new(vec![directory.join("Trash")]);
+        let path = directory.join("photos").join("summer 2024.jpg");
+        std::fs::create_dir_all(directory.join("photos")).expect("Failed to create directory");
+        std::fs::write(&path, b"first").expect("Failed to write file");
+
+        let first = trash.trash(&path).expect("Failed to trash");
+        assert!(!path.exists());
+        assert_eq!(first.get_name(), "summer 2024.jpg");
+        let info = std::fs::read_to_string(first.get_info_path()).expect("Info missing");
+        assert!(info.starts_with(INFO_HEADER));
+        assert!(info.contains("summer%202024.jpg"));
+
+        std::fs::write(&path, b"second").expect("Failed to write file");
+        let second = trash.trash(&path).expect("Failed to trash");
+        assert_eq!(second.get_name(), "1_summer 2024.jpg");
+        assert_eq!(trash.list().expect("Failed to list").len(), 2);
+
+        trash.restore(&second).expect("Failed to restore");
+        assert_eq!(std::fs::read(&path).expect("File missing"), b"second");
+        let error = trash.restore(&first).expect_err("Original path is taken");
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(trash.list().expect("Failed to list"), vec![first.clone()]);
+
+        std::fs::remove_file(&path).expect("Failed to remove file");
+        std::fs::remove_dir(directory.join("photos")).expect("Failed to remove directory");
+        trash.restore(&first).expect("Failed to restore");
+        assert_eq!(std::fs::read(&path).expect("File missing"), b"first");
+        assert!(trash.list().expect("Failed to list").is_empty());
+    }
+
+    #[test]
+    fn test_trash_falls_back_to_next_directory() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let directory = test_directory.path();
+        // A file where the first trash directory should be
+        std::fs::write(directory.join("blocked"), b"").expect("Failed to write file");
+        let trash = Trash::new(vec![directory.join("blocked"), directory.join("fallback")]);
+        let path = directory.join("notes.txt");
+        std::fs::write(&path, b"notes").expect("Failed to write file");
+        let trashed_item = trash.trash(&path).expect("Failed to trash");
+        assert_eq!(
+            trashed_item.get_trash_directory(),
+            &directory.join("fallback")
+        );
+        assert_eq!(
+            std::fs::read(trashed_item.get_trashed_path()).expect("File missing"),
+            b"notes"
+        );
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let path = PathBuf::from("/home/verneri/100% done/ä?.txt");
+        let deletion_date =
+            NaiveDateTime::parse_from_str("2025-08-31T22:32:08", DELETION_DATE_FORMAT).unwrap();
+        let info = format_info(&path, deletion_date);
+        assert_eq!(
+            info,
+            "[Trash Info]\nPath=/home/verneri/100%25%20done/%C3%A4%3F.txt\nDeletionDate=2025-08-31T22:32:08\n"
+        );
+        assert_eq!(parse_info(&info), Some((path, Some(deletion_date))));
+        assert_eq!(parse_info("Path=/missing/header"), None);
+        assert_eq!(parse_info("[Trash Info]\nPath=/bad%2"), None);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::metadata::DateType;
+use crate::rules::CheckboxStates;
+use std::ffi::OsStr;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+pub fn convert_os_str_to_str(key: &OsStr) -> std::io::Result<&str> {
+    if let Some(key) = key.to_str() {
+        return Ok(key);
+    }
+    Err(std::io::Error::new(
+        ErrorKind::Other,
+        "Could not parse &OsStr to &str",
+    ))
+}
+
+pub fn convert_path_to_str<'a>(path: &'a PathBuf) -> std::io::Result<&'a str> {
+    if let Some(path) = path.to_str() {
+        return Ok(path);
+    }
+    Err(std::io::Error::new(
+        ErrorKind::Other,
+        "Coult not parse PathBuf to &str",
+    ))
+}
+
+pub fn just_rename_checked(checkbox_states: &CheckboxStates) -> bool {
+    if checkbox_states.insert_directory_name_to_file_name
+        || checkbox_states.insert_date_to_file_name
+        || checkbox_states.convert_uppercase_to_lowercase
+        || checkbox_states.replace_character
+        || checkbox_states.use_only_ascii
+        || checkbox_states.remove_original_file_name
+        || checkbox_states.add_custom_name
+    {
+        return true;
+    }
+    return false;
+}
+
+pub fn get_date_type(date_type: Option<DateType>) -> std::io::Result<DateType> {
+    if let Some(date_type) = date_type {
+        return Ok(date_type);
+    }
+    Err(std::io::Error::new(
+        ErrorKind::NotFound,
+        "Date type not specified.",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_just_rename_checked() {
+        let checkbox_states =
+            CheckboxStates::new(false, false, true, true, true, true, true, true, true);
+        assert_eq!(just_rename_checked(&checkbox_states), true);
+        let checkbox_states =
+            CheckboxStates::new(true, true, false, false, false, false, false, false, false);
+        assert_eq!(just_rename_checked(&checkbox_states), false);
+    }
+}
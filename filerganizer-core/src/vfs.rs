@@ -0,0 +1,705 @@
+use crate::trash::{self, Trash, TrashedItem};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Filesystem operations the organizing engine needs. RealFileSystem works on
+// the disk and MemoryFileSystem keeps everything in memory for tests.
+pub trait FileSystem {
+    // Paths of the entries directly inside path.
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>>;
+    // Symbolic links are not followed.
+    fn metadata(&self, path: &Path) -> std::io::Result<EntryMetadata>;
+    fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+    // Copies keep the permissions and the access and modification times.
+    fn copy(&self, from: &Path, to: &Path) -> std::io::Result<u64>;
+    fn hard_link(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+    // Creates a symbolic link at to that points to from.
+    fn symlink(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+    // Copy that shares the data with the original. Fails where the
+    // filesystem does not support it.
+    fn reflink(&self, from: &Path, to: &Path) -> std::io::Result<()>;
+    fn create_dir(&self, path: &Path) -> std::io::Result<()>;
+    // Removes a file or an empty directory.
+    fn remove(&self, path: &Path) -> std::io::Result<()>;
+    // Moves path to the first directory of trash that takes it.
+    fn trash(&self, trash: &Trash, path: &Path) -> std::io::Result<TrashedItem>;
+    // Puts a trashed item back to its original path without overwriting.
+    fn restore(&self, trashed_item: &TrashedItem) -> std::io::Result<()>;
+
+    fn exists(&self, path: &Path) -> std::io::Result<bool> {
+        match self.metadata(path) {
+            Ok(_) => Ok(true),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryMetadata {
+    is_dir: bool,
+    is_file: bool,
+    is_symlink: bool,
+    len: u64,
+    readonly: bool,
+    created: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+}
+
+impl EntryMetadata {
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.is_file
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.is_symlink
+    }
+
+    pub fn get_len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    pub fn get_created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+    pub fn get_accessed(&self) -> Option<SystemTime> {
+        self.accessed
+    }
+
+    pub fn get_modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(path)?.flatten() {
+            paths.push(entry.path());
+        }
+        Ok(paths)
+    }
+
+    fn metadata(&self, path: &Path) -> std::io::Result<EntryMetadata> {
+        let metadata = std::fs::symlink_metadata(path)?;
+        Ok(EntryMetadata {
+            is_dir: metadata.is_dir(),
+            is_file: metadata.is_file(),
+            is_symlink: metadata.file_type().is_symlink(),
+            len: metadata.len(),
+            readonly: metadata.permissions().readonly(),
+            created: metadata.created().ok(),
+            accessed: metadata.accessed().ok(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        std::fs::rename(from, to)
+    }
+
+    // std::fs::copy keeps the permissions but not the times.
+    fn copy(&self, from: &Path, to: &Path) -> std::io::Result<u64> {
+        let metadata = std::fs::metadata(from)?;
+        let len = std::fs::copy(from, to)?;
+        filetime::set_file_times(
+            to,
+            filetime::FileTime::from_last_access_time(&metadata),
+            filetime::FileTime::from_last_modification_time(&metadata),
+        )?;
+        Ok(len)
+    }
+
+    fn hard_link(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        std::fs::hard_link(from, to)
+    }
+
+    #[cfg(unix)]
+    fn symlink(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        std::os::unix::fs::symlink(from, to)
+    }
+
+    #[cfg(windows)]
+    fn symlink(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        std::os::windows::fs::symlink_file(from, to)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn symlink(&self, _from: &Path, _to: &Path) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            ErrorKind::Unsupported,
+            "Symbolic links are not supported on this platform.",
+        ))
+    }
+
+    fn reflink(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        reflink_copy::reflink(from, to)
+    }
+
+    fn create_dir(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::create_dir(path)
+    }
+
+    fn remove(&self, path: &Path) -> std::io::Result<()> {
+        if std::fs::symlink_metadata(path)?.is_dir() {
+            std::fs::remove_dir(path)
+        } else {
+            std::fs::remove_file(path)
+        }
+    }
+
+    fn trash(&self, trash: &Trash, path: &Path) -> std::io::Result<TrashedItem> {
+        trash.trash(path)
+    }
+
+    fn restore(&self, trashed_item: &TrashedItem) -> std::io::Result<()> {
+        Trash::new(vec![PathBuf::from(trashed_item.get_trash_directory())])
+            .restore(trashed_item)
+            .map(|_| ())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MemoryEntry {
+    // None for directories
+    contents: Option<Vec<u8>>,
+    link_target: Option<PathBuf>,
+    readonly: bool,
+    created: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+}
+
+impl MemoryEntry {
+    fn new(contents: Option<Vec<u8>>) -> Self {
+        Self {
+            contents,
+            link_target: None,
+            readonly: false,
+            created: Some(SystemTime::UNIX_EPOCH),
+            accessed: Some(SystemTime::UNIX_EPOCH),
+            modified: Some(SystemTime::UNIX_EPOCH),
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        self.contents.is_none()
+    }
+}
+
+// Filesystem kept in memory. Paths are absolute and the root "/" always
+// exists. Failures can be injected for paths to test error handling.
+#[derive(Debug)]
+pub struct MemoryFileSystem {
+    entries: RefCell<BTreeMap<PathBuf, MemoryEntry>>,
+    failures: RefCell<BTreeMap<PathBuf, ErrorKind>>,
+}
+
+impl Default for MemoryFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        let mut entries = BTreeMap::new();
+        entries.insert(PathBuf::from("/"), MemoryEntry::new(None));
+        Self {
+            entries: RefCell::new(entries),
+            failures: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    // Creates the directory and its missing parents.
+    pub fn create_dir_all(&self, path: &Path) {
+        let mut entries = self.entries.borrow_mut();
+        for ancestor in path.ancestors() {
+            entries
+                .entry(PathBuf::from(ancestor))
+                .or_insert_with(|| MemoryEntry::new(None));
+        }
+    }
+
+    // Writes a file with its timestamps at the Unix epoch, creating missing
+    // parent directories.
+    pub fn write_file(&self, path: &Path, contents: &[u8]) {
+        if let Some(parent) = path.parent() {
+            self.create_dir_all(parent);
+        }
+        self.entries.borrow_mut().insert(
+            PathBuf::from(path),
+            MemoryEntry::new(Some(contents.to_vec())),
+        );
+    }
+
+    // Symbolic links are followed.
+    pub fn read_file(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        match self.entries.borrow().get(path) {
+            Some(MemoryEntry {
+                link_target: Some(link_target),
+                ..
+            }) => self.read_file(link_target),
+            Some(MemoryEntry {
+                contents: Some(contents),
+                ..
+            }) => Ok(contents.clone()),
+            Some(_) => Err(is_a_directory(path)),
+            None => Err(not_found(path)),
+        }
+    }
+
+    pub fn read_link(&self, path: &Path) -> std::io::Result<PathBuf> {
+        match self.entries.borrow().get(path) {
+            Some(MemoryEntry {
+                link_target: Some(link_target),
+                ..
+            }) => Ok(PathBuf::from(link_target)),
+            Some(_) => Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a symbolic link.", path.display()),
+            )),
+            None => Err(not_found(path)),
+        }
+    }
+
+    pub fn set_times(
+        &self,
+        path: &Path,
+        created: Option<SystemTime>,
+        accessed: Option<SystemTime>,
+        modified: Option<SystemTime>,
+    ) -> std::io::Result<()> {
+        let mut entries = self.entries.borrow_mut();
+        let entry = entries.get_mut(path).ok_or_else(|| not_found(path))?;
+        entry.created = created;
+        entry.accessed = accessed;
+        entry.modified = modified;
+        Ok(())
+    }
+
+    pub fn set_readonly(&self, path: &Path, readonly: bool) -> std::io::Result<()> {
+        let mut entries = self.entries.borrow_mut();
+        let entry = entries.get_mut(path).ok_or_else(|| not_found(path))?;
+        entry.readonly = readonly;
+        Ok(())
+    }
+
+    // Every following read_dir, rename, copy, link, create_dir and remove
+    // that touches path fails with kind.
+    pub fn inject_failure(&self, path: &Path, kind: ErrorKind) {
+        self.failures.borrow_mut().insert(PathBuf::from(path), kind);
+    }
+
+    pub fn clear_failures(&self) {
+        self.failures.borrow_mut().clear();
+    }
+
+    fn check_failure(&self, path: &Path) -> std::io::Result<()> {
+        match self.failures.borrow().get(path) {
+            Some(kind) => Err(std::io::Error::new(
+                *kind,
+                format!("Injected failure for {}.", path.display()),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    // Adds entry at to the way a new file is added: the parent has to exist
+    // and nothing may be in the way.
+    fn insert_new_entry(&self, to: &Path, entry: MemoryEntry) -> std::io::Result<()> {
+        self.check_failure(to)?;
+        let mut entries = self.entries.borrow_mut();
+        Self::check_parent_directory(&entries, to)?;
+        if entries.contains_key(to) {
+            return Err(std::io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists.", to.display()),
+            ));
+        }
+        entries.insert(PathBuf::from(to), entry);
+        Ok(())
+    }
+
+    fn get_file_entry(&self, path: &Path) -> std::io::Result<MemoryEntry> {
+        self.check_failure(path)?;
+        let entries = self.entries.borrow();
+        let entry = entries.get(path).ok_or_else(|| not_found(path))?;
+        if entry.is_dir() {
+            return Err(is_a_directory(path));
+        }
+        Ok(entry.clone())
+    }
+
+    fn check_parent_directory(
+        entries: &BTreeMap<PathBuf, MemoryEntry>,
+        path: &Path,
+    ) -> std::io::Result<()> {
+        let parent = path.parent().ok_or_else(|| not_found(path))?;
+        match entries.get(parent) {
+            Some(entry) if entry.is_dir() => Ok(()),
+            Some(_) => Err(not_a_directory(parent)),
+            None => Err(not_found(parent)),
+        }
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+        self.check_failure(path)?;
+        let entries = self.entries.borrow();
+        match entries.get(path) {
+            Some(entry) if entry.is_dir() => Ok(entries
+                .keys()
+                .filter(|entry_path| entry_path.parent() == Some(path))
+                .cloned()
+                .collect()),
+            Some(_) => Err(not_a_directory(path)),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> std::io::Result<EntryMetadata> {
+        let entries = self.entries.borrow();
+        let entry = entries.get(path).ok_or_else(|| not_found(path))?;
+        Ok(EntryMetadata {
+            is_dir: entry.is_dir(),
+            is_file: !entry.is_dir() && entry.link_target.is_none(),
+            is_symlink: entry.link_target.is_some(),
+            len: entry
+                .contents
+                .as_ref()
+                .map_or(0, |contents| contents.len() as u64),
+            readonly: entry.readonly,
+            created: entry.created,
+            accessed: entry.accessed,
+            modified: entry.modified,
+        })
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        self.check_failure(from)?;
+        self.check_failure(to)?;
+        let mut entries = self.entries.borrow_mut();
+        let entry = entries.get(from).ok_or_else(|| not_found(from))?;
+        Self::check_parent_directory(&entries, to)?;
+        if from == to {
+            return Ok(());
+        }
+        if entry.is_dir() && to.starts_with(from) {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Cannot move {} inside itself.", from.display()),
+            ));
+        }
+        if let Some(existing) = entries.get(to) {
+            if existing.is_dir() {
+                if !entry.is_dir() {
+                    return Err(is_a_directory(to));
+                }
+                if entries.keys().any(|path| path.parent() == Some(to)) {
+                    return Err(std::io::Error::new(
+                        ErrorKind::DirectoryNotEmpty,
+                        format!("{} is not empty.", to.display()),
+                    ));
+                }
+            } else if entry.is_dir() {
+                return Err(not_a_directory(to));
+            }
+        }
+        let moved: Vec<PathBuf> = entries
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+        for path in moved {
+            if let Some(moved_entry) = entries.remove(&path) {
+                let relative_path = path.strip_prefix(from).unwrap_or(Path::new(""));
+                entries.insert(to.join(relative_path), moved_entry);
+            }
+        }
+        Ok(())
+    }
+
+    // Copies keep the timestamps of the original file.
+    fn copy(&self, from: &Path, to: &Path) -> std::io::Result<u64> {
+        self.check_failure(from)?;
+        self.check_failure(to)?;
+        let mut entries = self.entries.borrow_mut();
+        let entry = entries.get(from).ok_or_else(|| not_found(from))?.clone();
+        if entry.is_dir() {
+            return Err(is_a_directory(from));
+        }
+        Self::check_parent_directory(&entries, to)?;
+        if entries.get(to).is_some_and(|existing| existing.is_dir()) {
+            return Err(is_a_directory(to));
+        }
+        let len = entry
+            .contents
+            .as_ref()
+            .map_or(0, |contents| contents.len() as u64);
+        entries.insert(PathBuf::from(to), entry);
+        Ok(len)
+    }
+
+    // Hard links and reflinks are stored as copies, the entries do not share
+    // their contents.
+    fn hard_link(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        let entry = self.get_file_entry(from)?;
+        self.insert_new_entry(to, entry)
+    }
+
+    fn symlink(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        let mut entry = MemoryEntry::new(Some(Vec::from(from.as_os_str().as_encoded_bytes())));
+        entry.link_target = Some(PathBuf::from(from));
+        self.insert_new_entry(to, entry)
+    }
+
+    fn reflink(&self, from: &Path, to: &Path) -> std::io::Result<()> {
+        let entry = self.get_file_entry(from)?;
+        self.insert_new_entry(to, entry)
+    }
+
+    fn create_dir(&self, path: &Path) -> std::io::Result<()> {
+        self.check_failure(path)?;
+        let mut entries = self.entries.borrow_mut();
+        if entries.contains_key(path) {
+            return Err(std::io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists.", path.display()),
+            ));
+        }
+        Self::check_parent_directory(&entries, path)?;
+        entries.insert(PathBuf::from(path), MemoryEntry::new(None));
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> std::io::Result<()> {
+        self.check_failure(path)?;
+        let mut entries = self.entries.borrow_mut();
+        if !entries.contains_key(path) {
+            return Err(not_found(path));
+        }
+        if entries
+            .keys()
+            .any(|entry_path| entry_path.parent() == Some(path))
+        {
+            return Err(std::io::Error::new(
+                ErrorKind::DirectoryNotEmpty,
+                format!("{} is not empty.", path.display()),
+            ));
+        }
+        entries.remove(path);
+        Ok(())
+    }
+
+    // Trashed entries are kept under the files directory of the first trash
+    // directory. No info files are written.
+    fn trash(&self, trash: &Trash, path: &Path) -> std::io::Result<TrashedItem> {
+        self.metadata(path)?;
+        let trash_directory = trash.get_directories().first().ok_or_else(|| {
+            std::io::Error::new(ErrorKind::NotFound, "No trash directory found.")
+        })?;
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "Path has no file name."))?;
+        let mut counter = 0;
+        let trashed_item = loop {
+            let trashed_item = TrashedItem::new(
+                PathBuf::from(trash_directory),
+                trash::get_trashed_name(file_name, counter),
+                PathBuf::from(path),
+                None,
+            );
+            if !self.exists(&trashed_item.get_trashed_path())? {
+                break trashed_item;
+            }
+            counter += 1;
+        };
+        let trashed_path = trashed_item.get_trashed_path();
+        if let Some(parent) = trashed_path.parent() {
+            self.create_dir_all(parent);
+        }
+        self.rename(path, &trashed_path)?;
+        Ok(trashed_item)
+    }
+
+    fn restore(&self, trashed_item: &TrashedItem) -> std::io::Result<()> {
+        let original_path = trashed_item.get_original_path();
+        if self.exists(original_path)? {
+            return Err(std::io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists.", original_path.display()),
+            ));
+        }
+        if let Some(parent) = original_path.parent() {
+            self.create_dir_all(parent);
+        }
+        self.rename(&trashed_item.get_trashed_path(), original_path)
+    }
+}
+
+fn not_found(path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::NotFound,
+        format!("{} not found.", path.display()),
+    )
+}
+
+fn not_a_directory(path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::NotADirectory,
+        format!("{} is not a directory.", path.display()),
+    )
+}
+
+fn is_a_directory(path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::IsADirectory,
+        format!("{} is a directory.", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_file_system_operations() {
+        let file_system = MemoryFileSystem::new();
+        file_system.write_file(Path::new("/home/notes.txt"), b"notes");
+        assert!(file_system.metadata(Path::new("/home")).unwrap().is_dir());
+        assert_eq!(
+            file_system.read_dir(Path::new("/home")).unwrap(),
+            vec![PathBuf::from("/home/notes.txt")]
+        );
+
+        file_system
+            .create_dir(Path::new("/home/text"))
+            .expect("Failed to create directory");
+        let error = file_system
+            .create_dir(Path::new("/missing/text"))
+            .expect_err("Parent should be missing");
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+
+        file_system
+            .rename(
+                Path::new("/home/notes.txt"),
+                Path::new("/home/text/notes.txt"),
+            )
+            .expect("Failed to rename");
+        assert_eq!(
+            file_system
+                .copy(
+                    Path::new("/home/text/notes.txt"),
+                    Path::new("/home/copy.txt")
+                )
+                .expect("Failed to copy"),
+            5
+        );
+        file_system
+            .rename(Path::new("/home/text"), Path::new("/home/moved"))
+            .expect("Failed to rename directory");
+        assert_eq!(
+            file_system
+                .read_file(Path::new("/home/moved/notes.txt"))
+                .expect("File missing"),
+            b"notes"
+        );
+        assert!(!file_system
+            .exists(Path::new("/home/text/notes.txt"))
+            .unwrap());
+
+        let error = file_system
+            .remove(Path::new("/home/moved"))
+            .expect_err("Directory is not empty");
+        assert_eq!(error.kind(), ErrorKind::DirectoryNotEmpty);
+        file_system
+            .remove(Path::new("/home/moved/notes.txt"))
+            .expect("Failed to remove file");
+        file_system
+            .remove(Path::new("/home/moved"))
+            .expect("Failed to remove directory");
+
+        file_system.inject_failure(Path::new("/home/copy.txt"), ErrorKind::PermissionDenied);
+        let error = file_system
+            .rename(Path::new("/home/copy.txt"), Path::new("/home/renamed.txt"))
+            .expect_err("Failure was injected");
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+        assert!(file_system.exists(Path::new("/home/copy.txt")).unwrap());
+    }
+
+    #[test]
+    fn test_memory_file_system_links() {
+        let file_system = MemoryFileSystem::new();
+        file_system.write_file(Path::new("/home/notes.txt"), b"notes");
+        file_system
+            .symlink(Path::new("/home/notes.txt"), Path::new("/home/link.txt"))
+            .expect("Failed to create symbolic link");
+        let metadata = file_system.metadata(Path::new("/home/link.txt")).unwrap();
+        assert!(metadata.is_symlink());
+        assert!(!metadata.is_file());
+        assert_eq!(
+            file_system.read_link(Path::new("/home/link.txt")).unwrap(),
+            PathBuf::from("/home/notes.txt")
+        );
+        assert_eq!(
+            file_system.read_file(Path::new("/home/link.txt")).unwrap(),
+            b"notes"
+        );
+
+        file_system
+            .hard_link(Path::new("/home/notes.txt"), Path::new("/home/hard.txt"))
+            .expect("Failed to create hard link");
+        assert!(file_system
+            .metadata(Path::new("/home/hard.txt"))
+            .unwrap()
+            .is_file());
+        let error = file_system
+            .reflink(Path::new("/home/notes.txt"), Path::new("/home/hard.txt"))
+            .expect_err("Destination exists");
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        let error = file_system
+            .hard_link(Path::new("/home"), Path::new("/other"))
+            .expect_err("Directories cannot be linked");
+        assert_eq!(error.kind(), ErrorKind::IsADirectory);
+    }
+
+    #[test]
+    fn test_real_file_system_copy_keeps_times() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let directory = test_directory.path();
+        let original = directory.join("original.txt");
+        let copy = directory.join("copy.txt");
+        std::fs::write(&original, b"original").expect("Failed to write file");
+        let modified = filetime::FileTime::from_unix_time(1_700_000_000, 0);
+        filetime::set_file_mtime(&original, modified).expect("Failed to set time");
+
+        let file_system = RealFileSystem;
+        assert_eq!(
+            file_system.copy(&original, &copy).expect("Failed to copy"),
+            8
+        );
+        let metadata = std::fs::metadata(&copy).expect("Copy missing");
+        assert_eq!(
+            filetime::FileTime::from_last_modification_time(&metadata),
+            modified
+        );
+    }
+}
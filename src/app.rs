@@ -1,25 +1,50 @@
+use chrono::{DateTime, Local};
+use iced::keyboard::{Key, Modifiers};
 use iced::widget::Container;
-use iced::Task;
+use iced::{Point, Size, Task, Theme};
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::ffi::{OsStr, OsString};
 use std::fs::read_dir;
 use std::io::ErrorKind;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::usize;
 
-use crate::app_util::convert_os_str_to_str;
-use crate::directory::Directory;
-use crate::file::File;
-use crate::filesystem;
-use crate::layouts::{
-    CheckboxStates, DirectoryView, FileSelectedLocation, IndexPosition, Layout, ReplaceWith,
-    Replaceable,
+use crate::app_util;
+use crate::duplicates::{self, DuplicateAction, DuplicateGroup};
+use crate::file_list::{self, FileFilter, FileSort, SortColumn};
+use crate::keymap::{KeyAction, KeyBinding, Keymap, KEY_ACTIONS};
+use crate::layouts::{DirectoryView, FileSelectedLocation, Layout};
+use crate::locale::{self, tr, Language};
+use crate::path_completion::{self, PathCompletion};
+use crate::preview::{self, FileInspection};
+use crate::settings::{self, Settings, ThemeSetting};
+use crate::similar_images::{self, PerceptualHashType, SimilarImageGroup};
+use crate::thumbnails::{self, ThumbnailState};
+use crate::watch_folder::{self, WatchFolder, WatchState};
+use filerganizer_core::directory::{self, Directory};
+use filerganizer_core::file::File;
+use filerganizer_core::metadata::{DateType, Timezone};
+use filerganizer_core::organize_files::{self, RenamePreview};
+use filerganizer_core::rules::{
+    CheckboxStates, FilenameComponents, IndexPosition, ReplacableSelection, ReplaceWith,
+    Replaceable, Rules,
 };
-use crate::metadata::DateType;
-use crate::organize_files;
-use crate::save_directory;
-use crate::save_directory::SAVE_FILE_NAME;
-use crate::{app_util, directory};
+use filerganizer_core::sanitize::{self, SanitizeAction, SanitizeOptions, SanitizedName};
+use filerganizer_core::sanitize::TargetProfile;
+use filerganizer_core::save_directory;
+use filerganizer_core::save_directory::SAVE_FILE_NAME;
+use filerganizer_core::util::{self, convert_os_str_to_str};
+use filerganizer_core::filesystem::{self, DEFAULT_JUNK_FILE_NAMES};
+use filerganizer_core::flatten;
+use filerganizer_core::{CleanupOptions, Journal, Plan, PlannedMove, Progress, RealFileSystem};
+use filerganizer_core::TransferMode;
+use filerganizer_core::{Trash, TrashedItem};
+
+// A selected file with its new name and how the target filesystem would
+// rewrite it.
+pub type RenamePreviewRow = (RenamePreview, Option<SanitizedName>);
 
 pub struct App {
     home_directory_path: PathBuf,
@@ -34,136 +59,115 @@ pub struct App {
 
     directories_selected: HashSet<PathBuf>,
     directory_selected: Option<PathBuf>,
-    selected_directory_rules: Option<SelectedDirectoryRules>,
+    selected_directory_rules: Option<Rules>,
 
     multiple_selection: MultipleSelection,
+    file_sort: FileSort,
+    file_filter_input: String,
+    file_filter: FileFilter,
+    // Entry of the current directory keyboard actions apply to
+    focused_path: Option<PathBuf>,
+    file_inspection: Option<FileInspection>,
+    hashing_path: Option<PathBuf>,
+    // Entry under the mouse and the file a drag started from
+    hovered_path: Option<PathBuf>,
+    dragged_path: Option<PathBuf>,
+    drop_filter_input: String,
+    drop_filter: FileFilter,
+    settings: Settings,
+    // Sidebar entries next to the bookmarks and recent paths of the settings
+    user_directories: Vec<PathBuf>,
+    organized_directories: Vec<PathBuf>,
+    path_completion: Option<PathCompletion>,
+    // Theme of the theme setting, which can take a while to find out
+    theme: Theme,
+    settings_save_failed: bool,
+    keymap_inputs: BTreeMap<KeyAction, String>,
     files_selected: BTreeMap<OsString, File>,
+    // New names typed for single selected files, by their current name
+    rename_overrides: BTreeMap<OsString, String>,
+    rename_previews: Result<Vec<RenamePreviewRow>, String>,
     new_directory_name: String,
     checkbox_states: CheckboxStates,
     replaceable_options: Vec<Replaceable>,
     replace_with_options: [ReplaceWith; 2],
     replaceables: Vec<ReplacableSelection>,
     date_type_selected: Option<DateType>,
+    timezone: Timezone,
+    timezone_input: String,
+    sanitize_options: SanitizeOptions,
     filename_input: String,
     order_of_filename_components: Vec<FilenameComponents>,
     index_position: Option<IndexPosition>,
     files_organized: BTreeMap<OsString, File>,
+    organized_directories_to_sanitize: BTreeMap<PathBuf, SanitizeOptions>,
+    sanitized_names: Vec<SanitizedName>,
+    transfer_mode: TransferMode,
+    remove_empty_directories: bool,
+    cleanup_boundary_input: String,
+    junk_file_names_input: String,
+    selected_directory_plan: Option<(DirectoryOperation, Plan)>,
+    flatten_target_input: String,
+    strip_rename_prefixes: bool,
+    commit_state: Option<CommitState>,
     files_have_been_organized: bool,
+    last_journal: Option<Journal>,
+    last_organized_directory: Option<PathBuf>,
+    duplicate_groups: Vec<DuplicateGroup>,
+    similar_image_groups: Vec<SimilarImageGroup>,
+    finding_duplicates: bool,
+    finding_similar_images: bool,
+    perceptual_hash_type: PerceptualHashType,
+    similarity_threshold: u32,
+
+    watch_folders: Vec<WatchFolder>,
+    // Shared with the poll running on a background thread
+    watch_state: Arc<Mutex<WatchState>>,
+    polling_watch_folders: bool,
+    watch_log: Vec<String>,
+    watch_inbox_input: String,
+    watch_organized_directory_input: String,
+    watch_ignore_input: String,
+
+    trashed_items: Vec<TrashedItem>,
+    // The modification time a thumbnail was made for, so files that change
+    // are thumbnailed again.
+    thumbnails: BTreeMap<PathBuf, (Option<DateTime<Local>>, ThumbnailState)>,
 }
 
-#[derive(Debug)]
-pub struct SelectedDirectoryRules {
-    checkbox_states: CheckboxStates,
-    replaceables: Vec<ReplacableSelection>,
-    date_type_selected: Option<DateType>,
-    order_of_filename_components: Vec<FilenameComponents>,
-    index_position: Option<IndexPosition>,
-    filename_input: String,
-}
-
-impl SelectedDirectoryRules {
-    fn from(
-        checkbox_states: CheckboxStates,
-        replaceables: Vec<ReplacableSelection>,
-        date_type_selected: Option<DateType>,
-        order_of_filename_components: Vec<FilenameComponents>,
-        index_position: Option<IndexPosition>,
-        filename_input: String,
-    ) -> Self {
-        Self {
-            checkbox_states,
-            replaceables,
-            date_type_selected,
-            order_of_filename_components,
-            index_position,
-            filename_input,
-        }
-    }
-
-    pub fn get_checkbox_states(&self) -> &CheckboxStates {
-        &self.checkbox_states
-    }
-
-    pub fn get_replaceables(&self) -> &Vec<ReplacableSelection> {
-        &self.replaceables
-    }
-
-    pub fn get_date_type_selected(&self) -> &Option<DateType> {
-        &self.date_type_selected
-    }
-
-    pub fn get_order_of_filename_components(&self) -> &Vec<FilenameComponents> {
-        &self.order_of_filename_components
-    }
-
-    pub fn get_index_position(&self) -> &Option<IndexPosition> {
-        &self.index_position
-    }
-
-    pub fn get_custom_filename(&self) -> &str {
-        &self.filename_input.as_str()
-    }
+// Commit, or the undo of the last commit, running on its own thread.
+struct CommitState {
+    cancel: Arc<AtomicBool>,
+    progress: Option<Progress>,
+    // None when flattening, which has no rules to save.
+    path_to_directory: Option<PathBuf>,
+    undoing: bool,
 }
 
-#[derive(Debug)]
-pub struct ReplacableSelection {
-    replaceable_selected: Option<Replaceable>,
-    replace_with_selected: Option<ReplaceWith>,
+// Operations planned for the selected directory as a whole.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DirectoryOperation {
+    Flatten,
+    ReapplyRules,
 }
 
-impl ReplacableSelection {
-    pub fn new() -> Self {
-        Self {
-            replace_with_selected: Some(ReplaceWith::Nothing),
-            replaceable_selected: None,
-        }
-    }
-
-    pub fn from(replaceable: Option<Replaceable>, replace_with: Option<ReplaceWith>) -> Self {
-        Self {
-            replaceable_selected: replaceable,
-            replace_with_selected: replace_with,
-        }
-    }
-
-    pub fn get_replaceable_selected(&self) -> Option<Replaceable> {
-        self.replaceable_selected
-    }
-
-    pub fn get_replace_with_selected(&self) -> Option<ReplaceWith> {
-        self.replace_with_selected
-    }
+#[derive(Debug, Clone)]
+pub enum CommitEvent {
+    Progress(Progress),
+    // The error of the cleanup that follows a successful commit comes
+    // separately, since the files are already in place by then.
+    Finished(Result<Journal, String>, Option<String>),
+    UndoFinished(Result<(), String>),
 }
 
 struct MultipleSelection {
     file_name: String,
-    file_index: usize,
 }
 
 impl MultipleSelection {
     fn new() -> Self {
         MultipleSelection {
             file_name: String::new(),
-            file_index: 0,
-        }
-    }
-}
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum FilenameComponents {
-    Date,
-    OriginalFilename,
-    DirectoryName,
-    CustomFilename,
-}
-
-impl FilenameComponents {
-    pub fn convert_to_text(&self) -> &str {
-        match self {
-            FilenameComponents::CustomFilename => "Custom filename",
-            FilenameComponents::Date => "Date",
-            FilenameComponents::DirectoryName => "Directory name",
-            FilenameComponents::OriginalFilename => "Original_filename",
         }
     }
 }
@@ -185,18 +189,69 @@ impl Default for App {
             directory_selected: None,
             selected_directory_rules: None,
             multiple_selection: MultipleSelection::new(),
+            file_sort: FileSort::default(),
+            file_filter_input: String::new(),
+            file_filter: FileFilter::All,
+            focused_path: None,
+            file_inspection: None,
+            hashing_path: None,
+            hovered_path: None,
+            dragged_path: None,
+            drop_filter_input: String::new(),
+            drop_filter: FileFilter::All,
+            settings: Settings::default(),
+            user_directories: Vec::new(),
+            organized_directories: Vec::new(),
+            path_completion: None,
+            theme: Theme::Dark,
+            settings_save_failed: false,
+            keymap_inputs: BTreeMap::new(),
             files_selected: BTreeMap::new(),
+            rename_overrides: BTreeMap::new(),
+            rename_previews: Ok(Vec::new()),
             new_directory_name: String::new(),
             checkbox_states: CheckboxStates::default(),
             replaceable_options: vec![Replaceable::Dash, Replaceable::Space, Replaceable::Comma],
             replace_with_options: [ReplaceWith::Underscore, ReplaceWith::Nothing],
             replaceables: Vec::new(),
             date_type_selected: None,
+            timezone: Timezone::Local,
+            timezone_input: String::new(),
+            sanitize_options: SanitizeOptions::default(),
             filename_input: String::new(),
             order_of_filename_components: Vec::new(),
             index_position: None,
             files_organized: BTreeMap::new(),
+            organized_directories_to_sanitize: BTreeMap::new(),
+            sanitized_names: Vec::new(),
+            transfer_mode: TransferMode::Move,
+            remove_empty_directories: false,
+            cleanup_boundary_input: String::new(),
+            junk_file_names_input: DEFAULT_JUNK_FILE_NAMES.join(", "),
+            selected_directory_plan: None,
+            flatten_target_input: String::new(),
+            strip_rename_prefixes: false,
+            commit_state: None,
             files_have_been_organized: false,
+            last_journal: None,
+            last_organized_directory: None,
+            duplicate_groups: Vec::new(),
+            similar_image_groups: Vec::new(),
+            finding_duplicates: false,
+            finding_similar_images: false,
+            perceptual_hash_type: PerceptualHashType::Difference,
+            similarity_threshold: similar_images::DEFAULT_SIMILARITY_THRESHOLD,
+
+            watch_folders: Vec::new(),
+            watch_state: Arc::new(Mutex::new(WatchState::new())),
+            polling_watch_folders: false,
+            watch_log: Vec::new(),
+            watch_inbox_input: String::new(),
+            watch_organized_directory_input: String::new(),
+            watch_ignore_input: String::new(),
+
+            trashed_items: Vec::new(),
+            thumbnails: BTreeMap::new(),
         }
     }
 }
@@ -212,42 +267,211 @@ pub enum Message {
 
     SelectDirectory(PathBuf),
     SelectFile(FileSelectedLocation),
-    SelectMultipleFiles(usize, FileSelectedLocation),
+    SelectMultipleFiles(FileSelectedLocation),
+    SortFilesBy(SortColumn),
+    FileFilterInput(String),
+    ThumbnailCreated(PathBuf, Option<DateTime<Local>>, ThumbnailState),
+    FocusFile(PathBuf),
+    FileInspected(PathBuf, Result<FileInspection, String>),
+    HashFocusedFile,
+    FileHashed(PathBuf, Result<String, String>),
+    ClosePreview,
+    FileDropped(PathBuf),
+    DropFilterInput(String),
+    EntryHovered(PathBuf),
+    EntryHoverEnded(PathBuf),
+    EntryPressed(PathBuf),
+    EntryReleased(PathBuf),
+    MouseReleased,
     InputNewDirectoryName(String),
     CreateDirectoryWithSelectedFiles,
     RenameFiles,
+    RenameOverrideInput(OsString, String),
     CheckboxToggled(bool, usize),
     SelectReplaceable(Replaceable, usize),
     SelectReplaceWith(ReplaceWith, usize),
     AddNewReplaceable,
     RemoveReplaceable(usize),
     DateTypeSelected(DateType),
+    TimezoneInput(String),
+    TargetProfileSelected(TargetProfile),
+    RejectInvalidNamesToggled(bool),
     InsertFilesToSelectedDirectory,
     SwapFileNameComponents(usize),
     FilenameInput(String),
     IndexPositionSelected(IndexPosition),
+    TransferModeSelected(TransferMode),
+    RemoveEmptyDirectoriesToggled(bool),
+    CleanupBoundaryInput(String),
+    JunkFileNamesInput(String),
+    FlattenTargetInput(String),
+    StripRenamePrefixesToggled(bool),
+    FlattenSelectedDirectory,
+    ReapplySelectedDirectoryRules,
+    CancelSelectedDirectoryPlan,
     Commit,
+    CommitProgress(CommitEvent),
+    CancelCommit,
     TabKeyPressed,
+    PathCompletionSelected(usize),
+    KeyPressed(Key, Modifiers),
+    KeymapInput(KeyAction, String),
+    SaveKeymap,
+    ResetKeymap,
+    FindDuplicates,
+    DuplicatesFound(Result<(Vec<DuplicateGroup>, Vec<PathBuf>), String>),
+    ResolveDuplicates(usize, DuplicateAction),
+    CloseDuplicates,
+    FindSimilarImages,
+    SimilarImagesFound(Vec<SimilarImageGroup>),
+    PerceptualHashTypeSelected(PerceptualHashType),
+    SimilarityThresholdChanged(u32),
+    KeepHighestResolution(usize),
+    CloseSimilarImages,
+
+    WatchInboxInput(String),
+    WatchOrganizedDirectoryInput(String),
+    WatchIgnoreInput(String),
+    AddWatchFolder,
+    ToggleWatchFolderPaused(usize),
+    RemoveWatchFolder(usize),
+    WatchTick,
+    WatchFoldersPolled(Vec<String>),
+    UndoCommit,
+    RestoreTrashedItem(usize),
+    ThemeSelected(ThemeSetting),
+    DefaultDirectoryViewSelected(DirectoryView),
+    LanguageSelected(Language),
+    JumpToPath(PathBuf),
+    AddBookmark,
+    RemoveBookmark(usize),
+    WindowResized(Size),
+    WindowMoved(Point),
+    WindowFocused,
     Exit,
 }
 
+impl Message {
+    // Only messages that can change the selection, the rules, the overrides
+    // or the current path recompute the rename previews, since computing them
+    // checks the target filesystem. Keyboard and mouse actions that select
+    // files go through SelectFile.
+    fn affects_rename_previews(&self) -> bool {
+        matches!(
+            self,
+            Message::SwitchLayout(_)
+                | Message::SearchPath(_)
+                | Message::MoveInExternalDirectory(_)
+                | Message::DropDownDirectory(_)
+                | Message::SelectDirectory(_)
+                | Message::SelectFile(_)
+                | Message::SelectMultipleFiles(_)
+                | Message::FileDropped(_)
+                | Message::InputNewDirectoryName(_)
+                | Message::CreateDirectoryWithSelectedFiles
+                | Message::RenameFiles
+                | Message::RenameOverrideInput(_, _)
+                | Message::CheckboxToggled(_, _)
+                | Message::SelectReplaceable(_, _)
+                | Message::SelectReplaceWith(_, _)
+                | Message::AddNewReplaceable
+                | Message::RemoveReplaceable(_)
+                | Message::DateTypeSelected(_)
+                | Message::TimezoneInput(_)
+                | Message::TargetProfileSelected(_)
+                | Message::InsertFilesToSelectedDirectory
+                | Message::SwapFileNameComponents(_)
+                | Message::FilenameInput(_)
+                | Message::IndexPositionSelected(_)
+                | Message::CommitProgress(CommitEvent::Finished(_, _))
+                | Message::CommitProgress(CommitEvent::UndoFinished(_))
+                | Message::ResolveDuplicates(_, _)
+                | Message::KeepHighestResolution(_)
+        )
+    }
+
+    // An entry removed from the view does not report that the mouse left
+    // it, so the hovered entry is forgotten whenever the view can change.
+    // Moving the mouse over an entry reports it again.
+    fn keeps_hovered_path(&self) -> bool {
+        matches!(
+            self,
+            Message::ThumbnailCreated(_, _, _)
+                | Message::FileInspected(_, _)
+                | Message::EntryHovered(_)
+                | Message::EntryHoverEnded(_)
+                | Message::EntryPressed(_)
+                | Message::CommitProgress(CommitEvent::Progress(_))
+                | Message::WatchTick
+                | Message::WatchFoldersPolled(_)
+                | Message::WindowMoved(_)
+        )
+    }
+}
+
 impl App {
+    pub fn new() -> (Self, Task<Message>) {
+        let mut app = App::default();
+        if let Some(home_directory_path) = directory::system_dir::get_home_directory() {
+            app.home_directory_path = home_directory_path;
+        }
+        app.user_directories =
+            directory::system_dir::get_user_directories(&app.home_directory_path);
+        if let Err(error) = app.load_watch_folders() {
+            app.error = error.to_string();
+        }
+        match settings::read_settings(&app.home_directory_path) {
+            Ok(settings) => app.settings = settings,
+            Err(error) => app.error = error.to_string(),
+        }
+        app.theme = app.settings.get_theme().get_theme();
+        app.directory_view = app.settings.get_directory_view();
+        locale::set_language(app.settings.get_language());
+        app.error = locale::translate(&app.error);
+        (app, Task::none())
+    }
+
     pub fn view(&self) -> Container<Message> {
         self.layout.get_layout(self)
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
+        let refresh_rename_previews = message.affects_rename_previews();
+        let keeps_hovered_path = message.keeps_hovered_path();
+        let previous_error = self.error.clone();
+        let task = self.handle_message(message);
+        // Errors are built in English and translated once when they are set.
+        if self.error != previous_error {
+            self.error = locale::translate(&self.error);
+        }
+        if refresh_rename_previews {
+            self.refresh_rename_previews();
+        }
+        if !keeps_hovered_path {
+            self.hovered_path = None;
+        }
+        task
+    }
+
+    fn handle_message(&mut self, message: Message) -> Task<Message> {
         self.error.clear();
         match message {
             Message::SwitchLayout(layout) => {
+                self.remember_last_path();
                 self.init_app_data();
                 if let Err(error) = self.switch_layout(&layout) {
                     self.error = error.to_string();
                 }
+                if matches!(layout, Layout::DirectorySelectionLayout) {
+                    self.load_organized_directories();
+                    self.open_last_path();
+                    return self.request_thumbnails();
+                }
                 Task::none()
             }
             Message::TextInput(text_input) => {
                 self.path_input = text_input;
+                self.path_completion = None;
                 Task::none()
             }
             Message::SearchPath(is_submit) => {
@@ -257,38 +481,52 @@ impl App {
                 if is_submit {
                     self.directories_selected.insert(self.path.clone());
                 }
-                Task::none()
+                self.request_thumbnails()
             }
-            Message::MoveInExternalDirectory(external) => {
-                if let Err(error) = self.move_in_external_directory(&external) {
+            Message::JumpToPath(path) => {
+                self.path_input = path.display().to_string();
+                self.update(Message::SearchPath(true))
+            }
+            Message::AddBookmark => {
+                self.settings.add_bookmark(PathBuf::from(&self.path));
+                let result = settings::write_settings(&self.home_directory_path, &self.settings);
+                if let Err(error) = result {
                     self.error = error.to_string();
                 }
                 Task::none()
             }
-            Message::DropDownDirectory(path_to_selected_directory) => {
-                if let Err(error) = self.select_drop_down_directory(&path_to_selected_directory) {
+            Message::RemoveBookmark(index) => {
+                self.settings.remove_bookmark(index);
+                let result = settings::write_settings(&self.home_directory_path, &self.settings);
+                if let Err(error) = result {
                     self.error = error.to_string();
                 }
-                self.directory_selected = None;
                 Task::none()
             }
-            Message::SwitchDirectoryView(directory_view) => match directory_view {
-                DirectoryView::List => {
-                    if let DirectoryView::DropDown = self.directory_view {
-                        self.directory_view = directory_view;
-                    }
-                    Task::none()
+            Message::MoveInExternalDirectory(external) => {
+                match self.move_in_external_directory(&external) {
+                    Ok(()) => self.record_recent_path(),
+                    Err(error) => self.error = error.to_string(),
                 }
-                DirectoryView::DropDown => {
-                    if let DirectoryView::List = self.directory_view {
-                        self.directory_view = directory_view;
-                    }
-                    Task::none()
+                self.request_thumbnails()
+            }
+            Message::DropDownDirectory(path_to_selected_directory) => {
+                match self.select_drop_down_directory(&path_to_selected_directory) {
+                    Ok(()) => self.record_recent_path(),
+                    Err(error) => self.error = error.to_string(),
                 }
-            },
+                self.directory_selected = None;
+                self.selected_directory_plan = None;
+                self.request_thumbnails()
+            }
+            Message::SwitchDirectoryView(directory_view) => {
+                self.directory_view = directory_view;
+                self.request_thumbnails()
+            }
 
             Message::SelectDirectory(path_to_directory) => {
                 self.selected_directory_rules = None;
+                self.selected_directory_plan = None;
                 match self.directory_selected {
                     Some(ref current_selected) => {
                         if *current_selected == path_to_directory {
@@ -300,28 +538,14 @@ impl App {
                     None => self.directory_selected = Some(path_to_directory),
                 }
                 if let Some(ref current_selected) = self.directory_selected {
-                    match save_directory::read_directory_rules_from_file(
-                        &self.home_directory_path,
-                        &current_selected,
-                    ) {
-                        Ok((
-                            checkbox_states,
-                            date_type,
-                            index_position,
-                            replaceables,
-                            order_of_filename_components,
-                            custom_filename,
-                        )) => {
-                            self.selected_directory_rules = Some(SelectedDirectoryRules::from(
-                                checkbox_states,
-                                replaceables,
-                                date_type,
-                                order_of_filename_components,
-                                index_position,
-                                custom_filename,
-                            ));
-                        }
-                        Err(_) => {}
+                    if let Ok(rules) =
+                        Rules::read_from_save_file(&self.home_directory_path, current_selected)
+                    {
+                        // Files inserted to the directory use its timezone, so
+                        // the input shows it instead of the last one typed.
+                        self.timezone = rules.get_timezone();
+                        self.timezone_input = self.timezone.to_string();
+                        self.selected_directory_rules = Some(rules);
                     }
                 }
                 return Task::none();
@@ -352,10 +576,12 @@ impl App {
                     FileSelectedLocation::FromFilesSelected(origin_path) => {
                         let mut origin_dir_path = PathBuf::from(&origin_path);
                         origin_dir_path.pop();
-                        if let Some(origin_directory) =
-                            self.root.get_mut_directory_by_path(&origin_dir_path)
-                        {
-                            if let Some(files) = origin_directory.get_mut_files() {
+                        let origin_files = self
+                            .root
+                            .get_mut_directory_by_path(&origin_dir_path)
+                            .and_then(|origin_directory| origin_directory.get_mut_files().as_mut());
+                        match origin_files {
+                            Some(files) => {
                                 if let Some(file_name) = origin_path.iter().last() {
                                     if files.contains_key(file_name) {
                                         self.error = std::io::Error::new(
@@ -372,12 +598,124 @@ impl App {
                                     }
                                 }
                             }
+                            // Dropped files can come from directories that
+                            // have not been read into the tree
+                            None => {
+                                if let Some(file_name) = origin_path.file_name() {
+                                    self.files_selected.remove(file_name);
+                                }
+                            }
                         }
                     }
                 }
                 return Task::none();
             }
-            Message::SelectMultipleFiles(file_index, file_location) => {
+            Message::SortFilesBy(sort_column) => {
+                self.file_sort = self.file_sort.select_column(sort_column);
+                Task::none()
+            }
+            Message::FileFilterInput(input) => {
+                self.file_filter = FileFilter::parse(&input);
+                self.file_filter_input = input;
+                self.request_thumbnails()
+            }
+            Message::ThumbnailCreated(path_to_file, modified, thumbnail_state) => {
+                self.thumbnails
+                    .insert(path_to_file, (modified, thumbnail_state));
+                Task::none()
+            }
+            Message::FocusFile(path_to_file) => {
+                self.focused_path = Some(path_to_file);
+                self.inspect_focused_file()
+            }
+            Message::FileInspected(path_to_file, result) => {
+                if self.focused_path.as_ref() != Some(&path_to_file) {
+                    return Task::none();
+                }
+                match result {
+                    Ok(file_inspection) => self.file_inspection = Some(file_inspection),
+                    Err(error) => self.error = error,
+                }
+                Task::none()
+            }
+            Message::HashFocusedFile => self.hash_focused_file(),
+            Message::FileHashed(path_to_file, result) => {
+                if self.hashing_path.as_ref() == Some(&path_to_file) {
+                    self.hashing_path = None;
+                }
+                let file_inspection = self
+                    .file_inspection
+                    .as_mut()
+                    .filter(|file_inspection| file_inspection.get_path() == &path_to_file);
+                match (file_inspection, result) {
+                    (Some(file_inspection), Ok(hash)) => file_inspection.set_hash(hash),
+                    (Some(_), Err(error)) => self.error = error,
+                    (None, _) => {}
+                }
+                Task::none()
+            }
+            Message::ClosePreview => {
+                self.focused_path = None;
+                self.file_inspection = None;
+                Task::none()
+            }
+            Message::FileDropped(path) => {
+                if !self.can_select_files() {
+                    return Task::none();
+                }
+                if let Err(error) = self.select_dropped_path(&path) {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::DropFilterInput(input) => {
+                self.drop_filter = FileFilter::parse(&input);
+                self.drop_filter_input = input;
+                Task::none()
+            }
+            Message::EntryHovered(path) => {
+                self.hovered_path = Some(path);
+                Task::none()
+            }
+            Message::EntryHoverEnded(path) => {
+                if self.hovered_path.as_ref() == Some(&path) {
+                    self.hovered_path = None;
+                }
+                Task::none()
+            }
+            // Drags start only from the mouse area of a file entry.
+            Message::EntryPressed(path) => {
+                self.hovered_path = Some(PathBuf::from(&path));
+                self.dragged_path =
+                    Some(path).filter(|path| self.is_file_in_tree_or_selected(path));
+                Task::none()
+            }
+            // Messages of the entries come before MouseReleased from the
+            // subscription, so releasing on the pressed entry is a click.
+            Message::EntryReleased(path) => {
+                if self.dragged_path.as_ref() != Some(&path) {
+                    return Task::none();
+                }
+                self.dragged_path = None;
+                match self.is_file_selected(&path) {
+                    true => self.update(Message::SelectFile(
+                        FileSelectedLocation::FromFilesSelected(path),
+                    )),
+                    false => self.update(Message::FocusFile(path)),
+                }
+            }
+            Message::MouseReleased => {
+                let Some(dragged_path) = self.dragged_path.take() else {
+                    return Task::none();
+                };
+                match self.hovered_path.clone() {
+                    Some(target) if target != dragged_path && self.can_select_files() => {
+                        self.drop_on_directory(&dragged_path, target)
+                    }
+                    _ => Task::none(),
+                }
+            }
+            Message::SelectMultipleFiles(file_location) => {
                 match file_location {
                     FileSelectedLocation::FromDirectory(path_to_file) => {
                         // Logic for multiple selecting from directory tree
@@ -385,11 +723,12 @@ impl App {
                             if let Ok(last_str) = convert_os_str_to_str(last) {
                                 let mut path_to_directory = PathBuf::from(&path_to_file);
                                 path_to_directory.pop();
-                                if let Err(error) = self.select_multiple_files_from_directories(
-                                    last_str,
-                                    file_index,
-                                    &path_to_directory,
-                                ) {
+                                if let Err(error) = self
+                                    .select_multiple_files_from_directories(
+                                        last_str,
+                                        &path_to_directory,
+                                    )
+                                {
                                     self.error = error.to_string();
                                 }
                             }
@@ -401,11 +740,12 @@ impl App {
                             if let Ok(last_str) = convert_os_str_to_str(last) {
                                 let mut path_to_original_directory = PathBuf::from(&origin_path);
                                 path_to_original_directory.pop();
-                                if let Err(error) = self.select_multiple_files_from_files_selected(
-                                    last_str,
-                                    file_index,
-                                    &path_to_original_directory,
-                                ) {
+                                if let Err(error) = self
+                                    .select_multiple_files_from_files_selected(
+                                        last_str,
+                                        &path_to_original_directory,
+                                    )
+                                {
                                     self.error = error.to_string();
                                 }
                             }
@@ -426,12 +766,14 @@ impl App {
                     return Task::none();
                 }
 
-                let mut files_selected = BTreeMap::new();
-                while let Some((key, value)) = self.files_selected.pop_last() {
-                    files_selected.insert(key, value);
-                }
-
-                match self.create_directory_with_selected_files(files_selected) {
+                let result = self.organize_staged(|app| {
+                    let mut files_selected = BTreeMap::new();
+                    while let Some((key, value)) = app.files_selected.pop_last() {
+                        files_selected.insert(key, value);
+                    }
+                    app.create_directory_with_selected_files(files_selected)
+                });
+                match result {
                     Ok(_) => {
                         // Refresh the directories in layouts
                     }
@@ -445,8 +787,16 @@ impl App {
 
                 Task::none()
             }
+            Message::RenameOverrideInput(file_name, input) => {
+                if input.is_empty() {
+                    self.rename_overrides.remove(&file_name);
+                } else {
+                    self.rename_overrides.insert(file_name, input);
+                }
+                Task::none()
+            }
             Message::RenameFiles => {
-                if !app_util::just_rename_checked(&self.checkbox_states) {
+                if !util::just_rename_checked(&self.checkbox_states) {
                     self.error =
                         std::io::Error::new(ErrorKind::NotFound, "No rename options specified")
                             .to_string();
@@ -460,39 +810,39 @@ impl App {
                     return Task::none();
                 }
                 if !self.checkbox_states.insert_date_to_file_name {
-                    let result = self.rename_files_without_directory(
-                        CheckboxStates::new(
-                            false,
-                            false,
-                            self.checkbox_states.insert_date_to_file_name,
-                            false,
-                            self.checkbox_states.convert_uppercase_to_lowercase,
-                            self.checkbox_states.replace_character,
-                            self.checkbox_states.use_only_ascii,
-                            self.checkbox_states.remove_original_file_name,
-                            self.checkbox_states.add_custom_name,
-                        ),
-                        None,
+                    let checkbox_states = CheckboxStates::new(
+                        false,
+                        false,
+                        self.checkbox_states.insert_date_to_file_name,
+                        false,
+                        self.checkbox_states.convert_uppercase_to_lowercase,
+                        self.checkbox_states.replace_character,
+                        self.checkbox_states.use_only_ascii,
+                        self.checkbox_states.remove_original_file_name,
+                        self.checkbox_states.add_custom_name,
                     );
+                    let result = self.organize_staged(|app| {
+                        app.rename_files_without_directory(checkbox_states, None)
+                    });
                     if let Err(error) = result {
                         self.error = error.to_string();
                     }
                 }
                 if let Some(date_type) = self.date_type_selected {
-                    let result = self.rename_files_without_directory(
-                        CheckboxStates::new(
-                            false,
-                            false,
-                            self.checkbox_states.insert_date_to_file_name,
-                            false,
-                            self.checkbox_states.convert_uppercase_to_lowercase,
-                            self.checkbox_states.replace_character,
-                            self.checkbox_states.use_only_ascii,
-                            self.checkbox_states.remove_original_file_name,
-                            self.checkbox_states.add_custom_name,
-                        ),
-                        Some(date_type),
+                    let checkbox_states = CheckboxStates::new(
+                        false,
+                        false,
+                        self.checkbox_states.insert_date_to_file_name,
+                        false,
+                        self.checkbox_states.convert_uppercase_to_lowercase,
+                        self.checkbox_states.replace_character,
+                        self.checkbox_states.use_only_ascii,
+                        self.checkbox_states.remove_original_file_name,
+                        self.checkbox_states.add_custom_name,
                     );
+                    let result = self.organize_staged(|app| {
+                        app.rename_files_without_directory(checkbox_states, Some(date_type))
+                    });
                     if let Err(error) = result {
                         self.error = error.to_string();
                     }
@@ -507,8 +857,8 @@ impl App {
                 Task::none()
             }
             Message::SelectReplaceable(replaceable, index) => {
-                let previous_selected = self.replaceables[index].replaceable_selected;
-                self.replaceables[index].replaceable_selected = Some(replaceable);
+                let previous_selected = self.replaceables[index].get_replaceable_selected();
+                self.replaceables[index].set_replaceable_selected(Some(replaceable));
                 self.replaceable_options = self
                     .replaceable_options
                     .iter()
@@ -525,7 +875,7 @@ impl App {
                 Task::none()
             }
             Message::SelectReplaceWith(replace_with, index) => {
-                self.replaceables[index].replace_with_selected = Some(replace_with);
+                self.replaceables[index].set_replace_with_selected(Some(replace_with));
                 Task::none()
             }
             Message::AddNewReplaceable => {
@@ -546,8 +896,26 @@ impl App {
                 self.date_type_selected = Some(date_type);
                 Task::none()
             }
+            Message::TimezoneInput(input) => {
+                if let Some(timezone) = Timezone::parse(&input) {
+                    self.timezone = timezone;
+                }
+                self.timezone_input = input;
+                Task::none()
+            }
+            Message::TargetProfileSelected(profile) => {
+                self.sanitize_options.profile = profile;
+                Task::none()
+            }
+            Message::RejectInvalidNamesToggled(toggle) => {
+                self.sanitize_options.action = match toggle {
+                    true => SanitizeAction::Reject,
+                    false => SanitizeAction::Rewrite,
+                };
+                Task::none()
+            }
             Message::InsertFilesToSelectedDirectory => {
-                if let Err(error) = self.insert_files_to_selected_dir() {
+                if let Err(error) = self.organize_staged(Self::insert_files_to_selected_dir) {
                     self.error = error.to_string();
                     return Task::none();
                 }
@@ -574,12 +942,138 @@ impl App {
                 }
                 return Task::none();
             }
-            Message::Commit => {
-                if let Err(error) = filesystem::move_files_organized(&self.files_organized) {
+            Message::TransferModeSelected(transfer_mode) => {
+                self.transfer_mode = transfer_mode;
+                Task::none()
+            }
+            Message::RemoveEmptyDirectoriesToggled(toggle) => {
+                self.remove_empty_directories = toggle;
+                Task::none()
+            }
+            Message::CleanupBoundaryInput(input) => {
+                self.cleanup_boundary_input = input;
+                Task::none()
+            }
+            Message::JunkFileNamesInput(input) => {
+                self.junk_file_names_input = input;
+                Task::none()
+            }
+            Message::FlattenTargetInput(input) => {
+                self.flatten_target_input = input;
+                Task::none()
+            }
+            Message::StripRenamePrefixesToggled(toggle) => {
+                self.strip_rename_prefixes = toggle;
+                Task::none()
+            }
+            Message::FlattenSelectedDirectory => {
+                if let Err(error) = self.plan_flatten() {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::ReapplySelectedDirectoryRules => {
+                if let Err(error) = self.plan_reapply() {
                     self.error = error.to_string();
                 }
-                let mut path_to_directory = PathBuf::from(&self.path);
-                path_to_directory.push(&self.new_directory_name);
+                Task::none()
+            }
+            Message::CancelSelectedDirectoryPlan => {
+                self.selected_directory_plan = None;
+                Task::none()
+            }
+            Message::Commit => {
+                if self.commit_state.is_some() {
+                    return Task::none();
+                }
+                let (plan, path_to_directory, cleanup_options) =
+                    match self.selected_directory_plan.take() {
+                        Some((_, plan)) => {
+                            let cleanup_options = self.get_selected_directory_cleanup_options();
+                            (plan, None, cleanup_options)
+                        }
+                        None => match self.sanitize_files_organized() {
+                            Ok((plan, _)) => {
+                                let mut path_to_directory = PathBuf::from(&self.path);
+                                path_to_directory.push(&self.new_directory_name);
+                                (plan, Some(path_to_directory), self.get_cleanup_options())
+                            }
+                            Err(error) => {
+                                self.error = error.to_string();
+                                return Task::none();
+                            }
+                        },
+                    };
+                let cancel = Arc::new(AtomicBool::new(false));
+                self.commit_state = Some(CommitState {
+                    cancel: Arc::clone(&cancel),
+                    progress: None,
+                    path_to_directory,
+                    undoing: false,
+                });
+                let transfer_mode = self.transfer_mode;
+                let trash = self.get_trash();
+                let (sender, receiver) = iced::futures::channel::mpsc::unbounded();
+                std::thread::spawn(move || {
+                    let result = filerganizer_core::apply_with_progress(
+                        &RealFileSystem,
+                        &plan,
+                        transfer_mode,
+                        &cancel,
+                        &mut |progress| {
+                            let _ = sender.unbounded_send(CommitEvent::Progress(progress.clone()));
+                        },
+                    );
+                    let mut cleanup_error = None;
+                    let result = result.map(|mut journal| {
+                        if let Some(cleanup_options) = &cleanup_options {
+                            if let Err(error) = filesystem::remove_empty_directories(
+                                &mut journal,
+                                cleanup_options,
+                                &trash,
+                            ) {
+                                cleanup_error = Some(error.to_string());
+                            }
+                        }
+                        journal
+                    });
+                    let _ = sender.unbounded_send(CommitEvent::Finished(
+                        result.map_err(|error| error.to_string()),
+                        cleanup_error,
+                    ));
+                });
+                Task::run(receiver, Message::CommitProgress)
+            }
+            Message::CommitProgress(CommitEvent::Progress(progress)) => {
+                if let Some(commit_state) = &mut self.commit_state {
+                    commit_state.progress = Some(progress);
+                }
+                Task::none()
+            }
+            Message::CommitProgress(CommitEvent::Finished(result, cleanup_error)) => {
+                let Some(commit_state) = self.commit_state.take() else {
+                    return Task::none();
+                };
+                match result {
+                    Ok(journal) => {
+                        if let Some(error) = cleanup_error {
+                            self.error = error;
+                        }
+                        self.last_journal = Some(journal);
+                    }
+                    Err(error) => {
+                        self.error = error;
+                        return Task::none();
+                    }
+                }
+                self.last_organized_directory = commit_state.path_to_directory.clone();
+                let Some(path_to_directory) = commit_state.path_to_directory else {
+                    self.init_app_data();
+                    if let Err(error) = self.switch_layout(&Layout::Main) {
+                        self.error = error.to_string();
+                    }
+                    return Task::none();
+                };
 
                 match save_directory::write_created_directory_to_save_file(
                     &self.home_directory_path,
@@ -587,6 +1081,8 @@ impl App {
                     self.checkbox_states.clone(),
                     &self.replaceables,
                     self.date_type_selected,
+                    self.timezone,
+                    self.sanitize_options,
                     self.index_position,
                     &self.order_of_filename_components,
                     &self.filename_input,
@@ -599,6 +1095,8 @@ impl App {
                     }
                 }
                 self.files_organized.clear();
+                self.organized_directories_to_sanitize.clear();
+                self.sanitized_names.clear();
                 self.files_have_been_organized = true;
                 self.init_app_data();
                 if let Err(error) = self.switch_layout(&Layout::Main) {
@@ -606,104 +1104,1136 @@ impl App {
                 }
                 return Task::none();
             }
-            Message::TabKeyPressed => {
-                match self.search_directories_from_path() {
-                    Ok(new_path) => {
-                        self.path_input = new_path;
-                        if let Err(error) = self.search_path() {
+            // The journal is kept when the undo fails or is cancelled, so
+            // undoing again puts back the rest.
+            Message::CommitProgress(CommitEvent::UndoFinished(result)) => {
+                self.commit_state = None;
+                match result {
+                    Ok(()) => {
+                        self.last_journal = None;
+                        self.files_have_been_organized = false;
+                        if let Err(error) = self.forget_undone_directory() {
                             self.error = error.to_string();
                         }
                     }
-                    Err(error) => self.error = error.to_string(),
+                    Err(error) => self.error = error,
+                }
+                Task::none()
+            }
+            Message::CancelCommit => {
+                if let Some(commit_state) = &self.commit_state {
+                    commit_state.cancel.store(true, Ordering::Relaxed);
+                }
+                Task::none()
+            }
+            Message::TabKeyPressed => {
+                if let Err(error) = self.complete_path() {
+                    self.error = error.to_string();
                 }
-                self.update_path_input();
                 iced::widget::text_input::move_cursor_to_end::<Message>(self.path_input_id.clone())
             }
-            Message::Exit => iced::exit(),
-        }
-    }
-
-    pub fn get_root_directory(&self) -> &Directory {
-        &self.root
-    }
-
-    pub fn get_path(&self) -> &PathBuf {
-        &self.path
-    }
-    pub fn get_path_input(&self) -> &str {
-        self.path_input.as_str()
-    }
-
-    pub fn get_path_input_id(&self) -> iced::widget::text_input::Id {
-        self.path_input_id.clone()
-    }
-
-    pub fn get_error(&self) -> &str {
-        self.error.as_str()
-    }
-
-    pub fn get_external_directories(&self) -> &BTreeSet<OsString> {
-        &self.external_storage
-    }
-
-    pub fn get_directory_view(&self) -> DirectoryView {
-        self.directory_view.clone()
-    }
-
-    pub fn get_files_selected(&self) -> &BTreeMap<OsString, File> {
-        &self.files_selected
-    }
-
-    pub fn get_new_directory_input(&self) -> &String {
-        &self.new_directory_name
+            Message::PathCompletionSelected(index) => {
+                let candidate_path = self
+                    .path_completion
+                    .as_ref()
+                    .and_then(|path_completion| path_completion.get_candidate_path(index));
+                if let Some(candidate_path) = candidate_path {
+                    if let Err(error) = self.open_completed_path(candidate_path) {
+                        self.error = error.to_string();
+                    }
+                }
+                Task::batch([
+                    self.request_thumbnails(),
+                    iced::widget::text_input::move_cursor_to_end(self.path_input_id.clone()),
+                ])
+            }
+            Message::FindDuplicates => self.find_duplicates(),
+            Message::DuplicatesFound(result) => {
+                self.finding_duplicates = false;
+                match result {
+                    Ok((duplicate_groups, skipped)) => {
+                        if !skipped.is_empty() {
+                            self.error = format!(
+                                "{} files could not be read and were skipped.",
+                                skipped.len()
+                            );
+                        } else if duplicate_groups.is_empty() {
+                            self.error = String::from(tr("No duplicate files found."));
+                        }
+                        self.duplicate_groups = duplicate_groups;
+                    }
+                    Err(error) => self.error = error,
+                }
+                Task::none()
+            }
+            Message::ResolveDuplicates(index, action) => {
+                if let Err(error) = self.resolve_duplicates(index, action) {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::CloseDuplicates => {
+                self.duplicate_groups.clear();
+                Task::none()
+            }
+            Message::FindSimilarImages => self.find_similar_images(),
+            Message::SimilarImagesFound(similar_image_groups) => {
+                self.finding_similar_images = false;
+                if similar_image_groups.is_empty() {
+                    self.error = String::from(tr("No similar images found."));
+                }
+                self.similar_image_groups = similar_image_groups;
+                Task::none()
+            }
+            Message::PerceptualHashTypeSelected(hash_type) => {
+                self.perceptual_hash_type = hash_type;
+                Task::none()
+            }
+            Message::SimilarityThresholdChanged(threshold) => {
+                self.similarity_threshold = threshold;
+                Task::none()
+            }
+            Message::KeepHighestResolution(index) => {
+                if index < self.similar_image_groups.len() {
+                    match similar_images::keep_highest_resolution(
+                        &self.similar_image_groups[index],
+                        &self.get_quarantine_directory(),
+                    ) {
+                        Ok(moved_paths) => {
+                            self.similar_image_groups.remove(index);
+                            self.remove_from_files_selected(moved_paths, false);
+                        }
+                        // The group stays listed with the images that were not moved.
+                        Err(failure) => {
+                            self.similar_image_groups[index].remove_images(&failure.processed);
+                            if self.similar_image_groups[index].get_images().len() < 2 {
+                                self.similar_image_groups.remove(index);
+                            }
+                            self.remove_from_files_selected(failure.processed, false);
+                            self.error = failure.error.to_string();
+                        }
+                    }
+                }
+                Task::none()
+            }
+            Message::CloseSimilarImages => {
+                self.similar_image_groups.clear();
+                Task::none()
+            }
+            Message::WatchInboxInput(input) => {
+                self.watch_inbox_input = input;
+                Task::none()
+            }
+            Message::WatchOrganizedDirectoryInput(input) => {
+                self.watch_organized_directory_input = input;
+                Task::none()
+            }
+            Message::WatchIgnoreInput(input) => {
+                self.watch_ignore_input = input;
+                Task::none()
+            }
+            Message::AddWatchFolder => {
+                if let Err(error) = self.add_watch_folder() {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::ToggleWatchFolderPaused(index) => {
+                if let Some(watch_folder) = self.watch_folders.get_mut(index) {
+                    watch_folder.set_paused(!watch_folder.get_paused());
+                }
+                if let Err(error) =
+                    watch_folder::write_watch_folders(&self.home_directory_path, &self.watch_folders)
+                {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::RemoveWatchFolder(index) => {
+                if index < self.watch_folders.len() {
+                    self.watch_folders.remove(index);
+                }
+                if let Err(error) =
+                    watch_folder::write_watch_folders(&self.home_directory_path, &self.watch_folders)
+                {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::WatchTick => self.poll_watch_folders(),
+            Message::WatchFoldersPolled(entries) => {
+                self.polling_watch_folders = false;
+                self.watch_log.extend(entries);
+                let overflow = self
+                    .watch_log
+                    .len()
+                    .saturating_sub(watch_folder::MAX_LOG_ENTRIES);
+                self.watch_log.drain(..overflow);
+                Task::none()
+            }
+            Message::UndoCommit => self.undo_last_commit(),
+            Message::RestoreTrashedItem(index) => {
+                if let Err(error) = self.restore_trashed_item(index) {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::KeyPressed(key, modifiers) => {
+                match self.settings.get_keymap().get_action(&key, modifiers) {
+                    Some(action) => self.run_key_action(action),
+                    None => Task::none(),
+                }
+            }
+            Message::KeymapInput(action, input) => {
+                self.keymap_inputs.insert(action, input);
+                Task::none()
+            }
+            Message::SaveKeymap => {
+                if let Err(error) = self.save_keymap() {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::ResetKeymap => {
+                self.settings.set_keymap(Keymap::default());
+                self.update_keymap_inputs();
+                let result = settings::write_settings(&self.home_directory_path, &self.settings);
+                if let Err(error) = result {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::ThemeSelected(theme_setting) => {
+                self.theme = theme_setting.get_theme();
+                self.settings.set_theme(theme_setting);
+                let result = settings::write_settings(&self.home_directory_path, &self.settings);
+                if let Err(error) = result {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::DefaultDirectoryViewSelected(directory_view) => {
+                self.directory_view = directory_view.clone();
+                self.settings.set_directory_view(directory_view);
+                let result = settings::write_settings(&self.home_directory_path, &self.settings);
+                if let Err(error) = result {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::LanguageSelected(language) => {
+                locale::set_language(language);
+                self.settings.set_language(language);
+                let result = settings::write_settings(&self.home_directory_path, &self.settings);
+                if let Err(error) = result {
+                    self.error = error.to_string();
+                }
+                Task::none()
+            }
+            Message::WindowResized(size) => {
+                self.settings.set_window_size(size);
+                Task::none()
+            }
+            Message::WindowMoved(position) => {
+                self.settings.set_window_position(position);
+                Task::none()
+            }
+            // The system theme may have changed while another window was in
+            // front.
+            Message::WindowFocused => {
+                if *self.settings.get_theme() == ThemeSetting::System {
+                    self.theme = ThemeSetting::System.get_theme();
+                }
+                Task::none()
+            }
+            // The window is only saved here, as it changes all the time while
+            // it is resized or moved. A failed save keeps the window open to
+            // show the error, and closing it again exits without saving.
+            Message::Exit => {
+                self.remember_last_path();
+                let result = settings::write_settings(&self.home_directory_path, &self.settings);
+                if let (Err(error), false) = (result, self.settings_save_failed) {
+                    self.settings_save_failed = true;
+                    self.error = format!("Failed to save settings: {}", error);
+                    return Task::none();
+                }
+                iced::exit()
+            }
+        }
+    }
+
+    // Path completion and undo work everywhere, the other actions only in
+    // the directory tree.
+    fn run_key_action(&mut self, action: KeyAction) -> Task<Message> {
+        match action {
+            KeyAction::CompletePath => self.update(Message::TabKeyPressed),
+            KeyAction::UndoCommit if self.can_undo_commit() => self.update(Message::UndoCommit),
+            KeyAction::UndoCommit => Task::none(),
+            _ if !matches!(self.layout, Layout::DirectorySelectionLayout)
+                || self.is_committing() =>
+            {
+                Task::none()
+            }
+            KeyAction::Commit => {
+                if self.files_organized.is_empty() && self.selected_directory_plan.is_none() {
+                    return Task::none();
+                }
+                self.update(Message::Commit)
+            }
+            KeyAction::FocusUp => {
+                self.move_focus(-1);
+                self.inspect_focused_file()
+            }
+            KeyAction::FocusDown => {
+                self.move_focus(1);
+                self.inspect_focused_file()
+            }
+            KeyAction::OpenDirectory => match self.focused_path.clone() {
+                Some(focused_path) if focused_path.is_dir() => {
+                    self.focused_path = None;
+                    self.update(Message::DropDownDirectory(focused_path))
+                }
+                _ => Task::none(),
+            },
+            KeyAction::ParentDirectory => {
+                let path = PathBuf::from(&self.path);
+                if path.parent().is_none() {
+                    return Task::none();
+                }
+                let task = self.update(Message::DropDownDirectory(PathBuf::from(&path)));
+                self.focused_path = Some(path);
+                task
+            }
+            _ if !self.files_organized.is_empty() => Task::none(),
+            KeyAction::ToggleSelection => self.toggle_focused_file(),
+            KeyAction::ExtendSelectionUp => self.extend_selection(-1),
+            KeyAction::ExtendSelectionDown => self.extend_selection(1),
+            KeyAction::SelectAll => self.select_all_files(),
+        }
+    }
+
+    // Directories and then files of the current directory, in the order they
+    // are shown. A focused file that was just selected keeps its place, so it
+    // can be unselected again.
+    fn get_focusable_entries(&self) -> Vec<PathBuf> {
+        let directory = self.root.get_directory_by_path(&self.path);
+        let mut entries = Vec::new();
+        if let Some(directories) = directory.get_directories() {
+            for key in directories.keys() {
+                entries.push(self.path.join(key));
+            }
+        }
+        let mut files = directory.get_files().clone().unwrap_or_default();
+        if let Some((file_name, file)) = self.get_focused_selected_file() {
+            files.insert(file_name, file.clone());
+        }
+        for key in file_list::visible_file_names(&files, self.file_sort, &self.file_filter) {
+            entries.push(self.path.join(key));
+        }
+        entries
+    }
+
+    fn get_focused_selected_file(&self) -> Option<(OsString, &File)> {
+        let focused_path = self.focused_path.as_ref()?;
+        let file_name = focused_path.file_name()?;
+        let file = self.files_selected.get(file_name)?;
+        let origin_path = file.get_metadata().as_ref()?.get_origin_path()?;
+        match &origin_path == focused_path {
+            true => Some((OsString::from(file_name), file)),
+            false => None,
+        }
+    }
+
+    fn move_focus(&mut self, step: isize) {
+        let entries = self.get_focusable_entries();
+        if entries.is_empty() {
+            self.focused_path = None;
+            return;
+        }
+        let position = self
+            .focused_path
+            .as_ref()
+            .and_then(|focused_path| entries.iter().position(|entry| entry == focused_path));
+        let new_position = match position {
+            Some(position) => position
+                .saturating_add_signed(step)
+                .min(entries.len() - 1),
+            None if step < 0 => entries.len() - 1,
+            None => 0,
+        };
+        self.focused_path = Some(PathBuf::from(&entries[new_position]));
+    }
+
+    // Walks the selected directory and hashes the files on a background
+    // thread, as it takes a while on large libraries.
+    fn find_duplicates(&mut self) -> Task<Message> {
+        if self.finding_duplicates {
+            return Task::none();
+        }
+        self.finding_duplicates = true;
+        let files_selected = self.files_selected.clone();
+        let directory_selected = self.directory_selected.clone();
+        let (sender, receiver) = iced::futures::channel::oneshot::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(Ok(duplicates::find_duplicate_groups(
+                &files_selected,
+                directory_selected.as_ref(),
+            )));
+        });
+        Task::perform(receiver, |result| {
+            Message::DuplicatesFound(result.unwrap_or_else(|error| Err(error.to_string())))
+        })
+    }
+
+    // Images are decoded and hashed on a background thread.
+    fn find_similar_images(&mut self) -> Task<Message> {
+        if self.finding_similar_images {
+            return Task::none();
+        }
+        self.finding_similar_images = true;
+        let paths = duplicates::get_origin_paths(&self.files_selected);
+        let hash_type = self.perceptual_hash_type;
+        let threshold = self.similarity_threshold;
+        let (sender, receiver) = iced::futures::channel::oneshot::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(similar_images::find_similar_images(
+                &paths, hash_type, threshold,
+            ));
+        });
+        Task::perform(receiver, |result| {
+            Message::SimilarImagesFound(result.unwrap_or_default())
+        })
+    }
+
+    // Moves the settled inbox files on a background thread with the transfer
+    // mode selected for commits. A tick that comes while the previous poll is
+    // still running is skipped.
+    fn poll_watch_folders(&mut self) -> Task<Message> {
+        if self.polling_watch_folders {
+            return Task::none();
+        }
+        if let Ok(watch_folders) = watch_folder::read_watch_folders(&self.home_directory_path) {
+            self.watch_folders = watch_folders;
+        }
+        self.polling_watch_folders = true;
+        let home_directory_path = PathBuf::from(&self.home_directory_path);
+        let watch_folders = self.watch_folders.clone();
+        let watch_state = Arc::clone(&self.watch_state);
+        let transfer_mode = self.transfer_mode;
+        let (sender, receiver) = iced::futures::channel::oneshot::channel();
+        std::thread::spawn(move || {
+            let mut watch_state = watch_state.lock().unwrap_or_else(PoisonError::into_inner);
+            let _ = sender.send(watch_folder::poll_watch_folders(
+                &home_directory_path,
+                &watch_folders,
+                &mut watch_state,
+                transfer_mode,
+            ));
+        });
+        Task::perform(receiver, |entries| {
+            Message::WatchFoldersPolled(entries.unwrap_or_default())
+        })
+    }
+
+    // Reads the preview of the focused file on a background thread. The
+    // result is dropped if focus has moved on by then.
+    fn inspect_focused_file(&mut self) -> Task<Message> {
+        let Some(focused_path) = self.focused_path.clone() else {
+            return Task::none();
+        };
+        if !focused_path.is_file() {
+            return Task::none();
+        }
+        let path_to_file = PathBuf::from(&focused_path);
+        let (sender, receiver) = iced::futures::channel::oneshot::channel();
+        std::thread::spawn(move || {
+            let result = preview::inspect_file(&path_to_file).map_err(|error| error.to_string());
+            let _ = sender.send(result);
+        });
+        Task::perform(receiver, move |result| {
+            let result = result.unwrap_or_else(|error| Err(error.to_string()));
+            Message::FileInspected(PathBuf::from(&focused_path), result)
+        })
+    }
+
+    // Only one file is hashed at a time and the result is kept only if the
+    // file is still being previewed.
+    fn hash_focused_file(&mut self) -> Task<Message> {
+        if self.hashing_path.is_some() {
+            return Task::none();
+        }
+        let Some(file_inspection) = self.get_file_inspection() else {
+            return Task::none();
+        };
+        let path_to_file = PathBuf::from(file_inspection.get_path());
+        self.hashing_path = Some(PathBuf::from(&path_to_file));
+        let (sender, receiver) = iced::futures::channel::oneshot::channel();
+        let hashed_path = PathBuf::from(&path_to_file);
+        std::thread::spawn(move || {
+            let result = duplicates::full_hash(&hashed_path).map_err(|error| error.to_string());
+            let _ = sender.send(result);
+        });
+        Task::perform(receiver, move |result| {
+            let result = result.unwrap_or_else(|error| Err(error.to_string()));
+            Message::FileHashed(PathBuf::from(&path_to_file), result)
+        })
+    }
+
+    fn can_select_files(&self) -> bool {
+        matches!(self.layout, Layout::DirectorySelectionLayout)
+            && !self.is_committing()
+            && self.files_organized.is_empty()
+    }
+
+    fn is_file_in_tree(&self, path_to_file: &Path) -> bool {
+        let (Some(path_to_directory), Some(file_name)) =
+            (path_to_file.parent(), path_to_file.file_name())
+        else {
+            return false;
+        };
+        let directory = self.root.get_directory_by_path(&PathBuf::from(path_to_directory));
+        directory.get_metadata().as_ref().and_then(|metadata| metadata.get_origin_path())
+            == Some(PathBuf::from(path_to_directory))
+            && directory
+                .get_files()
+                .as_ref()
+                .is_some_and(|files| files.contains_key(file_name))
+    }
+
+    fn is_file_selected(&self, path_to_file: &PathBuf) -> bool {
+        path_to_file
+            .file_name()
+            .and_then(|file_name| self.files_selected.get(file_name))
+            .and_then(|file| file.get_metadata().as_ref())
+            .and_then(|metadata| metadata.get_origin_path())
+            .is_some_and(|origin_path| &origin_path == path_to_file)
+    }
+
+    fn is_file_in_tree_or_selected(&self, path: &PathBuf) -> bool {
+        self.is_file_in_tree(path) || self.is_file_selected(path)
+    }
+
+    // Files dropped from the file manager are selected, directories are
+    // searched for files that match the drop filter. Files that cannot be
+    // read are left out and counted in the error.
+    fn select_dropped_path(&mut self, path: &PathBuf) -> std::io::Result<()> {
+        let paths_to_files = match path.is_dir() {
+            true => directory::list_files_recursive(path)?
+                .into_iter()
+                .filter(|path_to_file| {
+                    let file_name = path_to_file.file_name().unwrap_or_default();
+                    self.drop_filter.matches(&file_name.to_string_lossy())
+                })
+                .collect(),
+            false => vec![PathBuf::from(path)],
+        };
+        let mut duplicate_count = 0;
+        let mut unreadable_count = 0;
+        for path_to_file in paths_to_files {
+            if self.is_file_selected(&path_to_file) {
+                continue;
+            }
+            if self.is_file_in_tree(&path_to_file) {
+                let _ = self.update(Message::SelectFile(FileSelectedLocation::FromDirectory(
+                    path_to_file,
+                )));
+                continue;
+            }
+            let Some(file_name) = path_to_file.file_name() else {
+                continue;
+            };
+            if self.files_selected.contains_key(file_name) {
+                duplicate_count += 1;
+                continue;
+            }
+            match directory::read_file(&path_to_file) {
+                Ok(file) => {
+                    self.files_selected.insert(OsString::from(file_name), file);
+                }
+                Err(_) => unreadable_count += 1,
+            }
+        }
+        let message = match (unreadable_count, duplicate_count) {
+            (0, 0) => return Ok(()),
+            (0, _) => format!(
+                "{} dropped files have the same name as a selected file.",
+                duplicate_count
+            ),
+            (_, 0) => format!("{} dropped files could not be read.", unreadable_count),
+            (_, _) => format!(
+                "{} dropped files could not be read and {} have the same name as a selected file.",
+                unreadable_count, duplicate_count
+            ),
+        };
+        Err(std::io::Error::new(ErrorKind::InvalidData, message))
+    }
+
+    // Dropping a file on a directory moves the selected files into it with
+    // its rules, like "Insert files to selected directory". A file from the
+    // tree is selected first.
+    fn drop_on_directory(&mut self, dragged_path: &PathBuf, target: PathBuf) -> Task<Message> {
+        if !self.is_directory_in_tree(&target) {
+            return Task::none();
+        }
+        let mut task = Task::none();
+        if self.is_file_in_tree(dragged_path) {
+            task = self.update(Message::SelectFile(FileSelectedLocation::FromDirectory(
+                PathBuf::from(dragged_path),
+            )));
+        }
+        if self.files_selected.is_empty() {
+            return task;
+        }
+        self.directory_selected = Some(target);
+        Task::batch([task, self.update(Message::InsertFilesToSelectedDirectory)])
+    }
+
+    fn is_directory_in_tree(&self, path: &PathBuf) -> bool {
+        self.root
+            .get_directory_by_path(path)
+            .get_metadata()
+            .as_ref()
+            .and_then(|metadata| metadata.get_origin_path())
+            .is_some_and(|origin_path| &origin_path == path)
+    }
+
+    fn toggle_focused_file(&mut self) -> Task<Message> {
+        match self.focused_path.clone() {
+            Some(focused_path) if self.get_focused_selected_file().is_some() => self.update(
+                Message::SelectFile(FileSelectedLocation::FromFilesSelected(focused_path)),
+            ),
+            _ => self.select_focused_file(),
+        }
+    }
+
+    fn select_focused_file(&mut self) -> Task<Message> {
+        let Some(focused_path) = self.focused_path.clone() else {
+            return Task::none();
+        };
+        let directory = self.root.get_directory_by_path(&self.path);
+        let is_file = focused_path.file_name().is_some_and(|file_name| {
+            directory
+                .get_files()
+                .as_ref()
+                .is_some_and(|files| files.contains_key(file_name))
+        });
+        if !is_file || focused_path.parent() != Some(self.path.as_path()) {
+            return Task::none();
+        }
+        self.update(Message::SelectFile(FileSelectedLocation::FromDirectory(
+            focused_path,
+        )))
+    }
+
+    // Selects the focused file and the one focus moves to, so holding the
+    // key selects a range.
+    fn extend_selection(&mut self, step: isize) -> Task<Message> {
+        let task = self.select_focused_file();
+        self.move_focus(step);
+        Task::batch([task, self.select_focused_file()])
+    }
+
+    fn select_all_files(&mut self) -> Task<Message> {
+        let directory = self.root.get_directory_by_path(&self.path);
+        let Some(files) = directory.get_files() else {
+            return Task::none();
+        };
+        let paths_to_files: Vec<PathBuf> =
+            file_list::visible_file_names(files, self.file_sort, &self.file_filter)
+                .into_iter()
+                .map(|file_name| self.path.join(file_name))
+                .collect();
+        let tasks: Vec<Task<Message>> = paths_to_files
+            .into_iter()
+            .map(|path_to_file| {
+                self.update(Message::SelectFile(FileSelectedLocation::FromDirectory(
+                    path_to_file,
+                )))
+            })
+            .collect();
+        Task::batch(tasks)
+    }
+
+    // Creates the missing thumbnails of the shown files on a background
+    // thread when the grid view is open.
+    fn request_thumbnails(&mut self) -> Task<Message> {
+        if !matches!(self.directory_view, DirectoryView::Grid) {
+            return Task::none();
+        }
+        let directory = self.root.get_directory_by_path(&self.path);
+        let Some(files) = directory.get_files() else {
+            return Task::none();
+        };
+        let paths_to_files: Vec<(PathBuf, Option<DateTime<Local>>)> =
+            file_list::visible_file_names(files, self.file_sort, &self.file_filter)
+                .into_iter()
+                .filter(|file_name| thumbnails::has_thumbnail(&file_name.to_string_lossy()))
+                .map(|file_name| {
+                    let modified = files
+                        .get(file_name)
+                        .and_then(|file| file.get_metadata().as_ref())
+                        .and_then(|metadata| metadata.get_modified());
+                    (self.path.join(file_name), modified)
+                })
+                .filter(|(path_to_file, modified)| {
+                    self.thumbnails
+                        .get(path_to_file)
+                        .is_none_or(|(thumbnail_modified, _)| thumbnail_modified != modified)
+                })
+                .collect();
+        if paths_to_files.is_empty() {
+            return Task::none();
+        }
+        for (path_to_file, modified) in paths_to_files.iter() {
+            self.thumbnails.insert(
+                PathBuf::from(path_to_file),
+                (*modified, ThumbnailState::Loading),
+            );
+        }
+        let cache_directory = thumbnails::get_cache_directory(&self.home_directory_path);
+        let (sender, receiver) = iced::futures::channel::mpsc::unbounded();
+        std::thread::spawn(move || {
+            for (path_to_file, modified) in paths_to_files {
+                let thumbnail_state =
+                    match thumbnails::create_thumbnail(&cache_directory, &path_to_file) {
+                        Ok(thumbnail_path) => ThumbnailState::Ready(thumbnail_path),
+                        Err(_) => ThumbnailState::Failed,
+                    };
+                if sender
+                    .unbounded_send((path_to_file, modified, thumbnail_state))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        Task::run(receiver, |(path_to_file, modified, thumbnail_state)| {
+            Message::ThumbnailCreated(path_to_file, modified, thumbnail_state)
+        })
+    }
+
+    fn update_keymap_inputs(&mut self) {
+        self.keymap_inputs = KEY_ACTIONS
+            .into_iter()
+            .filter_map(|action| {
+                let binding = self.settings.get_keymap().get_binding(action)?;
+                Some((action, binding.to_string()))
+            })
+            .collect();
+    }
+
+    fn save_keymap(&mut self) -> std::io::Result<()> {
+        let mut keymap = self.settings.get_keymap().clone();
+        for (action, input) in self.keymap_inputs.iter() {
+            match KeyBinding::parse(input) {
+                Some(binding) => keymap.set_binding(*action, binding),
+                None => {
+                    return Err(std::io::Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("Invalid key for {}: {}", action, input),
+                    ))
+                }
+            }
+        }
+        if let Some((action, other_action, binding)) = keymap.find_conflict() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} and {} are both bound to {}", action, other_action, binding),
+            ));
+        }
+        self.settings.set_keymap(keymap);
+        self.update_keymap_inputs();
+        settings::write_settings(&self.home_directory_path, &self.settings)
+    }
+
+    pub fn get_root_directory(&self) -> &Directory {
+        &self.root
+    }
+
+    pub fn get_path(&self) -> &PathBuf {
+        &self.path
+    }
+    pub fn get_path_input(&self) -> &str {
+        self.path_input.as_str()
+    }
+
+    pub fn get_path_input_id(&self) -> iced::widget::text_input::Id {
+        self.path_input_id.clone()
+    }
+
+    pub fn get_error(&self) -> &str {
+        self.error.as_str()
+    }
+
+    pub fn get_external_directories(&self) -> &BTreeSet<OsString> {
+        &self.external_storage
+    }
+
+    pub fn get_directory_view(&self) -> DirectoryView {
+        self.directory_view.clone()
+    }
+
+    pub fn get_files_selected(&self) -> &BTreeMap<OsString, File> {
+        &self.files_selected
+    }
+
+    pub fn get_new_directory_input(&self) -> &String {
+        &self.new_directory_name
+    }
+
+    pub fn get_checkbox_states(&self) -> &CheckboxStates {
+        &self.checkbox_states
+    }
+
+    pub fn get_date_type_selected(&self) -> Option<DateType> {
+        self.date_type_selected
+    }
+
+    pub fn get_timezone_input(&self) -> &str {
+        self.timezone_input.as_str()
+    }
+
+    pub fn is_timezone_input_valid(&self) -> bool {
+        Timezone::parse(&self.timezone_input).is_some()
+    }
+
+    pub fn get_sanitize_options(&self) -> SanitizeOptions {
+        self.sanitize_options
+    }
+
+    pub fn get_sanitized_names(&self) -> &Vec<SanitizedName> {
+        &self.sanitized_names
+    }
+
+    pub fn get_rename_override(&self, file_name: &OsString) -> &str {
+        self.rename_overrides
+            .get(file_name)
+            .map_or("", |name_override| name_override.as_str())
+    }
+
+    pub fn get_rename_previews(&self) -> &Result<Vec<RenamePreviewRow>, String> {
+        &self.rename_previews
+    }
+
+    // Overrides of files that are no longer selected are dropped, so they are
+    // not applied again if the file is selected later.
+    fn refresh_rename_previews(&mut self) {
+        self.rename_overrides
+            .retain(|file_name, _| self.files_selected.contains_key(file_name));
+        if self.files_selected.is_empty() || !self.files_organized.is_empty() {
+            self.rename_previews = Ok(Vec::new());
+            return;
+        }
+        self.rename_previews = self
+            .compute_rename_previews()
+            .map_err(|error| error.to_string());
+    }
+
+    // What creating the directory would do to each selected file with the
+    // current rules, and the names the target filesystem would not accept.
+    fn compute_rename_previews(&self) -> std::io::Result<Vec<RenamePreviewRow>> {
+        let directory_name = self.get_directory_name_based_on_rules();
+        let previews = organize_files::preview_renames(
+            &self.path,
+            organize_files::OrganizingData::new(
+                self.files_selected.clone(),
+                &self.checkbox_states,
+                &self.replaceables,
+                &directory_name,
+                &self.filename_input,
+                &self.order_of_filename_components,
+                self.date_type_selected,
+                self.timezone,
+                self.index_position,
+                &self.rename_overrides,
+            ),
+        )?;
+        let mut moves = Vec::new();
+        for preview in &previews {
+            let origin_path = self
+                .files_selected
+                .get(preview.get_file_name())
+                .and_then(|file| file.get_metadata().as_ref())
+                .and_then(|metadata| metadata.get_origin_path())
+                .unwrap_or_default();
+            moves.push(PlannedMove::new(
+                origin_path,
+                PathBuf::from(preview.get_destination_path()),
+            ));
+        }
+        // Rewritten even when rejecting, so the table can show what is wrong
+        let (_, mut sanitized_names) = sanitize::sanitize_plan(
+            &Plan::new(moves),
+            &self.path.join(&directory_name),
+            SanitizeOptions::new(self.sanitize_options.profile, SanitizeAction::Rewrite),
+        )?;
+        let mut rename_previews = Vec::new();
+        for preview in previews {
+            let position = sanitized_names.iter().position(|sanitized_name| {
+                sanitized_name.get_planned_destination() == preview.get_destination_path()
+            });
+            let sanitized_name = position.map(|position| sanitized_names.remove(position));
+            rename_previews.push((preview, sanitized_name));
+        }
+        Ok(rename_previews)
+    }
+
+    pub fn get_transfer_mode(&self) -> TransferMode {
+        self.transfer_mode
+    }
+
+    pub fn get_remove_empty_directories(&self) -> bool {
+        self.remove_empty_directories
+    }
+
+    pub fn get_cleanup_boundary_input(&self) -> &str {
+        &self.cleanup_boundary_input
+    }
+
+    pub fn get_junk_file_names_input(&self) -> &str {
+        &self.junk_file_names_input
+    }
+
+    pub fn get_selected_directory_plan(&self) -> &Option<(DirectoryOperation, Plan)> {
+        &self.selected_directory_plan
+    }
+
+    pub fn get_flatten_target_input(&self) -> &str {
+        &self.flatten_target_input
+    }
+
+    pub fn get_strip_rename_prefixes(&self) -> bool {
+        self.strip_rename_prefixes
+    }
+
+    pub fn get_file_sort(&self) -> FileSort {
+        self.file_sort
+    }
+
+    pub fn get_file_filter_input(&self) -> &str {
+        &self.file_filter_input
+    }
+
+    pub fn get_file_filter(&self) -> &FileFilter {
+        &self.file_filter
+    }
+
+    pub fn get_directory_selected(&self) -> &Option<PathBuf> {
+        &self.directory_selected
+    }
+
+    pub fn get_filename_input(&self) -> &String {
+        &self.filename_input
+    }
+
+    pub fn get_order_of_filename_components(&self) -> &Vec<FilenameComponents> {
+        &self.order_of_filename_components
+    }
+
+    pub fn get_index_position(&self) -> Option<IndexPosition> {
+        self.index_position
+    }
+
+    pub fn get_files_have_been_organized(&self) -> bool {
+        self.files_have_been_organized
+    }
+
+    pub fn get_trashed_items(&self) -> &Vec<TrashedItem> {
+        &self.trashed_items
+    }
+
+    pub fn get_thumbnail(&self, path_to_file: &PathBuf) -> Option<&ThumbnailState> {
+        self.thumbnails
+            .get(path_to_file)
+            .map(|(_, thumbnail_state)| thumbnail_state)
+    }
+
+    // Only the inspection of the focused file is shown, an older one can
+    // still be around while the new one is read.
+    pub fn get_file_inspection(&self) -> Option<&FileInspection> {
+        let focused_path = self.focused_path.as_ref()?;
+        self.file_inspection
+            .as_ref()
+            .filter(|file_inspection| file_inspection.get_path() == focused_path)
+    }
+
+    pub fn is_hashing(&self, path_to_file: &Path) -> bool {
+        self.hashing_path.as_deref() == Some(path_to_file)
+    }
+
+    pub fn is_focused_file_selected(&self) -> bool {
+        self.get_focused_selected_file().is_some()
+    }
+
+    pub fn is_hovered(&self, path: &PathBuf) -> bool {
+        self.hovered_path.as_ref() == Some(path)
+    }
+
+    pub fn is_dragged(&self, path: &PathBuf) -> bool {
+        self.dragged_path.as_ref() == Some(path)
+    }
+
+    // Focused entries and the directory a drag would drop on are drawn
+    // highlighted.
+    pub fn is_highlighted(&self, path: &PathBuf) -> bool {
+        self.focused_path.as_ref() == Some(path)
+            || (self.dragged_path.is_some() && self.hovered_path.as_ref() == Some(path))
+    }
+
+    pub fn get_drop_filter_input(&self) -> &str {
+        &self.drop_filter_input
+    }
+
+    pub fn get_settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn get_theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    // Directories opened by navigation are listed in the sidebar.
+    fn record_recent_path(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            self.settings.add_recent_path(PathBuf::from(&self.path));
+        }
+    }
+
+    fn remember_last_path(&mut self) {
+        if !self.path.as_os_str().is_empty() {
+            self.settings.set_last_path(PathBuf::from(&self.path));
+        }
+    }
+
+    fn load_organized_directories(&mut self) {
+        match save_directory::read_organized_directories(&self.home_directory_path) {
+            Ok(organized_directories) => self.organized_directories = organized_directories,
+            Err(error) => self.error = error.to_string(),
+        }
+    }
+
+    pub fn get_user_directories(&self) -> &Vec<PathBuf> {
+        &self.user_directories
+    }
+
+    pub fn get_organized_directories(&self) -> &Vec<PathBuf> {
+        &self.organized_directories
+    }
+
+    // Directories that have been removed since are skipped.
+    fn open_last_path(&mut self) {
+        let Some(last_path) = self.settings.get_last_path() else {
+            return;
+        };
+        if !last_path.is_dir() || *last_path == self.path {
+            return;
+        }
+        self.path_input = last_path.display().to_string();
+        if let Err(error) = self.search_path() {
+            self.error = error.to_string();
+        }
+    }
+
+    pub fn get_keymap_input(&self, action: KeyAction) -> &str {
+        self.keymap_inputs
+            .get(&action)
+            .map_or("", |input| input.as_str())
+    }
+
+    pub fn can_undo_commit(&self) -> bool {
+        self.last_journal.is_some() && self.commit_state.is_none()
+    }
+
+    pub fn is_committing(&self) -> bool {
+        self.commit_state.is_some()
+    }
+
+    pub fn get_commit_progress(&self) -> Option<&Progress> {
+        self.commit_state
+            .as_ref()
+            .and_then(|commit_state| commit_state.progress.as_ref())
+    }
+
+    pub fn is_undoing_commit(&self) -> bool {
+        self.commit_state
+            .as_ref()
+            .is_some_and(|commit_state| commit_state.undoing)
+    }
+
+    pub fn is_commit_cancelled(&self) -> bool {
+        self.commit_state
+            .as_ref()
+            .is_some_and(|commit_state| commit_state.cancel.load(Ordering::Relaxed))
+    }
+
+    pub fn get_replaceable_options(&self) -> Vec<Replaceable> {
+        self.replaceable_options.to_owned()
+    }
+
+    pub fn get_files_organized(&self) -> &BTreeMap<OsString, File> {
+        &self.files_organized
+    }
+
+    pub fn get_replace_with_options(&self) -> [ReplaceWith; 2] {
+        self.replace_with_options
+    }
+
+    pub fn get_replaceables(&self) -> &Vec<ReplacableSelection> {
+        &self.replaceables
+    }
+
+    pub fn get_selected_directory_rules(&self) -> &Option<Rules> {
+        &self.selected_directory_rules
     }
 
-    pub fn get_checkbox_states(&self) -> &CheckboxStates {
-        &self.checkbox_states
+    pub fn get_duplicate_groups(&self) -> &Vec<DuplicateGroup> {
+        &self.duplicate_groups
     }
 
-    pub fn get_date_type_selected(&self) -> Option<DateType> {
-        self.date_type_selected
+    pub fn get_similar_image_groups(&self) -> &Vec<SimilarImageGroup> {
+        &self.similar_image_groups
     }
 
-    pub fn get_directory_selected(&self) -> &Option<PathBuf> {
-        &self.directory_selected
+    pub fn is_finding_duplicates(&self) -> bool {
+        self.finding_duplicates
     }
 
-    pub fn get_filename_input(&self) -> &String {
-        &self.filename_input
+    pub fn is_finding_similar_images(&self) -> bool {
+        self.finding_similar_images
     }
 
-    pub fn get_order_of_filename_components(&self) -> &Vec<FilenameComponents> {
-        &self.order_of_filename_components
+    pub fn get_perceptual_hash_type(&self) -> PerceptualHashType {
+        self.perceptual_hash_type
     }
 
-    pub fn get_index_position(&self) -> Option<IndexPosition> {
-        self.index_position
+    pub fn get_similarity_threshold(&self) -> u32 {
+        self.similarity_threshold
     }
 
-    pub fn get_files_have_been_organized(&self) -> bool {
-        self.files_have_been_organized
+    pub fn get_watch_folders(&self) -> &Vec<WatchFolder> {
+        &self.watch_folders
     }
 
-    pub fn get_replaceable_options(&self) -> Vec<Replaceable> {
-        self.replaceable_options.to_owned()
+    pub fn get_watch_log(&self) -> &Vec<String> {
+        &self.watch_log
     }
 
-    pub fn get_files_organized(&self) -> &BTreeMap<OsString, File> {
-        &self.files_organized
+    pub fn get_watch_inbox_input(&self) -> &str {
+        self.watch_inbox_input.as_str()
     }
 
-    pub fn get_replace_with_options(&self) -> [ReplaceWith; 2] {
-        self.replace_with_options
+    pub fn get_watch_organized_directory_input(&self) -> &str {
+        self.watch_organized_directory_input.as_str()
     }
 
-    pub fn get_replaceables(&self) -> &Vec<ReplacableSelection> {
-        &self.replaceables
+    pub fn get_watch_ignore_input(&self) -> &str {
+        self.watch_ignore_input.as_str()
     }
 
-    pub fn get_selected_directory_rules(&self) -> &Option<SelectedDirectoryRules> {
-        &self.selected_directory_rules
+    pub fn is_watching(&self) -> bool {
+        self.watch_folders
+            .iter()
+            .any(|watch_folder| !watch_folder.get_paused())
     }
 
     fn switch_layout(&mut self, layout: &Layout) -> std::io::Result<()> {
@@ -743,7 +2273,95 @@ impl App {
                 self.layout = Layout::Main;
                 Ok(())
             }
+            Layout::WatchFolders => {
+                self.load_watch_folders()?;
+                self.layout = Layout::WatchFolders;
+                Ok(())
+            }
+            Layout::Trash => {
+                self.trashed_items = self.get_trash().list()?;
+                self.layout = Layout::Trash;
+                Ok(())
+            }
+            Layout::KeyboardShortcuts => {
+                self.update_keymap_inputs();
+                self.layout = Layout::KeyboardShortcuts;
+                Ok(())
+            }
+            Layout::Settings => {
+                self.layout = Layout::Settings;
+                Ok(())
+            }
+        }
+    }
+
+    fn get_trash(&self) -> Trash {
+        Trash::open(&self.home_directory_path)
+    }
+
+    fn restore_trashed_item(&mut self, index: usize) -> std::io::Result<()> {
+        let trashed_item = match self.trashed_items.get(index) {
+            Some(trashed_item) => trashed_item,
+            None => {
+                return Err(std::io::Error::new(
+                    ErrorKind::NotFound,
+                    "Trashed item not found.",
+                ))
+            }
+        };
+        self.get_trash().restore(trashed_item)?;
+        self.trashed_items.remove(index);
+        Ok(())
+    }
+
+    fn load_watch_folders(&mut self) -> std::io::Result<()> {
+        self.watch_folders = watch_folder::read_watch_folders(&self.home_directory_path)?;
+        self.watch_log = watch_folder::read_activity_log(&self.home_directory_path)?;
+        Ok(())
+    }
+
+    fn add_watch_folder(&mut self) -> std::io::Result<()> {
+        if self.watch_inbox_input.is_empty() || self.watch_organized_directory_input.is_empty() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Inbox and organized directory must be specified.",
+            ));
+        }
+        let inbox = PathBuf::from(&self.watch_inbox_input);
+        let organized_directory = PathBuf::from(&self.watch_organized_directory_input);
+        if !inbox.is_dir() {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                "Inbox directory not found.",
+            ));
+        }
+        if inbox == organized_directory {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Inbox cannot be the organized directory.",
+            ));
+        }
+        Rules::read_from_save_file(&self.home_directory_path, &organized_directory)?;
+        if self
+            .watch_folders
+            .iter()
+            .any(|watch_folder| watch_folder.get_inbox() == &inbox)
+        {
+            return Err(std::io::Error::new(
+                ErrorKind::AlreadyExists,
+                "Inbox is already watched.",
+            ));
         }
+        self.watch_folders.push(WatchFolder::new(
+            inbox,
+            organized_directory,
+            watch_folder::parse_ignore_patterns(&self.watch_ignore_input),
+        ));
+        watch_folder::write_watch_folders(&self.home_directory_path, &self.watch_folders)?;
+        self.watch_inbox_input.clear();
+        self.watch_organized_directory_input.clear();
+        self.watch_ignore_input.clear();
+        Ok(())
     }
 
     fn switch_layout_windows(&mut self) -> std::io::Result<()> {
@@ -805,12 +2423,143 @@ impl App {
         }
     }
 
+    // Rules of a directory the commit created are forgotten once undoing has
+    // removed the directory again.
+    // Undoes the last commit on its own thread with the progress and cancel
+    // of a commit.
+    fn undo_last_commit(&mut self) -> Task<Message> {
+        if self.commit_state.is_some() {
+            return Task::none();
+        }
+        let Some(journal) = self.last_journal.clone() else {
+            return Task::none();
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.commit_state = Some(CommitState {
+            cancel: Arc::clone(&cancel),
+            progress: None,
+            path_to_directory: None,
+            undoing: true,
+        });
+        let (sender, receiver) = iced::futures::channel::mpsc::unbounded();
+        std::thread::spawn(move || {
+            let result = filerganizer_core::undo_with_progress(
+                &RealFileSystem,
+                &journal,
+                &cancel,
+                &mut |progress| {
+                    let _ = sender.unbounded_send(CommitEvent::Progress(progress.clone()));
+                },
+            );
+            let _ = sender.unbounded_send(CommitEvent::UndoFinished(
+                result.map_err(|error| error.to_string()),
+            ));
+        });
+        Task::run(receiver, Message::CommitProgress)
+    }
+
+    // A directory the undo removed is no longer listed as organized.
+    fn forget_undone_directory(&mut self) -> std::io::Result<()> {
+        if let Some(organized_directory) = self.last_organized_directory.take() {
+            if !organized_directory.exists() {
+                save_directory::remove_directory_from_save_file(
+                    &self.home_directory_path,
+                    &organized_directory,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    // Without a boundary the cleanup stops at the directory being organized.
+    fn get_cleanup_options(&self) -> Option<CleanupOptions> {
+        if !self.remove_empty_directories {
+            return None;
+        }
+        let boundary = match self.cleanup_boundary_input.trim() {
+            "" => PathBuf::from(&self.path),
+            boundary => PathBuf::from(boundary),
+        };
+        Some(CleanupOptions::new(boundary, self.get_junk_file_names()))
+    }
+
+    // The subdirectories a flatten or re-applying the rules empties are
+    // always removed, up to the selected directory itself.
+    fn get_selected_directory_cleanup_options(&self) -> Option<CleanupOptions> {
+        let directory = self.directory_selected.as_ref()?;
+        Some(CleanupOptions::new(
+            PathBuf::from(directory),
+            self.get_junk_file_names(),
+        ))
+    }
+
+    fn get_junk_file_names(&self) -> Vec<String> {
+        self.junk_file_names_input
+            .split(',')
+            .map(str::trim)
+            .filter(|junk_file_name| !junk_file_name.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    // Files of the selected directory are gathered into the target, or into
+    // the selected directory itself when no target is given.
+    fn plan_flatten(&mut self) -> std::io::Result<()> {
+        let Some(directory) = &self.directory_selected else {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                "Select a directory to flatten.",
+            ));
+        };
+        let target = match self.flatten_target_input.trim() {
+            "" => PathBuf::from(directory),
+            target => PathBuf::from(target),
+        };
+        let plan = flatten::plan_flatten(directory, &target, self.strip_rename_prefixes)?;
+        if plan.is_empty() {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                "There are no files to flatten.",
+            ));
+        }
+        self.selected_directory_plan = Some((DirectoryOperation::Flatten, plan));
+        Ok(())
+    }
+
+    fn plan_reapply(&mut self) -> std::io::Result<()> {
+        let (Some(directory), Some(rules)) =
+            (&self.directory_selected, &self.selected_directory_rules)
+        else {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                "Select an organized directory to re-apply its rules.",
+            ));
+        };
+        let plan = filerganizer_core::plan_reapply(rules, directory)?;
+        if plan.is_empty() {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                "Files already follow the rules of the directory.",
+            ));
+        }
+        self.selected_directory_plan = Some((DirectoryOperation::ReapplyRules, plan));
+        Ok(())
+    }
+
     fn init_app_data(&mut self) {
         self.order_of_filename_components = vec![FilenameComponents::OriginalFilename];
         self.directories_selected.clear();
         self.directory_selected = None;
+        self.selected_directory_plan = None;
+        self.focused_path = None;
+        self.file_inspection = None;
         self.date_type_selected = None;
+        self.timezone = Timezone::Local;
+        self.timezone_input.clear();
+        self.sanitize_options = SanitizeOptions::default();
+        self.transfer_mode = TransferMode::Move;
         self.files_selected.clear();
+        self.rename_overrides.clear();
 
         self.root.clear_directory_content();
         self.root = Directory::new(None);
@@ -820,14 +2569,81 @@ impl App {
         self.error.clear();
         self.new_directory_name.clear();
         self.checkbox_states = CheckboxStates::default();
+        self.duplicate_groups.clear();
+        self.similar_image_groups.clear();
+    }
+
+    fn get_quarantine_directory(&self) -> PathBuf {
+        let mut quarantine_directory = PathBuf::from(&self.home_directory_path);
+        quarantine_directory.push(duplicates::QUARANTINE_DIRECTORY_NAME);
+        quarantine_directory
+    }
+
+    fn resolve_duplicates(&mut self, index: usize, action: DuplicateAction) -> std::io::Result<()> {
+        if index >= self.duplicate_groups.len() {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                "Duplicate group not found.",
+            ));
+        }
+        let result = duplicates::apply_duplicate_action(
+            &self.duplicate_groups[index],
+            action,
+            &self.get_quarantine_directory(),
+            &self.get_trash(),
+        );
+        // The group stays listed with the files that were not processed.
+        let (processed_paths, result) = match result {
+            Ok(processed_paths) => {
+                self.duplicate_groups.remove(index);
+                (processed_paths, Ok(()))
+            }
+            Err(failure) => {
+                self.duplicate_groups[index].remove_duplicates(&failure.processed);
+                (failure.processed, Err(failure.error))
+            }
+        };
+        // Hard linked duplicates are still on disk and stay selected.
+        if action != DuplicateAction::HardLink {
+            self.remove_from_files_selected(processed_paths, action == DuplicateAction::Skip);
+        }
+        result
+    }
+
+    // Files that still exist on disk can be put back to their origin directory in the tree
+    fn remove_from_files_selected(&mut self, removed_paths: Vec<PathBuf>, put_back: bool) {
+        for removed_path in removed_paths {
+            let removed = self.files_selected.iter().find_map(|(key, file)| {
+                let origin_path = file.get_metadata().as_ref()?.get_origin_path()?;
+                if origin_path == removed_path {
+                    return Some(key.clone());
+                }
+                None
+            });
+            if let Some((key, file)) = removed.and_then(|key| self.files_selected.remove_entry(&key))
+            {
+                if put_back {
+                    if let Some(parent) = removed_path.parent() {
+                        if let Some(directory) =
+                            self.root.get_mut_directory_by_path(&PathBuf::from(parent))
+                        {
+                            directory.insert_file(key, file);
+                        }
+                    }
+                }
+            }
+        }
     }
 
     fn search_path(&mut self) -> std::io::Result<()> {
         if self.path_input.is_empty() {
             self.path_input = String::from("/");
         }
+        self.path_completion = None;
+        self.path_input = path_completion::expand_path(&self.path_input, &self.home_directory_path);
         self.write_directories_from_path(&PathBuf::from(&self.path_input))?;
         self.path = PathBuf::from(&self.path_input);
+        self.record_recent_path();
         self.update_path_input();
         Ok(())
     }
@@ -1055,6 +2871,12 @@ impl App {
                 "Directory name not specified.",
             ));
         }
+        if !self.is_timezone_input_valid() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Unknown timezone.",
+            ));
+        }
         let mut path_to_directory = PathBuf::from(&self.path);
         path_to_directory.push(&self.new_directory_name);
         if let Err(error) = save_directory::read_save_file_content(
@@ -1118,7 +2940,9 @@ impl App {
                 &self.filename_input,
                 &self.order_of_filename_components,
                 self.date_type_selected,
+                self.timezone,
                 self.index_position,
+                &self.rename_overrides,
             );
 
             // Write directory path and checkbox states to a file
@@ -1133,7 +2957,12 @@ impl App {
                 self.files_organized.clear();
                 return Err(error);
             }
-            return Ok(());
+            self.rename_overrides.clear();
+            let mut path_to_directory = PathBuf::from(&self.path);
+            path_to_directory.push(&self.new_directory_name);
+            self.organized_directories_to_sanitize
+                .insert(path_to_directory, self.sanitize_options);
+            return self.update_sanitized_names();
         }
         Err(std::io::Error::new(
             ErrorKind::NotFound,
@@ -1146,30 +2975,43 @@ impl App {
         checkbox_states: CheckboxStates,
         date_type: Option<DateType>,
     ) -> std::io::Result<()> {
+        if !self.is_timezone_input_valid() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Unknown timezone.",
+            ));
+        }
         if let Some(selected_dir) = self.root.get_mut_directory_by_path(&self.path) {
             while let Some((key, mut value)) = self.files_selected.pop_last() {
-                let file_name = app_util::convert_os_str_to_str(&key)?;
+                let file_name = convert_os_str_to_str(&key)?;
                 let mut renamed_file_name = String::new();
                 let file_count = selected_dir.get_file_count();
-                organize_files::rename_file_name(organize_files::RenameData::build(
-                    &mut renamed_file_name,
-                    &checkbox_states,
-                    &self.replaceables,
-                    &self.new_directory_name,
-                    &self.filename_input,
-                    file_count,
-                    &self.order_of_filename_components,
-                    file_name,
-                    &value,
-                    date_type,
-                    self.index_position,
-                ));
+                match organize_files::get_name_override(&self.rename_overrides, &key) {
+                    Some(name_override) => renamed_file_name = name_override,
+                    None => organize_files::rename_file_name(organize_files::RenameData::build(
+                        &mut renamed_file_name,
+                        &checkbox_states,
+                        &self.replaceables,
+                        &self.new_directory_name,
+                        &self.filename_input,
+                        file_count,
+                        &self.order_of_filename_components,
+                        file_name,
+                        &value,
+                        date_type,
+                        self.timezone,
+                        self.index_position,
+                    )),
+                }
                 organize_files::create_destination_path(&self.path, vec![], &mut value);
                 self.files_organized
                     .insert(OsString::from(&renamed_file_name), value.clone());
                 selected_dir.insert_file(OsString::from(renamed_file_name), value);
             }
-            return Ok(());
+            self.rename_overrides.clear();
+            self.organized_directories_to_sanitize
+                .insert(PathBuf::from(&self.path), self.sanitize_options);
+            return self.update_sanitized_names();
         }
         Err(std::io::Error::new(
             ErrorKind::NotFound,
@@ -1264,13 +3106,11 @@ impl App {
     fn select_multiple_files_from_directories(
         &mut self,
         new_file_name: &str,
-        new_file_index: usize,
         directory_path: &PathBuf,
     ) -> std::io::Result<()> {
         // Select multiple files from_directories
         if self.multiple_selection.file_name.is_empty() {
             self.multiple_selection.file_name = String::from(new_file_name);
-            self.multiple_selection.file_index = new_file_index;
             return Ok(());
         } else {
             // Do multiple select
@@ -1278,23 +3118,17 @@ impl App {
             let mut files_unselected = BTreeMap::new();
             if let Some(directory) = self.root.get_mut_directory_by_path(directory_path) {
                 if let Some(mut files) = directory.get_mut_files().take() {
-                    if self.multiple_selection.file_index > new_file_index {
-                        // Select from bottom
-                        (files_selected, files_unselected) = multiple_select_files(
-                            &mut files,
-                            &self.multiple_selection.file_name,
-                            new_file_name,
-                            SelectionDirection::Bottom,
-                        );
-                    } else {
-                        // Select from top
-                        (files_selected, files_unselected) = multiple_select_files(
-                            &mut files,
-                            &self.multiple_selection.file_name,
-                            new_file_name,
-                            SelectionDirection::Up,
-                        );
-                    }
+                    let order: Vec<OsString> =
+                        file_list::visible_file_names(&files, self.file_sort, &self.file_filter)
+                            .into_iter()
+                            .cloned()
+                            .collect();
+                    (files_selected, files_unselected) = multiple_select_files(
+                        &mut files,
+                        &order,
+                        &self.multiple_selection.file_name,
+                        new_file_name,
+                    );
                     directory.insert_empty_files();
                 }
             }
@@ -1328,7 +3162,6 @@ impl App {
                 }
             }
         }
-        self.multiple_selection.file_index = 0;
         self.multiple_selection.file_name.clear();
         Ok(())
     }
@@ -1336,31 +3169,20 @@ impl App {
     fn select_multiple_files_from_files_selected(
         &mut self,
         new_file_name: &str,
-        new_file_index: usize,
         origin_directory_path: &PathBuf,
     ) -> std::io::Result<()> {
         if self.multiple_selection.file_name.is_empty() {
             self.multiple_selection.file_name = String::from(new_file_name);
-            self.multiple_selection.file_index = new_file_index;
             return Ok(());
         } else {
             // Write logic for second click in files_selected
-            let (files_selected, files_unselected) =
-                if self.multiple_selection.file_index > new_file_index {
-                    multiple_select_files(
-                        &mut self.files_selected,
-                        &self.multiple_selection.file_name,
-                        new_file_name,
-                        SelectionDirection::Bottom,
-                    )
-                } else {
-                    multiple_select_files(
-                        &mut self.files_selected,
-                        &self.multiple_selection.file_name,
-                        new_file_name,
-                        SelectionDirection::Up,
-                    )
-                };
+            let order: Vec<OsString> = self.files_selected.keys().cloned().collect();
+            let (files_selected, files_unselected) = multiple_select_files(
+                &mut self.files_selected,
+                &order,
+                &self.multiple_selection.file_name,
+                new_file_name,
+            );
 
             // Do error checks
             if let Err(error) = app_util::is_duplicate_files_in_files_selected(
@@ -1392,7 +3214,6 @@ impl App {
                     }
                 }
             }
-            self.multiple_selection.file_index = 0;
             self.multiple_selection.file_name.clear();
             return Ok(());
         }
@@ -1404,36 +3225,32 @@ impl App {
         }
         if let Some(selected_dir_path) = &self.directory_selected {
             if let Some(selected_dir) = self.root.get_mut_directory_by_path(selected_dir_path) {
-                let (
-                    checkbox_states,
-                    date_type,
-                    index_position,
-                    replaceables,
-                    order_of_filename_components,
-                    custom_filename,
-                ) = save_directory::read_directory_rules_from_file(
-                    &self.home_directory_path,
-                    selected_dir_path,
-                )?;
+                let rules =
+                    Rules::read_from_save_file(&self.home_directory_path, selected_dir_path)?;
                 if let Some(last) = selected_dir_path.iter().last() {
-                    let directory_name = app_util::convert_os_str_to_str(last)?;
+                    let directory_name = convert_os_str_to_str(last)?;
                     organize_files::move_files_to_organized_directory(
                         &self.path,
                         &mut self.files_organized,
                         selected_dir,
                         organize_files::OrganizingData::new(
                             self.files_selected.clone(),
-                            &checkbox_states,
-                            &replaceables,
+                            rules.get_checkbox_states(),
+                            rules.get_replaceables(),
                             directory_name,
-                            &custom_filename,
-                            &order_of_filename_components,
-                            date_type,
-                            index_position,
+                            rules.get_custom_filename(),
+                            rules.get_order_of_filename_components(),
+                            *rules.get_date_type_selected(),
+                            rules.get_timezone(),
+                            *rules.get_index_position(),
+                            &self.rename_overrides,
                         ),
                     )?;
                     self.files_selected.clear();
-                    return Ok(());
+                    self.rename_overrides.clear();
+                    self.organized_directories_to_sanitize
+                        .insert(PathBuf::from(selected_dir_path), rules.get_sanitize_options());
+                    return self.update_sanitized_names();
                 }
             }
         }
@@ -1443,6 +3260,53 @@ impl App {
         ))
     }
 
+    // Organizing changes the selection, the tree and the organized files
+    // before the new names are checked for the target filesystem. They are
+    // put back as they were when organizing fails, so names rejected there
+    // leave nothing half organized.
+    fn organize_staged(
+        &mut self,
+        organize: impl FnOnce(&mut Self) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let root = self.root.clone();
+        let files_selected = self.files_selected.clone();
+        let files_organized = self.files_organized.clone();
+        let rename_overrides = self.rename_overrides.clone();
+        let organized_directories_to_sanitize = self.organized_directories_to_sanitize.clone();
+        let sanitized_names = self.sanitized_names.clone();
+        let result = organize(self);
+        if result.is_err() {
+            self.root = root;
+            self.files_selected = files_selected;
+            self.files_organized = files_organized;
+            self.rename_overrides = rename_overrides;
+            self.organized_directories_to_sanitize = organized_directories_to_sanitize;
+            self.sanitized_names = sanitized_names;
+        }
+        result
+    }
+
+    // Names are sanitized for the target filesystem of each organized
+    // directory the files go to.
+    fn sanitize_files_organized(&self) -> std::io::Result<(Plan, Vec<SanitizedName>)> {
+        let mut plan = Plan::from_files_organized(&self.files_organized);
+        let mut sanitized_names = Vec::new();
+        for (path_to_directory, sanitize_options) in self.organized_directories_to_sanitize.iter() {
+            let (sanitized_plan, names) =
+                sanitize::sanitize_plan(&plan, path_to_directory, *sanitize_options)?;
+            plan = sanitized_plan;
+            sanitized_names.extend(names);
+        }
+        Ok((plan, sanitized_names))
+    }
+
+    fn update_sanitized_names(&mut self) -> std::io::Result<()> {
+        self.sanitized_names.clear();
+        let (_, sanitized_names) = self.sanitize_files_organized()?;
+        self.sanitized_names = sanitized_names;
+        Ok(())
+    }
+
     fn swap_filename_components(&mut self, index: usize) {
         if self.order_of_filename_components.len() >= index {
             let temp = self.order_of_filename_components[index - 1].clone();
@@ -1452,29 +3316,6 @@ impl App {
         }
     }
 
-    fn follow_directory_path(&self, current_path: &PathBuf) -> Option<&Directory> {
-        let mut path_stack = PathBuf::new();
-        let mut dir = None;
-        for (i, component) in current_path.components().enumerate() {
-            path_stack.push(component);
-            if i == 0 {
-                continue;
-            }
-            if std::env::consts::OS == "windows" {
-                if i == 1 {
-                    continue;
-                }
-            }
-            let directory = self.root.get_directory_by_path(&path_stack);
-            if directory.get_name() != Some(OsString::from("/")) {
-                dir = Some(directory);
-            } else {
-                break;
-            }
-        }
-        dir
-    }
-
     fn path_has_only_prefix(&self, path: &str) -> bool {
         let mut contains_character = false;
         let mut contains_colon = false;
@@ -1502,117 +3343,94 @@ impl App {
         false
     }
 
-    fn search_directories_from_path(&mut self) -> std::io::Result<String> {
-        let current_path = PathBuf::from(&self.path_input);
-        if std::env::consts::OS == "windows" {
-            let current_path = app_util::convert_path_to_str(&current_path)?;
-            if self.path_has_only_prefix(current_path) {
-                let mut prefix_path = String::from(current_path);
-                if prefix_path.len() == 2 {
-                    prefix_path.push('\\');
-                }
-                if prefix_path.len() == 1 {
-                    prefix_path.push(':');
-                    prefix_path.push('\\');
-                }
-                return Ok(prefix_path);
-            }
-        }
-        if let Some(last_component) = current_path.iter().last() {
-            if let Some(directory) = self.follow_directory_path(&current_path) {
-                let mut dir_with_greatest_score = None;
-                let mut path_is_equal = false;
-
-                if directory.get_name() == Some(OsString::from(last_component)) {
-                    let last_component = app_util::convert_os_str_to_str(last_component)?;
-                    dir_with_greatest_score = Some(last_component);
-                    path_is_equal = true;
-                } else if let Some(sub_directories) = directory.get_directories() {
-                    let mut score = 0;
-                    for dir_name in sub_directories.keys() {
-                        if let Some((last_component, dir_name)) =
-                            self.get_path_components_to_str(last_component, dir_name)
-                        {
-                            let count = app_util::is_substring(last_component, dir_name);
-                            if count > score {
-                                score = count;
-                                dir_with_greatest_score = Some(dir_name);
-                            }
-                        }
-                    }
-                }
-                if let Some(dir) = dir_with_greatest_score {
-                    return self.add_new_dir_to_path_input(dir, path_is_equal);
+    // Completes the last component of the path input like a shell. A single
+    // match is completed and opened, several matches are completed to their
+    // common prefix and listed under the input, and completing again cycles
+    // through them.
+    fn complete_path(&mut self) -> std::io::Result<()> {
+        if let Some(path_completion) = &mut self.path_completion {
+            if path_completion.get_completed_input() == self.path_input
+                && path_completion.get_candidates().len() > 1
+            {
+                if let Some(candidate_path) = path_completion.select_next() {
+                    self.path_input = candidate_path;
                 }
+                return Ok(());
             }
-            if current_path == PathBuf::from("/") {
-                return Ok(String::from("/"));
-            }
-        }
-        Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "No match found.",
-        ))
-    }
-
-    fn insert_slash(&self, path: &mut String) {
-        match std::env::consts::OS {
-            "windows" => path.push_str("\\"),
-            "linux" | "macos" => path.push_str("/"),
-            _ => {}
         }
-    }
-
-    fn add_new_dir_to_path_input(&self, dir: &str, path_is_equal: bool) -> std::io::Result<String> {
-        let mut result_path = self.path_input.clone();
-        if path_is_equal {
-            if result_path.ends_with("/") || result_path.ends_with("\\") {
-                return Ok(result_path);
-            }
-            self.insert_slash(&mut result_path);
-            return Ok(result_path);
+        self.path_completion = None;
+        let input = path_completion::expand_path(&self.path_input, &self.home_directory_path);
+        if std::env::consts::OS == "windows" && self.path_has_only_prefix(&input) {
+            let mut prefix_path = input;
+            if prefix_path.len() == 2 {
+                prefix_path.push('\\');
+            }
+            if prefix_path.len() == 1 {
+                prefix_path.push(':');
+                prefix_path.push('\\');
+            }
+            self.path_input = prefix_path;
+            return self.search_path();
         }
-
-        while let Some(ch) = result_path.pop() {
-            if ch == '/' || ch == '\\' {
-                break;
+        let (parent, candidates) = path_completion::find_candidates(&input)?;
+        match candidates.as_slice() {
+            [] => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "No match found.",
+            )),
+            [candidate] => {
+                let candidate_path =
+                    format!("{}{}{}", parent, candidate, std::path::MAIN_SEPARATOR);
+                self.open_completed_path(candidate_path)
+            }
+            _ => {
+                self.path_input = format!(
+                    "{}{}",
+                    parent,
+                    path_completion::longest_common_prefix(&candidates)
+                );
+                self.path_completion = Some(PathCompletion::new(
+                    parent,
+                    candidates,
+                    self.path_input.clone(),
+                ));
+                Ok(())
             }
         }
+    }
 
-        self.insert_slash(&mut result_path);
-        result_path.push_str(dir);
-        self.insert_slash(&mut result_path);
-        return Ok(result_path);
+    // The separator is kept at the end, so completing again lists the
+    // directories inside.
+    fn open_completed_path(&mut self, completed_path: String) -> std::io::Result<()> {
+        self.path_input = completed_path.clone();
+        self.search_path()?;
+        self.path_input = completed_path;
+        Ok(())
     }
 
-    fn get_path_components_to_str<'a>(
-        &'a self,
-        last_component: &'a OsStr,
-        dir_name: &'a OsStr,
-    ) -> Option<(&'a str, &'a str)> {
-        if let Some(dir_name) = dir_name.to_str() {
-            if let Some(last_component) = last_component.to_str() {
-                return Some((last_component, dir_name));
-            }
-        }
-        None
+    pub fn get_path_completion(&self) -> Option<&PathCompletion> {
+        self.path_completion.as_ref()
     }
 
     fn rename_directory_name_based_on_rules(&mut self) {
+        self.new_directory_name = self.get_directory_name_based_on_rules();
+    }
+
+    fn get_directory_name_based_on_rules(&self) -> String {
+        let mut directory_name = self.new_directory_name.to_owned();
         if self.checkbox_states.use_only_ascii {
-            self.new_directory_name =
-                organize_files::replace_non_ascii(self.new_directory_name.to_owned());
+            directory_name = organize_files::replace_non_ascii(directory_name);
         }
 
         if self.checkbox_states.convert_uppercase_to_lowercase {
-            self.new_directory_name = self.new_directory_name.to_lowercase();
+            directory_name = directory_name.to_lowercase();
         }
         if self.checkbox_states.replace_character {
             for replaceable in &self.replaceables {
                 if let Some(replace) = replaceable.get_replaceable_selected() {
                     if let Some(replace_with) = replaceable.get_replace_with_selected() {
                         organize_files::replace_character_with(
-                            &mut self.new_directory_name,
+                            &mut directory_name,
                             replace,
                             replace_with,
                         );
@@ -1620,54 +3438,36 @@ impl App {
                 }
             }
         }
+        directory_name
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum SelectionDirection {
-    Up,
-    Bottom,
-}
-
+// Takes the files from previous_file_name to new_file_name, in either
+// direction, in the order the files are shown. Files that are not shown are
+// left unselected.
 pub fn multiple_select_files(
     files_holder: &mut BTreeMap<OsString, File>,
+    order: &[OsString],
     previous_file_name: &str,
     new_file_name: &str,
-    direction: SelectionDirection,
 ) -> (BTreeMap<OsString, File>, BTreeMap<OsString, File>) {
+    let previous_position = order
+        .iter()
+        .position(|file_name| file_name == previous_file_name);
+    let new_position = order.iter().position(|file_name| file_name == new_file_name);
+    let in_range: HashSet<&OsString> = match (previous_position, new_position) {
+        (Some(previous), Some(new)) => order[previous.min(new)..=previous.max(new)]
+            .iter()
+            .collect(),
+        _ => HashSet::new(),
+    };
     let mut files_selected = BTreeMap::new();
     let mut files_unselected = BTreeMap::new();
-    let mut in_boundaries = false;
-    match direction {
-        SelectionDirection::Bottom => {
-            while let Some((key, value)) = files_holder.pop_last() {
-                if OsString::from(previous_file_name) == key {
-                    in_boundaries = true;
-                }
-                if in_boundaries {
-                    files_selected.insert(key.to_owned(), value);
-                } else {
-                    files_unselected.insert(key.to_owned(), value);
-                }
-                if OsString::from(new_file_name) == key {
-                    in_boundaries = false;
-                }
-            }
-        }
-        SelectionDirection::Up => {
-            while let Some((key, value)) = files_holder.pop_first() {
-                if OsString::from(previous_file_name) == key {
-                    in_boundaries = true;
-                }
-                if in_boundaries {
-                    files_selected.insert(key.to_owned(), value);
-                } else {
-                    files_unselected.insert(key.to_owned(), value);
-                }
-                if OsString::from(new_file_name) == key {
-                    in_boundaries = false;
-                }
-            }
+    while let Some((key, value)) = files_holder.pop_first() {
+        if in_range.contains(&key) {
+            files_selected.insert(key, value);
+        } else {
+            files_unselected.insert(key, value);
         }
     }
     (files_selected, files_unselected)
@@ -1676,8 +3476,7 @@ pub fn multiple_select_files(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::directory::system_dir;
-    use crate::metadata::Metadata;
+    use filerganizer_core::metadata::Metadata;
     use std::fs;
 
     #[test]
@@ -1725,14 +3524,12 @@ mod tests {
         if let Err(error) = app.is_directory_creation_valid(TEST_SAVE_FILE) {
             assert_eq!(error.to_string(), "Directory name not specified.");
         }
-        let home_path =
-            system_dir::get_home_directory().expect("Could not get home directory path");
-        let _save_file = save_directory::create_save_file(&home_path, TEST_SAVE_FILE)
-            .expect("Failed to create temporary save file.");
-        let mut path_to_file = PathBuf::from(home_path);
-        path_to_file.push(TEST_SAVE_FILE);
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        app.home_directory_path = test_directory.path().to_path_buf();
+        let _save_file =
+            save_directory::create_save_file(&app.home_directory_path, TEST_SAVE_FILE)
+                .expect("Failed to create temporary save file.");
         if let Err(error) = app.is_directory_creation_valid(TEST_SAVE_FILE) {
-            fs::remove_file(path_to_file).expect("Failed to remove test save file");
             panic!(
                 "is_directory_creation_valid could not read temporary save file: {}",
                 error
@@ -1753,10 +3550,8 @@ mod tests {
 
         app.index_position = Some(IndexPosition::Before);
         if let Err(error) = app.is_directory_creation_valid(TEST_SAVE_FILE) {
-            fs::remove_file(path_to_file).expect("Failed to remove test save file");
             panic!("Directory creation should be valid: {}", error);
         }
-        fs::remove_file(path_to_file).expect("Failed to remove test save file");
     }
 
     #[test]
@@ -1779,4 +3574,264 @@ mod tests {
         app.update_path_input();
         assert_eq!(app.path_input, String::from("/home/verneri/rust"));
     }
+
+    #[test]
+    fn test_navigation_records_recent_path() {
+        let mut app = App {
+            path: PathBuf::from("/home/verneri/rust"),
+            ..Default::default()
+        };
+        let _ = app.update(Message::DropDownDirectory(PathBuf::from("/home/verneri/rust")));
+        assert_eq!(app.path, PathBuf::from("/home/verneri"));
+        assert_eq!(
+            app.settings.get_recent_paths(),
+            &vec![PathBuf::from("/home/verneri")]
+        );
+    }
+
+    #[test]
+    fn test_multiple_select_files_follows_shown_order() {
+        let mut files = BTreeMap::new();
+        for file_name in ["img1.jpg", "img10.jpg", "img2.jpg", "img3.jpg"] {
+            files.insert(OsString::from(file_name), File::new(Metadata::new()));
+        }
+        let order: Vec<OsString> = ["img3.jpg", "img2.jpg", "img10.jpg", "img1.jpg"]
+            .map(OsString::from)
+            .to_vec();
+        let (files_selected, files_unselected) =
+            multiple_select_files(&mut files, &order, "img1.jpg", "img2.jpg");
+        assert!(files.is_empty());
+        assert_eq!(
+            files_selected.keys().collect::<Vec<_>>(),
+            vec!["img1.jpg", "img10.jpg", "img2.jpg"]
+        );
+        assert_eq!(files_unselected.keys().collect::<Vec<_>>(), vec!["img3.jpg"]);
+    }
+
+    #[test]
+    fn test_keyboard_focus_and_selection() {
+        let path = PathBuf::from("/photos");
+        let mut photos = Directory::new(None);
+        photos.insert_directory(Directory::new(None), "jpg");
+        for file_name in ["a.txt", "b.txt"] {
+            let metadata = Metadata::build(
+                Some(OsString::from(file_name)),
+                None,
+                None,
+                None,
+                None,
+                false,
+                Some(path.join(file_name)),
+                None,
+            );
+            photos.insert_file(OsString::from(file_name), File::new(metadata));
+        }
+        let mut app = App::default();
+        app.root.insert_directory(photos, "photos");
+        app.path = PathBuf::from(&path);
+        app.layout = Layout::DirectorySelectionLayout;
+
+        let _ = app.run_key_action(KeyAction::FocusUp);
+        assert_eq!(app.focused_path, Some(path.join("b.txt")));
+        let _ = app.run_key_action(KeyAction::FocusDown);
+        assert_eq!(app.focused_path, Some(path.join("b.txt")));
+        let _ = app.run_key_action(KeyAction::FocusUp);
+        let _ = app.run_key_action(KeyAction::FocusUp);
+        assert_eq!(app.focused_path, Some(path.join("jpg")));
+
+        // Selecting a directory does nothing, a selected file keeps its place
+        let _ = app.run_key_action(KeyAction::ToggleSelection);
+        assert!(app.files_selected.is_empty());
+        let _ = app.run_key_action(KeyAction::FocusDown);
+        let _ = app.run_key_action(KeyAction::ToggleSelection);
+        assert!(app.files_selected.contains_key(OsStr::new("a.txt")));
+        assert_eq!(app.get_focusable_entries().len(), 3);
+        let _ = app.run_key_action(KeyAction::ToggleSelection);
+        assert!(app.files_selected.is_empty());
+
+        let _ = app.run_key_action(KeyAction::ExtendSelectionDown);
+        assert_eq!(app.focused_path, Some(path.join("b.txt")));
+        assert_eq!(app.files_selected.len(), 2);
+    }
+
+    #[test]
+    fn test_select_dropped_directory() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let directory = test_directory.path();
+        fs::create_dir_all(directory.join("trip")).expect("Failed to create test directory");
+        for file_name in ["a.jpg", "notes.txt", "trip/b.JPG", "trip/a.jpg"] {
+            fs::write(directory.join(file_name), file_name).expect("Failed to write test file");
+        }
+        let mut app = App::default();
+        let _ = app.update(Message::DropFilterInput(String::from(".jpg")));
+
+        let result = app.select_dropped_path(&directory.to_path_buf());
+        let mut file_names: Vec<&OsString> = app.files_selected.keys().collect();
+        file_names.sort();
+        assert_eq!(file_names, vec!["a.jpg", "b.JPG"]);
+        assert_eq!(
+            result.expect_err("Names should collide").to_string(),
+            "1 dropped files have the same name as a selected file."
+        );
+
+        let _ = app.update(Message::SelectFile(FileSelectedLocation::FromFilesSelected(
+            directory.join("a.jpg"),
+        )));
+        assert_eq!(app.files_selected.keys().collect::<Vec<_>>(), vec!["b.JPG"]);
+
+        // Unreadable files are counted and the readable ones still selected.
+        let result = app.select_dropped_path(&directory.join("missing.jpg"));
+        assert_eq!(
+            result.expect_err("Missing file was read").to_string(),
+            "1 dropped files could not be read."
+        );
+        assert_eq!(app.files_selected.len(), 1);
+    }
+
+    #[test]
+    fn test_rename_previews() {
+        let mut app = App {
+            path: PathBuf::from("/filerganizer_rename_preview"),
+            new_directory_name: String::from("Trip"),
+            checkbox_states: CheckboxStates {
+                convert_uppercase_to_lowercase: true,
+                ..Default::default()
+            },
+            order_of_filename_components: vec![FilenameComponents::OriginalFilename],
+            ..Default::default()
+        };
+        for file_name in ["Beach.JPG", "notes.txt"] {
+            app.files_selected.insert(
+                OsString::from(file_name),
+                File::new(Metadata::build(
+                    Some(OsString::from(file_name)),
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                    Some(app.path.join(file_name)),
+                    None,
+                )),
+            );
+        }
+        let _ = app.update(Message::TargetProfileSelected(TargetProfile::Windows));
+        let _ = app.update(Message::RenameOverrideInput(
+            OsString::from("notes.txt"),
+            String::from("to do?.txt"),
+        ));
+
+        let previews = app
+            .get_rename_previews()
+            .as_ref()
+            .expect("Failed to preview renames");
+        let (beach, beach_sanitized) = &previews[0];
+        assert_eq!(beach.get_new_file_name(), "beach.jpg");
+        assert_eq!(
+            beach.get_destination_path(),
+            &app.path.join("trip").join("beach.jpg")
+        );
+        assert!(beach_sanitized.is_none());
+        let (notes, notes_sanitized) = &previews[1];
+        assert!(notes.is_name_overridden());
+        let notes_sanitized = notes_sanitized.as_ref().expect("Name should be sanitized");
+        assert_eq!(
+            notes_sanitized.get_destination(),
+            &app.path.join("trip").join("to do_.txt")
+        );
+
+        let _ = app.update(Message::RenameOverrideInput(
+            OsString::from("notes.txt"),
+            String::new(),
+        ));
+        assert_eq!(app.get_rename_override(&OsString::from("notes.txt")), "");
+
+        // Overrides are dropped with the file they belong to.
+        let _ = app.update(Message::RenameOverrideInput(
+            OsString::from("notes.txt"),
+            String::from("later.txt"),
+        ));
+        let _ = app.update(Message::SelectFile(FileSelectedLocation::FromFilesSelected(
+            app.path.join("notes.txt"),
+        )));
+        assert!(!app.files_selected.contains_key(&OsString::from("notes.txt")));
+        assert_eq!(app.get_rename_override(&OsString::from("notes.txt")), "");
+        assert_eq!(app.get_rename_previews().as_ref().map(Vec::len), Ok(1));
+    }
+
+    #[test]
+    fn test_rejected_names_leave_files_selected() {
+        let mut app = App {
+            path: PathBuf::from("/"),
+            new_directory_name: String::from("Trip"),
+            checkbox_states: CheckboxStates {
+                convert_uppercase_to_lowercase: true,
+                ..Default::default()
+            },
+            order_of_filename_components: vec![FilenameComponents::OriginalFilename],
+            sanitize_options: SanitizeOptions::new(TargetProfile::Windows, SanitizeAction::Reject),
+            ..Default::default()
+        };
+        app.files_selected.insert(
+            OsString::from("notes.txt"),
+            File::new(Metadata::build(
+                Some(OsString::from("notes.txt")),
+                None,
+                None,
+                None,
+                None,
+                false,
+                Some(PathBuf::from("/notes.txt")),
+                None,
+            )),
+        );
+        app.rename_overrides
+            .insert(OsString::from("notes.txt"), String::from("to do?.txt"));
+
+        let result = app.organize_staged(|app| {
+            let files_selected = std::mem::take(&mut app.files_selected);
+            app.create_directory_with_selected_files(files_selected)
+        });
+        assert_eq!(
+            result.expect_err("Name should be rejected").kind(),
+            ErrorKind::InvalidInput
+        );
+        assert!(app.files_organized.is_empty());
+        assert!(app.files_selected.contains_key(&OsString::from("notes.txt")));
+        assert_eq!(app.get_rename_override(&OsString::from("notes.txt")), "to do?.txt");
+        assert!(app.root.get_files().as_ref().is_none_or(BTreeMap::is_empty));
+    }
+
+    #[test]
+    fn test_complete_path() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let directory = test_directory.path();
+        for directory_name in ["Alpha", "alpine", "beta"] {
+            fs::create_dir_all(directory.join(directory_name))
+                .expect("Failed to create test directory");
+        }
+        let separator = std::path::MAIN_SEPARATOR;
+        let root = format!("{}{}", directory.display(), separator);
+        let mut app = App {
+            home_directory_path: directory.to_path_buf(),
+            ..Default::default()
+        };
+        app.insert_root_directory(&PathBuf::from("/"));
+
+        let _ = app.update(Message::TextInput(format!("{}AL", root)));
+        let _ = app.update(Message::TabKeyPressed);
+        assert_eq!(app.get_path_input(), format!("{}Alp", root));
+        let candidates = app.get_path_completion().map(|completion| completion.get_candidates());
+        assert_eq!(candidates, Some(&vec![String::from("Alpha"), String::from("alpine")]));
+        let _ = app.update(Message::TabKeyPressed);
+        assert_eq!(app.get_path_input(), format!("{}Alpha{}", root, separator));
+        let _ = app.update(Message::TabKeyPressed);
+        assert_eq!(app.get_path_input(), format!("{}alpine{}", root, separator));
+
+        let _ = app.update(Message::TextInput(format!("~{}B", separator)));
+        assert!(app.get_path_completion().is_none());
+        let _ = app.update(Message::TabKeyPressed);
+        assert_eq!(app.get_path(), &directory.join("beta"));
+        assert_eq!(app.get_path_input(), format!("{}beta{}", root, separator));
+    }
 }
@@ -1,11 +1,9 @@
-use crate::directory::Directory;
-use crate::file::File;
-use crate::layouts::CheckboxStates;
-use crate::metadata::DateType;
+use filerganizer_core::directory::Directory;
+use filerganizer_core::file::File;
 use std::collections::BTreeMap;
-use std::ffi::{OsStr, OsString};
-use std::io::ErrorKind;
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
 
 pub fn is_duplicate_files_in_files_selected(
     root_dir: &Directory,
@@ -41,65 +39,50 @@ pub fn is_duplicate_files_in_directory_selection(
     Ok(())
 }
 
-pub fn convert_os_str_to_str(key: &OsStr) -> std::io::Result<&str> {
-    if let Some(key) = key.to_str() {
-        return Ok(key);
+pub fn matches_wildcard(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star = None;
+    let mut star_match = 0;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some(p);
+            star_match = t;
+            p += 1;
+        } else if let Some(star_position) = star {
+            p = star_position + 1;
+            star_match += 1;
+            t = star_match;
+        } else {
+            return false;
+        }
     }
-    Err(std::io::Error::new(
-        ErrorKind::Other,
-        "Could not parse &OsStr to &str",
-    ))
-}
-
-pub fn convert_path_to_str<'a>(path: &'a PathBuf) -> std::io::Result<&'a str> {
-    if let Some(path) = path.to_str() {
-        return Ok(path);
+    while p < pattern.len() && pattern[p] == '*' {
+        p += 1;
     }
-    Err(std::io::Error::new(
-        ErrorKind::Other,
-        "Coult not parse PathBuf to &str",
-    ))
+    p == pattern.len()
 }
 
-pub fn just_rename_checked(checkbox_states: &CheckboxStates) -> bool {
-    if checkbox_states.insert_directory_name_to_file_name
-        || checkbox_states.insert_date_to_file_name
-        || checkbox_states.convert_uppercase_to_lowercase
-        || checkbox_states.replace_character
-        || checkbox_states.use_only_ascii
-        || checkbox_states.remove_original_file_name
-        || checkbox_states.add_custom_name
-    {
-        return true;
+// Writes to a temporary file next to path and renames it over path, so a
+// crash during the write leaves the old file in place.
+pub fn write_file_atomically(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut temporary_name = path.file_name().map(OsString::from).unwrap_or_default();
+    temporary_name.push(format!(".{}.tmp", std::process::id()));
+    let temporary_path = path.with_file_name(temporary_name);
+    let result = std::fs::File::create(&temporary_path)
+        .and_then(|mut file| {
+            file.write_all(content)?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&temporary_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary_path);
     }
-    return false;
-}
-
-pub fn get_date_type(date_type: Option<DateType>) -> std::io::Result<DateType> {
-    if let Some(date_type) = date_type {
-        return Ok(date_type);
-    }
-    Err(std::io::Error::new(
-        ErrorKind::NotFound,
-        "Date type not specified.",
-    ))
-}
-
-pub fn is_substring(needle: &str, haystack: &str) -> usize {
-    let mut score = 0;
-    let mut iterator = needle.chars();
-    for hay in haystack.chars() {
-        if let Some(next) = iterator.next() {
-            if hay == next {
-                score += 1;
-            } else {
-                return score;
-            }
-        } else {
-            break;
-        }
-    }
-    score
+    result
 }
 
 #[cfg(test)]
@@ -107,12 +90,31 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_just_rename_checked() {
-        let checkbox_states =
-            CheckboxStates::new(false, false, true, true, true, true, true, true, true);
-        assert_eq!(just_rename_checked(&checkbox_states), true);
-        let checkbox_states =
-            CheckboxStates::new(true, true, false, false, false, false, false, false, false);
-        assert_eq!(just_rename_checked(&checkbox_states), false);
+    fn test_matches_wildcard() {
+        assert!(matches_wildcard("*.part", "movie.mkv.part"));
+        assert!(matches_wildcard("Thumbs.db", "Thumbs.db"));
+        assert!(matches_wildcard("IMG_????.jpg", "IMG_0001.jpg"));
+        assert!(matches_wildcard("*", ""));
+        assert!(!matches_wildcard("*.part", "movie.mkv"));
+        assert!(!matches_wildcard("IMG_????.jpg", "IMG_01.jpg"));
+    }
+
+    #[test]
+    fn test_write_file_atomically() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let path = test_directory.path().join(".settings");
+        std::fs::write(&path, b"old").expect("Failed to write test file");
+        write_file_atomically(&path, b"new").expect("Failed to write file");
+        assert_eq!(std::fs::read(&path).expect("Failed to read file"), b"new");
+        let file_names: Vec<OsString> = std::fs::read_dir(test_directory.path())
+            .expect("Failed to read test directory")
+            .map(|entry| entry.expect("Failed to read entry").file_name())
+            .collect();
+        assert_eq!(file_names, vec![OsString::from(".settings")]);
+
+        // A failed write leaves the old file alone.
+        let missing_directory_path = test_directory.path().join("missing").join(".settings");
+        assert!(write_file_atomically(&missing_directory_path, b"new").is_err());
+        assert_eq!(std::fs::read(&path).expect("Failed to read file"), b"new");
     }
 }
@@ -0,0 +1,431 @@
+use crate::locale::tr;
+use filerganizer_core::file::File;
+use filerganizer_core::trash::{self, Trash};
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsString;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+pub const QUARANTINE_DIRECTORY_NAME: &str = ".filerganizer_quarantine";
+const FAST_HASH_SAMPLE_SIZE: u64 = 64 * 1024;
+
+// Size, content hash and the paths that share them.
+pub type HashGroup = (u64, String, Vec<PathBuf>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateAction {
+    Skip,
+    Delete,
+    HardLink,
+    Quarantine,
+}
+
+impl std::fmt::Display for DuplicateAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DuplicateAction::Skip => tr("Skip"),
+            DuplicateAction::Delete => tr("Move to trash"),
+            DuplicateAction::HardLink => tr("Hard link"),
+            DuplicateAction::Quarantine => tr("Quarantine"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    size: u64,
+    hash: String,
+    original: PathBuf,
+    duplicates: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn get_hash(&self) -> &str {
+        self.hash.as_str()
+    }
+
+    pub fn get_original(&self) -> &PathBuf {
+        &self.original
+    }
+
+    pub fn get_duplicates(&self) -> &Vec<PathBuf> {
+        &self.duplicates
+    }
+
+    pub fn remove_duplicates(&mut self, paths: &[PathBuf]) {
+        self.duplicates.retain(|duplicate| !paths.contains(duplicate));
+    }
+}
+
+// An action that stopped partway. The files processed before the error are
+// kept so they can be taken out of the selection.
+#[derive(Debug)]
+pub struct PartialFailure {
+    pub processed: Vec<PathBuf>,
+    pub error: std::io::Error,
+}
+
+impl std::fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+pub fn fast_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = Vec::new();
+    (&mut file).take(FAST_HASH_SAMPLE_SIZE).read_to_end(&mut buffer)?;
+    hasher.update(&buffer);
+    if size > FAST_HASH_SAMPLE_SIZE * 2 {
+        buffer.clear();
+        file.seek(SeekFrom::End(-(FAST_HASH_SAMPLE_SIZE as i64)))?;
+        file.read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+pub fn full_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+// Groups paths by size first, then by a hash of the first and last blocks
+// and only then by a hash of the whole content, so most files are never read fully.
+// Files that cannot be read are skipped and returned instead of failing the
+// whole scan.
+pub fn find_duplicates(paths: &[PathBuf]) -> (Vec<HashGroup>, Vec<PathBuf>) {
+    let mut skipped = Vec::new();
+    let mut by_size: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+    for path in paths {
+        match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => {
+                by_size
+                    .entry(metadata.len())
+                    .or_default()
+                    .push(PathBuf::from(path));
+            }
+            Ok(_) => {}
+            Err(_) => skipped.push(PathBuf::from(path)),
+        }
+    }
+    let mut groups = Vec::new();
+    for (size, paths) in by_size {
+        if paths.len() < 2 {
+            continue;
+        }
+        for candidates in group_by(paths, fast_hash, &mut skipped).into_values() {
+            if candidates.len() < 2 {
+                continue;
+            }
+            for (hash, duplicates) in group_by(candidates, full_hash, &mut skipped) {
+                if duplicates.len() > 1 {
+                    groups.push((size, hash, duplicates));
+                }
+            }
+        }
+    }
+    (groups, skipped)
+}
+
+fn group_by(
+    paths: Vec<PathBuf>,
+    hash_function: fn(&Path) -> std::io::Result<String>,
+    skipped: &mut Vec<PathBuf>,
+) -> BTreeMap<String, Vec<PathBuf>> {
+    let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for path in paths {
+        match hash_function(&path) {
+            Ok(hash) => groups.entry(hash).or_default().push(path),
+            Err(_) => skipped.push(path),
+        }
+    }
+    groups
+}
+
+pub fn get_origin_paths(files_selected: &BTreeMap<OsString, File>) -> Vec<PathBuf> {
+    files_selected
+        .values()
+        .filter_map(|file| file.get_metadata().as_ref())
+        .filter_map(|metadata| metadata.get_origin_path())
+        .collect()
+}
+
+// Directories and entries that cannot be read are added to skipped and the
+// walk goes on with the rest.
+pub fn get_file_paths_recursive(path: &Path, skipped: &mut Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => {
+            skipped.push(PathBuf::from(path));
+            return paths;
+        }
+    };
+    for entry in entries {
+        let Ok(entry) = entry else {
+            skipped.push(PathBuf::from(path));
+            continue;
+        };
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => {
+                paths.extend(get_file_paths_recursive(&entry.path(), skipped));
+            }
+            Ok(file_type) if file_type.is_file() => paths.push(entry.path()),
+            Ok(_) => {}
+            Err(_) => skipped.push(entry.path()),
+        }
+    }
+    paths
+}
+
+// Finds duplicates among the selected files and between the selected files and
+// the target organized directory. Copies already in the target directory are
+// always kept, so only selected files are ever marked as duplicates. Returns
+// the groups along with the files and directories that could not be read.
+pub fn find_duplicate_groups(
+    files_selected: &BTreeMap<OsString, File>,
+    target_directory: Option<&PathBuf>,
+) -> (Vec<DuplicateGroup>, Vec<PathBuf>) {
+    let selected_paths = get_origin_paths(files_selected);
+    let mut skipped = Vec::new();
+    let mut target_paths = Vec::new();
+    if let Some(target_directory) = target_directory {
+        target_paths = get_file_paths_recursive(target_directory, &mut skipped);
+    }
+    let mut all_paths = selected_paths.clone();
+    all_paths.extend(target_paths.iter().cloned());
+
+    let (duplicates_found, unreadable_files) = find_duplicates(&all_paths);
+    skipped.extend(unreadable_files);
+    let selected_paths: HashSet<&Path> = selected_paths.iter().map(PathBuf::as_path).collect();
+    let target_paths: HashSet<&Path> = target_paths.iter().map(PathBuf::as_path).collect();
+    let mut duplicate_groups = Vec::new();
+    for (size, hash, mut paths) in duplicates_found {
+        paths.sort();
+        let original = match paths
+            .iter()
+            .find(|path| target_paths.contains(path.as_path()))
+        {
+            Some(in_target) => PathBuf::from(in_target),
+            None => PathBuf::from(&paths[0]),
+        };
+        let duplicates: Vec<PathBuf> = paths
+            .into_iter()
+            .filter(|path| *path != original && selected_paths.contains(path.as_path()))
+            .collect();
+        if !duplicates.is_empty() {
+            duplicate_groups.push(DuplicateGroup {
+                size,
+                hash,
+                original,
+                duplicates,
+            });
+        }
+    }
+    (duplicate_groups, skipped)
+}
+
+// Applies the action to every duplicate in the group and returns the
+// duplicates that were processed. Deleted duplicates go to the trash so they
+// can still be restored.
+pub fn apply_duplicate_action(
+    group: &DuplicateGroup,
+    action: DuplicateAction,
+    quarantine_directory: &Path,
+    trash: &Trash,
+) -> Result<Vec<PathBuf>, PartialFailure> {
+    let mut processed = Vec::new();
+    for duplicate in &group.duplicates {
+        let result = match action {
+            DuplicateAction::Skip => Ok(()),
+            DuplicateAction::Delete => trash.trash(duplicate).map(|_| ()),
+            DuplicateAction::HardLink => replace_with_hard_link(&group.original, duplicate),
+            DuplicateAction::Quarantine => {
+                move_to_quarantine(duplicate, quarantine_directory).map(|_| ())
+            }
+        };
+        if let Err(error) = result {
+            return Err(PartialFailure { processed, error });
+        }
+        processed.push(PathBuf::from(duplicate));
+    }
+    Ok(processed)
+}
+
+fn replace_with_hard_link(original: &Path, duplicate: &Path) -> std::io::Result<()> {
+    let mut temporary_path = PathBuf::from(duplicate);
+    let mut temporary_name = OsString::from(".");
+    if let Some(file_name) = duplicate.file_name() {
+        temporary_name.push(file_name);
+    }
+    temporary_name.push(".filerganizer_link");
+    temporary_path.set_file_name(temporary_name);
+    std::fs::hard_link(original, &temporary_path)?;
+    if let Err(error) = std::fs::rename(&temporary_path, duplicate) {
+        std::fs::remove_file(&temporary_path)?;
+        return Err(error);
+    }
+    Ok(())
+}
+
+pub fn move_to_quarantine(path: &Path, quarantine_directory: &Path) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(quarantine_directory)?;
+    let file_name = match path.file_name() {
+        Some(file_name) => file_name,
+        None => {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Path has no file name.",
+            ))
+        }
+    };
+    let mut destination_path = PathBuf::from(quarantine_directory);
+    destination_path.push(file_name);
+    let mut counter = 1;
+    while destination_path.exists() {
+        let mut numbered_name = OsString::from(counter.to_string());
+        numbered_name.push("_");
+        numbered_name.push(file_name);
+        destination_path.set_file_name(numbered_name);
+        counter += 1;
+    }
+    trash::transfer(path, &destination_path)?;
+    Ok(destination_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filerganizer_core::metadata::Metadata;
+
+    fn write_test_file(directory: &Path, name: &str, content: &[u8]) -> PathBuf {
+        let mut path = PathBuf::from(directory);
+        path.push(name);
+        std::fs::write(&path, content).expect("Failed to write test file");
+        path
+    }
+
+    fn create_files_selected(paths: &[&PathBuf]) -> BTreeMap<OsString, File> {
+        let mut files_selected = BTreeMap::new();
+        for path in paths {
+            let name = path.file_name().expect("No file name").to_owned();
+            files_selected.insert(
+                name.clone(),
+                File::new(Metadata::build(
+                    Some(name),
+                    None,
+                    None,
+                    None,
+                    None,
+                    false,
+                    Some(PathBuf::from(path)),
+                    None,
+                )),
+            );
+        }
+        files_selected
+    }
+
+    #[test]
+    fn test_find_duplicate_groups() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let directory = test_directory.path();
+        let target = directory.join("target");
+        std::fs::create_dir_all(&target).expect("Failed to create target");
+        let a = write_test_file(directory, "a.jpg", b"same content");
+        let b = write_test_file(directory, "b.jpg", b"same content");
+        let c = write_test_file(directory, "c.jpg", b"same length!");
+        let d = write_test_file(directory, "d.jpg", b"in target");
+        let in_target = write_test_file(&target, "d.jpg", b"in target");
+        let missing = directory.join("missing.jpg");
+        let files_selected = create_files_selected(&[&a, &b, &c, &d, &missing]);
+
+        let (groups, skipped) = find_duplicate_groups(&files_selected, Some(&target));
+        assert_eq!(skipped, vec![missing.clone()]);
+        assert_eq!(groups.len(), 2);
+        let selection_group = groups
+            .iter()
+            .find(|group| group.get_original() == &a)
+            .expect("Selection duplicates not found");
+        assert_eq!(selection_group.get_duplicates(), &vec![b.clone()]);
+        let target_group = groups
+            .iter()
+            .find(|group| group.get_original() == &in_target)
+            .expect("Target duplicates not found");
+        assert_eq!(target_group.get_duplicates(), &vec![d.clone()]);
+
+        // A target that cannot be read is skipped and the selection still scanned.
+        let missing_target = directory.join("missing_target");
+        let (groups, skipped) = find_duplicate_groups(&files_selected, Some(&missing_target));
+        assert_eq!(skipped, vec![missing_target, missing]);
+        assert_eq!(groups.len(), 1);
+    }
+
+    #[test]
+    fn test_apply_duplicate_action() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let directory = test_directory.path();
+        let quarantine = directory.join("quarantine");
+        let trash = Trash::new(vec![directory.join("trash")]);
+        let a = write_test_file(directory, "a.txt", b"content");
+        let b = write_test_file(directory, "b.txt", b"content");
+        let files_selected = create_files_selected(&[&a, &b]);
+        let (groups, _) = find_duplicate_groups(&files_selected, None);
+
+        let processed =
+            apply_duplicate_action(&groups[0], DuplicateAction::HardLink, &quarantine, &trash)
+                .expect("Hard link failed");
+        assert_eq!(processed, vec![b.clone()]);
+        assert_eq!(std::fs::read(&b).expect("Link missing"), b"content");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let original_inode = std::fs::metadata(&a).expect("Original missing").ino();
+            let link_inode = std::fs::metadata(&b).expect("Link missing").ino();
+            assert_eq!(original_inode, link_inode);
+        }
+
+        let processed =
+            apply_duplicate_action(&groups[0], DuplicateAction::Quarantine, &quarantine, &trash)
+                .expect("Quarantine failed");
+        assert_eq!(processed, vec![b.clone()]);
+        assert!(!b.exists());
+        assert!(quarantine.join("b.txt").exists());
+
+        let c = write_test_file(directory, "c.txt", b"content");
+        let files_selected = create_files_selected(&[&a, &c]);
+        let (groups, _) = find_duplicate_groups(&files_selected, None);
+        let processed =
+            apply_duplicate_action(&groups[0], DuplicateAction::Delete, &quarantine, &trash)
+                .expect("Moving to trash failed");
+        assert_eq!(processed, vec![c.clone()]);
+
+        assert!(!c.exists());
+        let trashed_items = trash.list().expect("Failed to list trash");
+        assert_eq!(trashed_items.len(), 1);
+        assert_eq!(trashed_items[0].get_original_path(), &c);
+
+        // Files already moved are reported when a later file fails.
+        let d = write_test_file(directory, "d.txt", b"content");
+        let e = write_test_file(directory, "e.txt", b"content");
+        let files_selected = create_files_selected(&[&a, &d, &e]);
+        let (mut groups, _) = find_duplicate_groups(&files_selected, None);
+        let missing = PathBuf::from(&groups[0].get_duplicates()[1]);
+        std::fs::remove_file(&missing).expect("Failed to remove test file");
+        let failure =
+            apply_duplicate_action(&groups[0], DuplicateAction::Quarantine, &quarantine, &trash)
+                .expect_err("Quarantine of a missing file succeeded");
+        assert_eq!(failure.processed.len(), 1);
+        groups[0].remove_duplicates(&failure.processed);
+        assert_eq!(groups[0].get_duplicates(), &vec![missing]);
+    }
+}
@@ -0,0 +1,318 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Display;
+
+use crate::app_util;
+use crate::locale::tr;
+use filerganizer_core::file::File;
+use filerganizer_core::organize_files;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortColumn {
+    #[default]
+    Name,
+    Size,
+    Created,
+    Modified,
+    Type,
+}
+
+pub const SORT_COLUMNS: [SortColumn; 5] = [
+    SortColumn::Name,
+    SortColumn::Size,
+    SortColumn::Created,
+    SortColumn::Modified,
+    SortColumn::Type,
+];
+
+impl Display for SortColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortColumn::Name => f.write_str(tr("Name")),
+            SortColumn::Size => f.write_str(tr("Size")),
+            SortColumn::Created => f.write_str(tr("Created")),
+            SortColumn::Modified => f.write_str(tr("Modified")),
+            SortColumn::Type => f.write_str(tr("Type")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FileSort {
+    column: SortColumn,
+    order: SortOrder,
+}
+
+impl FileSort {
+    pub fn new(column: SortColumn, order: SortOrder) -> Self {
+        Self { column, order }
+    }
+
+    pub fn get_column(&self) -> SortColumn {
+        self.column
+    }
+
+    pub fn get_order(&self) -> SortOrder {
+        self.order
+    }
+
+    // Clicking the sorted column again reverses the order, another column
+    // starts out ascending.
+    pub fn select_column(self, column: SortColumn) -> Self {
+        if self.column != column {
+            return Self::new(column, SortOrder::Ascending);
+        }
+        match self.order {
+            SortOrder::Ascending => Self::new(column, SortOrder::Descending),
+            SortOrder::Descending => Self::new(column, SortOrder::Ascending),
+        }
+    }
+
+    fn compare(
+        &self,
+        (a_name, a_file): (&str, &File),
+        (b_name, b_file): (&str, &File),
+    ) -> Ordering {
+        let a_metadata = a_file.get_metadata().as_ref();
+        let b_metadata = b_file.get_metadata().as_ref();
+        let order = |ordering: Ordering| match self.order {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        };
+        // Files without the value are sorted last in both orders
+        match self.column {
+            SortColumn::Name => Ordering::Equal,
+            SortColumn::Size => {
+                let a_size = a_metadata.and_then(|metadata| metadata.get_size());
+                let b_size = b_metadata.and_then(|metadata| metadata.get_size());
+                compare_options(a_size, b_size, |a, b| order(a.total_cmp(b)))
+            }
+            SortColumn::Created => {
+                let a_created = a_metadata.and_then(|metadata| metadata.get_created());
+                let b_created = b_metadata.and_then(|metadata| metadata.get_created());
+                compare_options(a_created, b_created, |a, b| order(a.cmp(b)))
+            }
+            SortColumn::Modified => {
+                let a_modified = a_metadata.and_then(|metadata| metadata.get_modified());
+                let b_modified = b_metadata.and_then(|metadata| metadata.get_modified());
+                compare_options(a_modified, b_modified, |a, b| order(a.cmp(b)))
+            }
+            SortColumn::Type => {
+                let a_type = organize_files::get_file_type_from_file_name(a_name);
+                let b_type = organize_files::get_file_type_from_file_name(b_name);
+                compare_options(a_type, b_type, |a, b| order(natural_cmp(a, b)))
+            }
+        }
+        .then_with(|| order(natural_cmp(a_name, b_name)))
+    }
+}
+
+fn compare_options<T>(
+    a: Option<T>,
+    b: Option<T>,
+    compare: impl Fn(&T, &T) -> Ordering,
+) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => compare(&a, &b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+// Compares runs of digits by their value, so img2 comes before img10.
+// Letters are compared without case.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let a_number = take_digits(&mut a_chars);
+                let b_number = take_digits(&mut b_chars);
+                let a_digits = a_number.trim_start_matches('0');
+                let b_digits = b_number.trim_start_matches('0');
+                let ordering = a_digits
+                    .len()
+                    .cmp(&b_digits.len())
+                    .then_with(|| a_digits.cmp(b_digits));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(a_char), Some(b_char)) => {
+                let ordering = a_char.to_lowercase().cmp(b_char.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(character) = chars.next_if(|character| character.is_ascii_digit()) {
+        digits.push(character);
+    }
+    digits
+}
+
+// Filter typed above the file list. Patterns with * or ? are globs, a
+// leading dot matches the end of the name like an extension and anything
+// else is a substring. Matching ignores case.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum FileFilter {
+    #[default]
+    All,
+    Substring(String),
+    Glob(String),
+    Extension(String),
+}
+
+impl FileFilter {
+    pub fn parse(input: &str) -> Self {
+        let input = input.trim().to_lowercase();
+        if input.is_empty() {
+            FileFilter::All
+        } else if input.contains(['*', '?']) {
+            FileFilter::Glob(input)
+        } else if input.starts_with('.') {
+            FileFilter::Extension(input)
+        } else {
+            FileFilter::Substring(input)
+        }
+    }
+
+    pub fn matches(&self, file_name: &str) -> bool {
+        let file_name = file_name.to_lowercase();
+        match self {
+            FileFilter::All => true,
+            FileFilter::Substring(substring) => file_name.contains(substring.as_str()),
+            FileFilter::Glob(pattern) => app_util::matches_wildcard(pattern, &file_name),
+            FileFilter::Extension(extension) => file_name.ends_with(extension.as_str()),
+        }
+    }
+}
+
+// Names of the files in the order they are shown. Multiple selection uses
+// the same order, so a range covers what is between the clicked files.
+pub fn visible_file_names<'a>(
+    files: &'a BTreeMap<OsString, File>,
+    sort: FileSort,
+    filter: &FileFilter,
+) -> Vec<&'a OsString> {
+    let mut visible: Vec<(&OsString, &File)> = files
+        .iter()
+        .filter(|(file_name, _)| filter.matches(&file_name.to_string_lossy()))
+        .collect();
+    visible.sort_by(|(a_name, a_file), (b_name, b_file)| {
+        sort.compare(
+            (&a_name.to_string_lossy(), a_file),
+            (&b_name.to_string_lossy(), b_file),
+        )
+    });
+    visible
+        .into_iter()
+        .map(|(file_name, _)| file_name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filerganizer_core::metadata::Metadata;
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec![
+            "img10.jpg",
+            "IMG1.jpg",
+            "img2.jpg",
+            "img02b.jpg",
+            "a",
+            "img",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec![
+                "a",
+                "img",
+                "IMG1.jpg",
+                "img2.jpg",
+                "img02b.jpg",
+                "img10.jpg"
+            ]
+        );
+        assert_eq!(natural_cmp("file007", "file7"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_file_filter() {
+        assert!(FileFilter::parse("").matches("photo.jpg"));
+        assert!(FileFilter::parse("OTO").matches("photo.jpg"));
+        assert!(FileFilter::parse("img_*.JPG").matches("IMG_0001.jpg"));
+        assert!(!FileFilter::parse("img_*.jpg").matches("photo.jpg"));
+        assert!(FileFilter::parse(".jpg").matches("photo.JPG"));
+        assert!(!FileFilter::parse(".jpg").matches("photo.jpg.part"));
+    }
+
+    #[test]
+    fn test_visible_file_names() {
+        let mut files = BTreeMap::new();
+        for (file_name, size) in [
+            ("img10.jpg", Some(10.0)),
+            ("img2.jpg", Some(30.0)),
+            ("notes.txt", Some(20.0)),
+            ("unknown.bin", None),
+        ] {
+            let metadata = Metadata::build(
+                Some(OsString::from(file_name)),
+                None,
+                None,
+                None,
+                size,
+                false,
+                None,
+                None,
+            );
+            files.insert(OsString::from(file_name), File::new(metadata));
+        }
+        let names = |sort: FileSort, filter: &str| -> Vec<String> {
+            visible_file_names(&files, sort, &FileFilter::parse(filter))
+                .into_iter()
+                .map(|file_name| file_name.to_string_lossy().to_string())
+                .collect()
+        };
+        assert_eq!(
+            names(FileSort::default(), ""),
+            vec!["img2.jpg", "img10.jpg", "notes.txt", "unknown.bin"]
+        );
+        let by_size = FileSort::default().select_column(SortColumn::Size);
+        assert_eq!(
+            names(by_size, ""),
+            vec!["img10.jpg", "notes.txt", "img2.jpg", "unknown.bin"]
+        );
+        let by_size = by_size.select_column(SortColumn::Size);
+        assert_eq!(by_size.get_order(), SortOrder::Descending);
+        assert_eq!(
+            names(by_size, ""),
+            vec!["img2.jpg", "notes.txt", "img10.jpg", "unknown.bin"]
+        );
+        assert_eq!(names(by_size, ".jpg"), vec!["img2.jpg", "img10.jpg"]);
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use iced::keyboard::{Key, Modifiers};
+
+use crate::locale::tr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyAction {
+    FocusUp,
+    FocusDown,
+    OpenDirectory,
+    ParentDirectory,
+    ToggleSelection,
+    ExtendSelectionUp,
+    ExtendSelectionDown,
+    SelectAll,
+    UndoCommit,
+    Commit,
+    CompletePath,
+}
+
+pub const KEY_ACTIONS: [KeyAction; 11] = [
+    KeyAction::FocusUp,
+    KeyAction::FocusDown,
+    KeyAction::OpenDirectory,
+    KeyAction::ParentDirectory,
+    KeyAction::ToggleSelection,
+    KeyAction::ExtendSelectionUp,
+    KeyAction::ExtendSelectionDown,
+    KeyAction::SelectAll,
+    KeyAction::UndoCommit,
+    KeyAction::Commit,
+    KeyAction::CompletePath,
+];
+
+impl KeyAction {
+    // Name of the action in the settings file.
+    pub fn get_setting_name(&self) -> &'static str {
+        match self {
+            KeyAction::FocusUp => "focus_up",
+            KeyAction::FocusDown => "focus_down",
+            KeyAction::OpenDirectory => "open_directory",
+            KeyAction::ParentDirectory => "parent_directory",
+            KeyAction::ToggleSelection => "toggle_selection",
+            KeyAction::ExtendSelectionUp => "extend_selection_up",
+            KeyAction::ExtendSelectionDown => "extend_selection_down",
+            KeyAction::SelectAll => "select_all",
+            KeyAction::UndoCommit => "undo_commit",
+            KeyAction::Commit => "commit",
+            KeyAction::CompletePath => "complete_path",
+        }
+    }
+
+    pub fn parse(setting_name: &str) -> Option<Self> {
+        KEY_ACTIONS
+            .into_iter()
+            .find(|action| action.get_setting_name() == setting_name)
+    }
+
+    fn get_default_binding(&self) -> KeyBinding {
+        let (key, control, shift) = match self {
+            KeyAction::FocusUp => ("ArrowUp", false, false),
+            KeyAction::FocusDown => ("ArrowDown", false, false),
+            KeyAction::OpenDirectory => ("Enter", false, false),
+            KeyAction::ParentDirectory => ("Backspace", false, false),
+            KeyAction::ToggleSelection => ("Space", false, false),
+            KeyAction::ExtendSelectionUp => ("ArrowUp", false, true),
+            KeyAction::ExtendSelectionDown => ("ArrowDown", false, true),
+            KeyAction::SelectAll => ("a", true, false),
+            KeyAction::UndoCommit => ("z", true, false),
+            KeyAction::Commit => ("Enter", true, false),
+            KeyAction::CompletePath => ("Tab", false, false),
+        };
+        KeyBinding {
+            key: String::from(key),
+            control,
+            shift,
+            alt: false,
+        }
+    }
+}
+
+impl Display for KeyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyAction::FocusUp => f.write_str(tr("Move focus up")),
+            KeyAction::FocusDown => f.write_str(tr("Move focus down")),
+            KeyAction::OpenDirectory => f.write_str(tr("Open directory")),
+            KeyAction::ParentDirectory => f.write_str(tr("Go to parent directory")),
+            KeyAction::ToggleSelection => f.write_str(tr("Select or unselect file")),
+            KeyAction::ExtendSelectionUp => f.write_str(tr("Extend selection up")),
+            KeyAction::ExtendSelectionDown => f.write_str(tr("Extend selection down")),
+            KeyAction::SelectAll => f.write_str(tr("Select all files")),
+            KeyAction::UndoCommit => f.write_str(tr("Undo last commit")),
+            KeyAction::Commit => f.write_str(tr("Commit")),
+            KeyAction::CompletePath => f.write_str(tr("Complete path")),
+        }
+    }
+}
+
+// Key with the modifiers that have to be held, written like Ctrl+Shift+a.
+// Named keys use the iced names, like ArrowUp, Enter or Space. Ctrl is the
+// Command key on macOS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    key: String,
+    control: bool,
+    shift: bool,
+    alt: bool,
+}
+
+impl KeyBinding {
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut binding = KeyBinding {
+            key: String::new(),
+            control: false,
+            shift: false,
+            alt: false,
+        };
+        let parts: Vec<&str> = input.trim().split('+').map(str::trim).collect();
+        let (key, modifiers) = parts.split_last()?;
+        for modifier in modifiers {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" | "command" => binding.control = true,
+                "shift" => binding.shift = true,
+                "alt" | "option" => binding.alt = true,
+                _ => return None,
+            }
+        }
+        if key.is_empty() {
+            return None;
+        }
+        binding.key = match key.chars().count() {
+            1 => key.to_lowercase(),
+            _ => String::from(*key),
+        };
+        Some(binding)
+    }
+
+    pub fn matches(&self, key: &Key, modifiers: Modifiers) -> bool {
+        if self.control != modifiers.command()
+            || self.shift != modifiers.shift()
+            || self.alt != modifiers.alt()
+        {
+            return false;
+        }
+        match key.as_ref() {
+            Key::Named(named) => format!("{:?}", named) == self.key,
+            Key::Character(character) => character.to_lowercase() == self.key,
+            Key::Unidentified => false,
+        }
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.control {
+            write!(f, "Ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: BTreeMap<KeyAction, KeyBinding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = KEY_ACTIONS
+            .into_iter()
+            .map(|action| (action, action.get_default_binding()))
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    pub fn get_binding(&self, action: KeyAction) -> Option<&KeyBinding> {
+        self.bindings.get(&action)
+    }
+
+    pub fn set_binding(&mut self, action: KeyAction, binding: KeyBinding) {
+        self.bindings.insert(action, binding);
+    }
+
+    // Two actions bound to the same key, of which only the first could ever
+    // be triggered.
+    pub fn find_conflict(&self) -> Option<(KeyAction, KeyAction, &KeyBinding)> {
+        self.bindings.iter().enumerate().find_map(|(i, (action, binding))| {
+            self.bindings
+                .iter()
+                .skip(i + 1)
+                .find(|(_, other_binding)| *other_binding == binding)
+                .map(|(other_action, _)| (*action, *other_action, binding))
+        })
+    }
+
+    pub fn get_action(&self, key: &Key, modifiers: Modifiers) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .find(|(_, binding)| binding.matches(key, modifiers))
+            .map(|(action, _)| *action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::keyboard::key::Named;
+
+    #[test]
+    fn test_parse_key_binding() {
+        let binding = KeyBinding::parse("Ctrl+Shift+A").expect("Failed to parse");
+        assert_eq!(binding.to_string(), "Ctrl+Shift+a");
+        assert!(binding.matches(
+            &Key::Character("A".into()),
+            Modifiers::COMMAND | Modifiers::SHIFT
+        ));
+        assert!(!binding.matches(&Key::Character("a".into()), Modifiers::COMMAND));
+        assert_eq!(
+            KeyBinding::parse("ArrowDown").unwrap().to_string(),
+            "ArrowDown"
+        );
+        assert_eq!(KeyBinding::parse("Hyper+x"), None);
+        assert_eq!(KeyBinding::parse("Ctrl+"), None);
+    }
+
+    #[test]
+    fn test_default_keymap() {
+        let mut keymap = Keymap::default();
+        let enter = Key::Named(Named::Enter);
+        assert_eq!(
+            keymap.get_action(&enter, Modifiers::empty()),
+            Some(KeyAction::OpenDirectory)
+        );
+        assert_eq!(
+            keymap.get_action(&enter, Modifiers::COMMAND),
+            Some(KeyAction::Commit)
+        );
+        assert_eq!(
+            keymap.get_action(&Key::Named(Named::ArrowDown), Modifiers::SHIFT),
+            Some(KeyAction::ExtendSelectionDown)
+        );
+        keymap.set_binding(KeyAction::Commit, KeyBinding::parse("F5").unwrap());
+        assert_eq!(keymap.get_action(&enter, Modifiers::COMMAND), None);
+        assert_eq!(
+            keymap.get_action(&Key::Named(Named::F5), Modifiers::empty()),
+            Some(KeyAction::Commit)
+        );
+        assert_eq!(keymap.find_conflict(), None);
+        keymap.set_binding(KeyAction::SelectAll, KeyBinding::parse("F5").unwrap());
+        let (first, second, binding) = keymap.find_conflict().expect("Conflict not found");
+        assert_eq!(
+            (binding.to_string(), [first, second].contains(&KeyAction::Commit)),
+            (String::from("F5"), true)
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeMap, BTreeSet},
     ffi::{OsStr, OsString},
     path::{Iter, PathBuf},
 };
@@ -7,129 +7,81 @@ use std::{
 use iced::{
     alignment::Vertical,
     widget::{
-        button, checkbox, column, container, mouse_area, pick_list, radio, row, scrollable, text,
-        text_input, Column, Container, Row,
+        button, checkbox, column, container, mouse_area, pick_list, progress_bar, radio, row,
+        scrollable, slider, text, text_input, Button, Column, Container, MouseArea, Row,
     },
     Alignment::Center,
-    Background, Color,
+    Background, Element, Font,
     Length::{Fill, FillPortion},
+    theme::palette,
     Theme,
 };
 
 use chrono::{DateTime, Local};
 
 use crate::{
-    app::{App, FilenameComponents, Message, ReplacableSelection, SelectedDirectoryRules},
+    app::{App, DirectoryOperation, Message},
+    duplicates::DuplicateAction,
+    file_list::{self, SortOrder, SORT_COLUMNS},
+    keymap::KEY_ACTIONS,
+    locale::{self, tr, tr_format},
+    settings,
+    preview::{FileInspection, PreviewContent},
+    similar_images::{self, PerceptualHashType},
+    thumbnails::{self, ThumbnailState},
+};
+use filerganizer_core::{
     directory::Directory,
     metadata::{DateType, Metadata},
-    organize_files,
+    filesystem::TRANSFER_MODES,
+    organize_files::{self, RenamePreview},
+    rules::{
+        CheckboxStates, FilenameComponents, IndexPosition, ReplacableSelection, ReplaceWith,
+        Replaceable, Rules,
+    },
+    sanitize::{self, SanitizeAction, SanitizedName},
+    Plan,
 };
 
-#[derive(Debug, Clone, PartialEq, Copy, Eq)]
-pub enum Replaceable {
-    Dash,
-    Space,
-    Comma,
-}
-
 const MAX_REPLACEABLE_OPTIONS: usize = 3;
-
-impl std::fmt::Display for Replaceable {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            Replaceable::Dash => "Dash",
-            Replaceable::Space => "Space",
-            Replaceable::Comma => "Comma",
-        })
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Copy, Eq)]
-pub enum ReplaceWith {
-    Underscore,
-    Nothing,
-}
-
-impl std::fmt::Display for ReplaceWith {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            ReplaceWith::Nothing => "Nothing",
-            ReplaceWith::Underscore => "Underscore",
-        })
-    }
-}
+const GRID_COLUMNS: usize = 5;
+const THUMBNAIL_TILE_WIDTH: f32 = 150.0;
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct CheckboxStates {
-    pub organize_by_filetype: bool,
-    pub organize_by_date: bool,
-    pub insert_date_to_file_name: bool,
-    pub insert_directory_name_to_file_name: bool,
-    pub convert_uppercase_to_lowercase: bool,
-    pub replace_character: bool,
-    pub use_only_ascii: bool,
-    pub remove_original_file_name: bool,
-    pub add_custom_name: bool,
+pub enum DirectoryView {
+    List,
+    DropDown,
+    Grid,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum IndexPosition {
-    Before,
-    After,
-}
+pub const DIRECTORY_VIEWS: [DirectoryView; 3] =
+    [DirectoryView::List, DirectoryView::DropDown, DirectoryView::Grid];
 
-impl Default for CheckboxStates {
-    fn default() -> Self {
-        Self {
-            organize_by_filetype: false,
-            organize_by_date: false,
-            insert_date_to_file_name: false,
-            insert_directory_name_to_file_name: false,
-            convert_uppercase_to_lowercase: false,
-            replace_character: false,
-            use_only_ascii: false,
-            remove_original_file_name: false,
-            add_custom_name: false,
-        }
+impl std::fmt::Display for DirectoryView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(tr(self.get_setting_name()))
     }
 }
 
-impl CheckboxStates {
-    pub fn new(
-        organize_by_filetype: bool,
-        organize_by_date: bool,
-        insert_date_to_file_name: bool,
-        insert_directory_name_to_file_name: bool,
-        convert_uppercase_to_lowercase: bool,
-        replace_character: bool,
-        use_only_ascii: bool,
-        remove_original_file_name: bool,
-        add_custom_name: bool,
-    ) -> Self {
-        Self {
-            organize_by_filetype,
-            organize_by_date,
-            insert_date_to_file_name,
-            insert_directory_name_to_file_name,
-            convert_uppercase_to_lowercase,
-            replace_character,
-            use_only_ascii,
-            remove_original_file_name,
-            add_custom_name,
+impl DirectoryView {
+    // The setting name stays the same whatever language is selected.
+    pub fn get_setting_name(&self) -> &'static str {
+        match self {
+            DirectoryView::List => "List",
+            DirectoryView::DropDown => "Drop down",
+            DirectoryView::Grid => "Grid",
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum DirectoryView {
-    List,
-    DropDown,
-}
-
 #[derive(Debug, Clone)]
 pub enum Layout {
     Main,
     DirectorySelectionLayout,
+    WatchFolders,
+    Trash,
+    KeyboardShortcuts,
+    Settings,
 }
 
 #[derive(Debug, Clone)]
@@ -142,33 +94,61 @@ impl Layout {
     pub fn get_layout<'a>(&'a self, app: &'a App) -> Container<'a, Message> {
         match self {
             Layout::Main => self.main_layout(app),
+            Layout::WatchFolders => self.watch_folders_layout(app),
+            Layout::Trash => self.trash_layout(app),
+            Layout::KeyboardShortcuts => self.keyboard_shortcuts_layout(app),
+            Layout::Settings => self.settings_layout(app),
             _ => self.directory_tree_layout(app),
         }
     }
 
-    fn main_layout<'a>(&self, app: &App) -> Container<'a, Message> {
+    fn main_layout<'a>(&'a self, app: &'a App) -> Container<'a, Message> {
         let files_have_been_organized = match app.get_files_have_been_organized() {
-            true => "Your files have been Filerganized",
+            true => tr("Your files have been Filerganized"),
             false => "",
         };
+        let mut undo_row = Row::new().spacing(10).padding(10);
+        if app.is_undoing_commit() {
+            undo_row = undo_row.push(self.insert_commit_progress(app));
+        } else if app.can_undo_commit() {
+            undo_row = undo_row.push(
+                button(tr("Undo last commit"))
+                    .style(directory_button_style)
+                    .on_press(Message::UndoCommit),
+            );
+        }
+        undo_row = undo_row.push(text(app.get_error()));
         container(column![
             row![text("Filerganizer").size(50)].spacing(10).padding(10),
             row![
-                button("Select directory to organize")
+                button(tr("Select directory to organize"))
                     .style(directory_button_style)
                     .on_press(Message::SwitchLayout(Layout::DirectorySelectionLayout)),
-                button("Exit")
+                button(tr("Watch folders"))
+                    .style(directory_button_style)
+                    .on_press(Message::SwitchLayout(Layout::WatchFolders)),
+                button(tr("Trash"))
+                    .style(directory_button_style)
+                    .on_press(Message::SwitchLayout(Layout::Trash)),
+                button(tr("Keyboard shortcuts"))
+                    .style(directory_button_style)
+                    .on_press(Message::SwitchLayout(Layout::KeyboardShortcuts)),
+                button(tr("Settings"))
+                    .style(directory_button_style)
+                    .on_press(Message::SwitchLayout(Layout::Settings)),
+                button(tr("Exit"))
                     .on_press(Message::Exit)
                     .style(directory_button_style)
             ]
             .spacing(10)
             .padding(10),
             row![text(files_have_been_organized)
-                .color(Color::from_rgb(0.0, 0.5, 0.1))
+                .style(text::success)
                 .center()
                 .size(25)]
             .spacing(10)
-            .padding(10)
+            .padding(10),
+            undo_row
         ])
         .padding(10)
         .center(Fill)
@@ -179,12 +159,19 @@ impl Layout {
             let mut main_row = Row::new();
             let mut header_column = Column::new();
             header_column = header_column.push(
-                button("Main Menu")
+                button(tr("Main Menu"))
                     .on_press(Message::SwitchLayout(Layout::Main))
                     .style(directory_button_style),
             );
             let mut header_column_row = Row::new();
+            main_row = main_row.push(scrollable(self.insert_sidebar(app)).width(200));
             main_row = main_row.push(self.display_directory_contents(app).spacing(5));
+            if let Some(file_inspection) = app.get_file_inspection() {
+                main_row = main_row.push(
+                    scrollable(self.insert_file_preview(app, file_inspection).padding(10))
+                        .width(FillPortion(2)),
+                );
+            }
             if !app.get_files_selected().is_empty() {
                 main_row = main_row.push(
                     scrollable(column![self.insert_files_selected(app),].padding(10))
@@ -196,21 +183,54 @@ impl Layout {
             header_column_row = header_column_row
                 .push(self.insert_directory_view_buttons(app))
                 .spacing(5);
-            if !app.get_files_organized().is_empty() {
-                header_column_row =
-                    header_column_row.push(button("Commit").on_press(Message::Commit));
+            if app.is_committing() {
+                header_column_row = header_column_row.push(self.insert_commit_progress(app));
+            } else if !app.get_files_organized().is_empty()
+                || app.get_selected_directory_plan().is_some()
+            {
+                header_column_row = header_column_row.push(
+                    row![
+                        text(tr("Transfer mode")),
+                        pick_list(
+                            TRANSFER_MODES,
+                            Some(app.get_transfer_mode()),
+                            Message::TransferModeSelected,
+                        ),
+                        button(tr("Commit")).on_press(Message::Commit)
+                    ]
+                    .spacing(5)
+                    .align_y(Vertical::Center),
+                );
             }
             header_column = header_column.push(header_column_row);
+            if !app.is_committing() && !app.get_files_organized().is_empty() {
+                header_column = header_column.push(self.insert_cleanup_options(app));
+            }
+            if !app.get_sanitized_names().is_empty() {
+                header_column = header_column.push(self.insert_sanitized_names(app));
+            }
             if let Some(directory_path) = app.get_directory_selected() {
                 main_row = main_row.push(self.selected_directory_option(app, directory_path));
             }
+            if !app.get_similar_image_groups().is_empty() {
+                main_row = main_row.push(
+                    scrollable(self.insert_similar_image_groups(app).padding(10))
+                        .width(FillPortion(3)),
+                );
+            }
+            if !app.get_duplicate_groups().is_empty() {
+                main_row = main_row.push(
+                    scrollable(self.insert_duplicate_groups(app).padding(10))
+                        .width(FillPortion(2)),
+                );
+            }
 
             container(
                 column![
                     header_column,
                     column![
                         self.insert_external_storage(app),
-                        button("Previous")
+                        button(tr("Previous"))
                             .on_press(Message::DropDownDirectory(PathBuf::from(path)))
                             .style(directory_button_style),
                         text(app.get_error()),
@@ -222,8 +242,209 @@ impl Layout {
                 .padding(10),
             )
         } else {
-            container(text("Could not find path"))
+            container(text(tr("Could not find path")))
+        }
+    }
+
+    fn trash_layout<'a>(&'a self, app: &'a App) -> Container<'a, Message> {
+        let mut trashed_items_column = Column::new().spacing(5);
+        for (i, trashed_item) in app.get_trashed_items().iter().enumerate() {
+            let deletion_date = match trashed_item.get_deletion_date() {
+                Some(deletion_date) => locale::format_date_time(&deletion_date),
+                None => String::from("-"),
+            };
+            trashed_items_column = trashed_items_column.push(
+                row![
+                    text(trashed_item.get_original_path().display().to_string())
+                        .width(FillPortion(4)),
+                    text(deletion_date).width(FillPortion(1)),
+                    button(tr("Restore"))
+                        .style(directory_button_style)
+                        .on_press(Message::RestoreTrashedItem(i)),
+                ]
+                .spacing(5)
+                .align_y(Center),
+            );
+        }
+        if app.get_trashed_items().is_empty() {
+            trashed_items_column = trashed_items_column.push(text(tr("Trash is empty.")));
+        }
+
+        container(
+            column![
+                button(tr("Main Menu"))
+                    .on_press(Message::SwitchLayout(Layout::Main))
+                    .style(directory_button_style),
+                text(tr("Trash")).size(25),
+                text(app.get_error()),
+                row![
+                    text(tr("Original path")).width(FillPortion(4)),
+                    text(tr("Deleted")).width(FillPortion(1)),
+                ]
+                .spacing(5),
+                scrollable(trashed_items_column),
+            ]
+            .spacing(10),
+        )
+        .padding(10)
+    }
+
+    fn keyboard_shortcuts_layout<'a>(&'a self, app: &'a App) -> Container<'a, Message> {
+        let mut shortcuts_column = Column::new().spacing(5);
+        for action in KEY_ACTIONS {
+            shortcuts_column = shortcuts_column.push(
+                row![
+                    text(action.to_string()).width(250),
+                    text_input("Ctrl+Shift+a", app.get_keymap_input(action))
+                        .on_input(move |input| Message::KeymapInput(action, input))
+                        .width(200),
+                ]
+                .spacing(5)
+                .align_y(Center),
+            );
+        }
+
+        container(
+            column![
+                button(tr("Main Menu"))
+                    .on_press(Message::SwitchLayout(Layout::Main))
+                    .style(directory_button_style),
+                text(tr("Keyboard shortcuts")).size(25),
+                text(tr("Modifiers are Ctrl, Shift and Alt. Named keys are like ArrowUp or Enter."))
+                    .size(12),
+                text(app.get_error()),
+                scrollable(shortcuts_column),
+                row![
+                    button(tr("Save"))
+                        .style(directory_button_style)
+                        .on_press(Message::SaveKeymap),
+                    button(tr("Reset to defaults"))
+                        .style(directory_button_style)
+                        .on_press(Message::ResetKeymap),
+                ]
+                .spacing(10),
+            ]
+            .spacing(10),
+        )
+        .padding(10)
+    }
+
+    fn settings_layout<'a>(&'a self, app: &'a App) -> Container<'a, Message> {
+        let settings = app.get_settings();
+        container(
+            column![
+                button(tr("Main Menu"))
+                    .on_press(Message::SwitchLayout(Layout::Main))
+                    .style(directory_button_style),
+                text(tr("Settings")).size(25),
+                text(app.get_error()),
+                row![
+                    text(tr("Theme")).width(200),
+                    pick_list(
+                        settings::get_theme_settings(),
+                        Some(settings.get_theme().clone()),
+                        Message::ThemeSelected,
+                    ),
+                ]
+                .spacing(5)
+                .align_y(Center),
+                row![
+                    text(tr("Default directory view")).width(200),
+                    pick_list(
+                        DIRECTORY_VIEWS,
+                        Some(settings.get_directory_view()),
+                        Message::DefaultDirectoryViewSelected,
+                    ),
+                ]
+                .spacing(5)
+                .align_y(Center),
+                row![
+                    text(tr("Language")).width(200),
+                    pick_list(
+                        locale::LANGUAGES,
+                        Some(settings.get_language()),
+                        Message::LanguageSelected,
+                    ),
+                ]
+                .spacing(5)
+                .align_y(Center),
+                text(tr(
+                    "The window size and position and the last opened directory are \
+                     remembered when Filerganizer is closed."
+                ))
+                .size(12),
+            ]
+            .spacing(10),
+        )
+        .padding(10)
+    }
+
+    fn watch_folders_layout<'a>(&'a self, app: &'a App) -> Container<'a, Message> {
+        let mut watch_folders_column = Column::new().spacing(5);
+        for (i, watch_folder) in app.get_watch_folders().iter().enumerate() {
+            let pause_text = if watch_folder.get_paused() {
+                tr("Resume")
+            } else {
+                tr("Pause")
+            };
+            watch_folders_column = watch_folders_column.push(
+                row![
+                    text(format!(
+                        "{} -> {}",
+                        watch_folder.get_inbox().display(),
+                        watch_folder.get_organized_directory().display()
+                    ))
+                    .width(FillPortion(3)),
+                    text(watch_folder.get_ignore_patterns().join(", ")).width(FillPortion(1)),
+                    button(pause_text)
+                        .style(directory_button_style)
+                        .on_press(Message::ToggleWatchFolderPaused(i)),
+                    button(tr("Remove"))
+                        .style(directory_button_style)
+                        .on_press(Message::RemoveWatchFolder(i)),
+                ]
+                .spacing(5)
+                .align_y(Center),
+            );
+        }
+
+        let mut log_column = Column::new().spacing(2);
+        for entry in app.get_watch_log().iter().rev() {
+            log_column = log_column.push(text(entry).size(12));
         }
+
+        container(
+            column![
+                button(tr("Main Menu"))
+                    .on_press(Message::SwitchLayout(Layout::Main))
+                    .style(directory_button_style),
+                text(tr("Watch folders")).size(25),
+                row![
+                    text_input(tr("Inbox directory"), app.get_watch_inbox_input())
+                        .on_input(Message::WatchInboxInput),
+                    text_input(
+                        tr("Organized directory"),
+                        app.get_watch_organized_directory_input()
+                    )
+                    .on_input(Message::WatchOrganizedDirectoryInput),
+                    text_input(
+                        tr("Ignore patterns (*.part, .*)"),
+                        app.get_watch_ignore_input()
+                    )
+                    .on_input(Message::WatchIgnoreInput),
+                    button(tr("Add"))
+                        .style(directory_button_style)
+                        .on_press(Message::AddWatchFolder),
+                ]
+                .spacing(5),
+                text(app.get_error()),
+                watch_folders_column,
+                text(tr("Activity log")).size(15),
+                scrollable(log_column).height(Fill),
+            ]
+            .spacing(10)
+            .padding(10),
+        )
     }
 
     fn insert_replaceables<'a>(&'a self, app: &'a App) -> Column<'a, Message> {
@@ -234,20 +455,20 @@ impl Layout {
         for (i, replaceable_rule) in app.get_replaceables().iter().enumerate() {
             column = column.push(
                 row![
-                    text("Replace"),
+                    text(tr("Replace")),
                     pick_list(
                         app.get_replaceable_options(),
                         replaceable_rule.get_replaceable_selected(),
                         move |replaceable| Message::SelectReplaceable(replaceable, i),
                     )
                     .width(150),
-                    text("With"),
+                    text(tr("With")),
                     pick_list(
                         app.get_replace_with_options(),
                         replaceable_rule.get_replace_with_selected(),
                         move |replace_with| { Message::SelectReplaceWith(replace_with, i) }
                     ),
-                    button("Remove").on_press(Message::RemoveReplaceable(i))
+                    button(tr("Remove")).on_press(Message::RemoveReplaceable(i))
                 ]
                 .spacing(5)
                 .padding(5)
@@ -257,79 +478,81 @@ impl Layout {
         if !app.get_replaceable_options().is_empty()
             && app.get_replaceables().len() < MAX_REPLACEABLE_OPTIONS
         {
-            column = column.push(row![button("Add new").on_press(Message::AddNewReplaceable)]);
+            column = column.push(row![button(tr("Add new")).on_press(Message::AddNewReplaceable)]);
         }
         column
     }
 
     fn rules_for_directory<'a>(&'a self, app: &'a App) -> Column<'a, Message> {
         let created = radio(
-            "Created",
+            tr("Created"),
             DateType::Created,
             app.get_date_type_selected(),
             Message::DateTypeSelected,
         );
         let accessed = radio(
-            "Accessed",
+            tr("Accessed"),
             DateType::Accessed,
             app.get_date_type_selected(),
             Message::DateTypeSelected,
         );
         let modified = radio(
-            "Modified",
+            tr("Modified"),
             DateType::Modified,
             app.get_date_type_selected(),
             Message::DateTypeSelected,
         );
         let replaceables = self.insert_replaceables(app);
         column![
-            text("Rules for directory"),
+            text(tr("Rules for directory")),
             column![
                 checkbox(
-                    "Organize to directories by file type.",
+                    tr("Organize to directories by file type."),
                     app.get_checkbox_states().organize_by_filetype
                 )
                 .on_toggle(|toggle| { Message::CheckboxToggled(toggle, 1) }),
                 checkbox(
-                    "Organize to directories by date.",
+                    tr("Organize to directories by date."),
                     app.get_checkbox_states().organize_by_date
                 )
                 .on_toggle(|toggle| { Message::CheckboxToggled(toggle, 2) }),
-                column![text("Datetype"), created, accessed, modified].padding(10),
+                column![text(tr("Datetype")), created, accessed, modified].padding(10),
+                self.timezone_input(app),
+                self.target_profile_input(app),
                 checkbox(
-                    "Convert uppercase to lowercase.",
+                    tr("Convert uppercase to lowercase."),
                     app.get_checkbox_states().convert_uppercase_to_lowercase
                 )
                 .on_toggle(|toggle| { Message::CheckboxToggled(toggle, 3) }),
                 checkbox(
-                    "Replace character with.",
+                    tr("Replace character with."),
                     app.get_checkbox_states().replace_character
                 )
                 .on_toggle(|toggle| { Message::CheckboxToggled(toggle, 4) }),
                 replaceables,
                 checkbox(
-                    "Use ascii characters only",
+                    tr("Use ascii characters only"),
                     app.get_checkbox_states().use_only_ascii
                 )
                 .on_toggle(|toggle| { Message::CheckboxToggled(toggle, 5) }),
                 checkbox(
-                    "Insert directory name to file name",
+                    tr("Insert directory name to file name"),
                     app.get_checkbox_states().insert_directory_name_to_file_name
                 )
                 .on_toggle(|toggle| { Message::CheckboxToggled(toggle, 6) }),
                 checkbox(
-                    "Insert date to file name",
+                    tr("Insert date to file name"),
                     app.get_checkbox_states().insert_date_to_file_name
                 )
                 .on_toggle(|toggle| { Message::CheckboxToggled(toggle, 7) }),
                 checkbox(
-                    "Remove original file name",
+                    tr("Remove original file name"),
                     app.get_checkbox_states().remove_original_file_name
                 )
                 .on_toggle(|toggle| { Message::CheckboxToggled(toggle, 8) }),
                 row![
                     checkbox(
-                        "Add custom name to file name",
+                        tr("Add custom name to file name"),
                         app.get_checkbox_states().add_custom_name
                     )
                     .on_toggle(|toggle| { Message::CheckboxToggled(toggle, 9) }),
@@ -342,23 +565,142 @@ impl Layout {
         ]
     }
 
+    fn timezone_input<'a>(&'a self, app: &'a App) -> Row<'a, Message> {
+        let mut row = row![
+            text(tr("Timezone")),
+            text_input(
+                tr("Local, UTC, +02:00 or Europe/Helsinki"),
+                app.get_timezone_input()
+            )
+            .on_input(Message::TimezoneInput)
+            .width(300),
+        ]
+        .spacing(5)
+        .padding(10)
+        .align_y(Vertical::Center);
+        if !app.is_timezone_input_valid() {
+            row = row.push(text(tr("Unknown timezone")));
+        }
+        row
+    }
+
+    fn target_profile_input<'a>(&'a self, app: &'a App) -> Row<'a, Message> {
+        let sanitize_options = app.get_sanitize_options();
+        row![
+            text(tr("Target filesystem")),
+            pick_list(
+                sanitize::TARGET_PROFILES,
+                Some(sanitize_options.profile),
+                Message::TargetProfileSelected,
+            ),
+            checkbox(
+                tr("Reject invalid names instead of rewriting"),
+                sanitize_options.action == SanitizeAction::Reject
+            )
+            .on_toggle(Message::RejectInvalidNamesToggled),
+        ]
+        .spacing(5)
+        .padding(10)
+        .align_y(Vertical::Center)
+    }
+
+    fn insert_cleanup_options<'a>(&'a self, app: &'a App) -> Row<'a, Message> {
+        let mut cleanup_row = row![checkbox(
+            tr("Remove directories left empty"),
+            app.get_remove_empty_directories()
+        )
+        .on_toggle(Message::RemoveEmptyDirectoriesToggled)]
+        .spacing(5)
+        .align_y(Vertical::Center);
+        if app.get_remove_empty_directories() {
+            cleanup_row = cleanup_row
+                .push(
+                    text_input(
+                        tr("Stop at (defaults to current directory)"),
+                        app.get_cleanup_boundary_input(),
+                    )
+                    .on_input(Message::CleanupBoundaryInput),
+                )
+                .push(
+                    text_input(
+                        tr("Junk files (.DS_Store, Thumbs.db)"),
+                        app.get_junk_file_names_input(),
+                    )
+                    .on_input(Message::JunkFileNamesInput),
+                );
+        }
+        cleanup_row
+    }
+
+    fn insert_commit_progress<'a>(&'a self, app: &'a App) -> Row<'a, Message> {
+        let mut cancel_button = button(tr("Cancel")).style(directory_button_style);
+        if !app.is_commit_cancelled() {
+            cancel_button = cancel_button.on_press(Message::CancelCommit);
+        }
+        let Some(progress) = app.get_commit_progress() else {
+            let starting = match app.is_undoing_commit() {
+                true => tr("Starting undo"),
+                false => tr("Starting commit"),
+            };
+            return row![text(starting), cancel_button]
+                .spacing(5)
+                .align_y(Vertical::Center);
+        };
+        let mut status = tr_format(
+            "{}/{} files, {} of {}",
+            &[
+                &progress.get_files_done(),
+                &progress.get_files_total(),
+                &format_size(progress.get_bytes_done() as f64),
+                &format_size(progress.get_bytes_total() as f64),
+            ],
+        );
+        if let Some(eta) = progress.get_eta() {
+            status.push_str(&tr_format(", {} s left", &[&eta.as_secs()]));
+        }
+        let mut column = column![
+            progress_bar(0.0..=1.0, progress.get_fraction_done())
+                .width(300)
+                .height(10),
+            text(status).size(13)
+        ]
+        .spacing(5);
+        if let Some(current_file) = progress.get_current_file() {
+            if let Some(file_name) = current_file.file_name() {
+                column = column.push(text(file_name.to_string_lossy().to_string()).size(13));
+            }
+        }
+        row![column, cancel_button]
+            .spacing(10)
+            .align_y(Vertical::Center)
+    }
+
+    fn insert_sanitized_names<'a>(&'a self, app: &'a App) -> Column<'a, Message> {
+        let mut column = Column::new();
+        column = column.push(text(tr("Names changed for the target filesystem")));
+        for sanitized_name in app.get_sanitized_names() {
+            column = column.push(text(sanitized_name.to_string()).size(13));
+        }
+        column.spacing(5).padding(10)
+    }
+
     fn custom_name_box(&self, app: &App) -> Row<Message> {
         let index_before = radio(
-            "Prefix",
+            tr("Prefix"),
             IndexPosition::Before,
             app.get_index_position(),
             Message::IndexPositionSelected,
         );
 
         let index_after = radio(
-            "Suffix",
+            tr("Suffix"),
             IndexPosition::After,
             app.get_index_position(),
             Message::IndexPositionSelected,
         );
         if app.get_checkbox_states().add_custom_name {
             return row![
-                text_input("Add custom file name", app.get_filename_input())
+                text_input(tr("Add custom file name"), app.get_filename_input())
                     .on_input(Message::FilenameInput),
                 column![index_before, index_after]
             ]
@@ -376,7 +718,7 @@ impl Layout {
                 if !app.get_filename_input().is_empty() {
                     filename_input.push_str(app.get_filename_input());
                 } else {
-                    filename_input.push_str("Custom Name");
+                    filename_input.push_str(tr("Custom Name"));
                 }
 
                 self.convert_text_by_checkbox_states(app, filename_input)
@@ -386,7 +728,7 @@ impl Layout {
                 if !app.get_filename_input().is_empty() {
                     filename_input.push_str(app.get_filename_input());
                 } else {
-                    filename_input.push_str("Custom Name");
+                    filename_input.push_str(tr("Custom Name"));
                 }
                 filename_input.push_str(" 1");
                 self.convert_text_by_checkbox_states(app, filename_input)
@@ -420,7 +762,7 @@ impl Layout {
         let mut row = Row::new();
         let order_of_filename_components = app.get_order_of_filename_components();
         if !order_of_filename_components.is_empty() {
-            column = column.push(text("Order of filename components example"));
+            column = column.push(text(tr("Order of filename components example")));
         }
         for (i, component) in order_of_filename_components.iter().enumerate() {
             let example_component = match component {
@@ -430,7 +772,7 @@ impl Layout {
                     formatted.to_string()
                 }
                 FilenameComponents::OriginalFilename => {
-                    let original_filename = String::from("Original Filename");
+                    let original_filename = String::from(tr("Original Filename"));
                     self.convert_text_by_checkbox_states(app, original_filename)
                 }
                 FilenameComponents::DirectoryName => {
@@ -439,12 +781,12 @@ impl Layout {
                     if !app.get_new_directory_input().is_empty() {
                         directory_name.push_str(new_directory_name);
                     } else {
-                        directory_name.push_str("Directory Name");
+                        directory_name.push_str(tr("Directory Name"));
                     }
                     self.convert_text_by_checkbox_states(app, directory_name)
                 }
                 FilenameComponents::CustomFilename => {
-                    let mut custom_name = String::from("Custom Name");
+                    let mut custom_name = String::from(tr("Custom Name"));
                     let filename_input = app.get_filename_input();
                     if !filename_input.is_empty() {
                         custom_name = String::from(filename_input);
@@ -477,24 +819,44 @@ impl Layout {
         if let Some(last_component) = directory_path.iter().last() {
             if let Some(dir_name) = last_component.to_str() {
                 let row = row![
-                    text("Selected directory").size(15),
+                    text(tr("Selected directory")).size(15),
                     button(dir_name).style(directory_button_style)
                 ]
                 .spacing(5)
                 .align_y(Vertical::Center);
                 column = column.push(row);
+                if let Some((operation, plan)) = app.get_selected_directory_plan() {
+                    return column
+                        .push(self.insert_selected_directory_plan(
+                            *operation,
+                            plan,
+                            directory_path,
+                        ))
+                        .padding(10)
+                        .spacing(10);
+                }
                 if !app.get_files_selected().is_empty() {
                     column = column.push(
-                        button("Insert selected files to selected directory")
+                        button(tr("Insert selected files to selected directory"))
                             .style(directory_button_style)
                             .on_press(Message::InsertFilesToSelectedDirectory),
                     );
                 }
+                if app.get_files_organized().is_empty() {
+                    column = column.push(self.insert_flatten_options(app));
+                }
                 if let Some(rules) = app.get_selected_directory_rules() {
                     column = column.push(column![
-                        text("Rules for selected directory"),
+                        text(tr("Rules for selected directory")),
                         self.selected_directory_rules(rules).padding(10)
-                    ])
+                    ]);
+                    if app.get_files_organized().is_empty() {
+                        column = column.push(
+                            button(tr("Re-apply rules to existing files"))
+                                .style(directory_button_style)
+                                .on_press(Message::ReapplySelectedDirectoryRules),
+                        );
+                    }
                 }
                 column = column.padding(10).spacing(10);
             }
@@ -502,10 +864,65 @@ impl Layout {
         column
     }
 
-    fn selected_directory_rules<'a>(
+    fn insert_flatten_options<'a>(&'a self, app: &'a App) -> Column<'a, Message> {
+        column![
+            text(tr("Flatten")),
+            text_input(
+                tr("Target directory (defaults to selected directory)"),
+                app.get_flatten_target_input()
+            )
+            .on_input(Message::FlattenTargetInput),
+            checkbox(
+                tr("Remove date and index prefixes from file names."),
+                app.get_strip_rename_prefixes()
+            )
+            .on_toggle(Message::StripRenamePrefixesToggled),
+            button(tr("Flatten"))
+                .style(directory_button_style)
+                .on_press(Message::FlattenSelectedDirectory),
+        ]
+        .spacing(5)
+    }
+
+    // Paths are shown relative to the selected directory.
+    fn insert_selected_directory_plan<'a>(
         &'a self,
-        rules: &'a SelectedDirectoryRules,
+        operation: DirectoryOperation,
+        plan: &'a Plan,
+        directory_path: &'a PathBuf,
     ) -> Column<'a, Message> {
+        let title = match operation {
+            DirectoryOperation::Flatten => "Flatten {} files",
+            DirectoryOperation::ReapplyRules => "Re-apply rules to {} files",
+        };
+        let mut column = column![row![
+            text(tr_format(title, &[&plan.get_moves().len()])),
+            button(tr("Cancel"))
+                .style(directory_button_style)
+                .on_press(Message::CancelSelectedDirectoryPlan),
+        ]
+        .spacing(5)
+        .align_y(Vertical::Center)]
+        .spacing(5);
+        for planned_move in plan.get_moves() {
+            let origin = planned_move
+                .get_origin()
+                .strip_prefix(directory_path)
+                .unwrap_or(planned_move.get_origin());
+            let destination = planned_move
+                .get_destination()
+                .strip_prefix(directory_path)
+                .unwrap_or(planned_move.get_destination());
+            column = column.push(text(format!(
+                "{} -> {}",
+                origin.display(),
+                destination.display()
+            )));
+        }
+        column
+    }
+
+    fn selected_directory_rules<'a>(&'a self, rules: &'a Rules) -> Column<'a, Message> {
         let mut column = Column::new();
         let checkbox_states = rules.get_checkbox_states();
         let replaceables = rules.get_replaceables();
@@ -513,6 +930,19 @@ impl Layout {
             column.push(self.insert_checkbox_states_for_directory(checkbox_states, replaceables));
         let date_type_selected = rules.get_date_type_selected();
         column = column.push(self.insert_date_type_selected_for_directory(date_type_selected));
+        column = column.push(row![
+            text(tr("Timezone: ")),
+            text(rules.get_timezone().to_string())
+        ]);
+        let sanitize_options = rules.get_sanitize_options();
+        column = column.push(row![
+            text(tr("Target filesystem: ")),
+            text(sanitize_options.profile.to_string())
+        ]);
+        column = column.push(row![
+            text(tr("Invalid names: ")),
+            text(sanitize_options.action.to_string())
+        ]);
         let index_position = rules.get_index_position();
         column = column.push(self.insert_index_position_for_directory(index_position));
         let order_of_filename_components = rules.get_order_of_filename_components();
@@ -526,7 +956,7 @@ impl Layout {
 
     fn insert_custom_filename<'a>(&'a self, custom_filename: &'a str) -> Column<'a, Message> {
         let mut column = Column::new();
-        column = column.push(row![text("Custom filename: "), text(custom_filename)]);
+        column = column.push(row![text(tr("Custom filename: ")), text(custom_filename)]);
 
         column
     }
@@ -536,7 +966,7 @@ impl Layout {
         order_of_filename_components: &'a Vec<FilenameComponents>,
     ) -> Column<'a, Message> {
         let mut column = Column::new();
-        column = column.push(text("Order of filename components: "));
+        column = column.push(text(tr("Order of filename components: ")));
         let mut row = Row::new();
         for component in order_of_filename_components {
             row = row.push(text(component.convert_to_text()));
@@ -553,10 +983,10 @@ impl Layout {
         let mut column = Column::new();
         if let Some(index_position) = index_position {
             let index_position_text = match index_position {
-                IndexPosition::After => "After",
-                IndexPosition::Before => "Before",
+                IndexPosition::After => tr("After"),
+                IndexPosition::Before => tr("Before"),
             };
-            column = column.push(row![text("Index position: "), text(index_position_text)]);
+            column = column.push(row![text(tr("Index position: ")), text(index_position_text)]);
         }
         column
     }
@@ -568,11 +998,11 @@ impl Layout {
         let mut column = Column::new();
         if let Some(date_type) = date_type_selected {
             let date_type_text = match date_type {
-                DateType::Created => "Created",
-                DateType::Accessed => "Accessed",
-                DateType::Modified => "Modified",
+                DateType::Created => tr("Created"),
+                DateType::Accessed => tr("Accessed"),
+                DateType::Modified => tr("Modified"),
             };
-            column = column.push(row![text("Date type: "), text(date_type_text)]);
+            column = column.push(row![text(tr("Date type: ")), text(date_type_text)]);
         }
         column
     }
@@ -595,15 +1025,15 @@ impl Layout {
             &checkbox_states.add_custom_name,
         ];
         let checkbox_text: [&str; 9] = [
-            "Organize by filetype",
-            "Organize by date",
-            "Convert uppercase to lowercase",
-            "Replace character",
-            "Use only ascii",
-            "Insert directory name to filename",
-            "Insert date to filename",
-            "Remove original filename",
-            "Add a custom name",
+            tr("Organize by filetype"),
+            tr("Organize by date"),
+            tr("Convert uppercase to lowercase"),
+            tr("Replace character"),
+            tr("Use only ascii"),
+            tr("Insert directory name to filename"),
+            tr("Insert date to filename"),
+            tr("Remove original filename"),
+            tr("Add a custom name"),
         ];
         for (i, checkbox_state) in checkbox_state_array.iter().enumerate() {
             if **checkbox_state {
@@ -622,22 +1052,19 @@ impl Layout {
             if let Some(replace) = replaceable.get_replaceable_selected() {
                 if let Some(replace_with) = replaceable.get_replace_with_selected() {
                     let replace_text = match replace {
-                        Replaceable::Dash => "Dash",
-                        Replaceable::Space => "Space",
-                        Replaceable::Comma => "Comma",
+                        Replaceable::Dash => tr("Dash"),
+                        Replaceable::Space => tr("Space"),
+                        Replaceable::Comma => tr("Comma"),
                     };
 
                     let replace_with_text = match replace_with {
-                        ReplaceWith::Nothing => "Nothing",
-                        ReplaceWith::Underscore => "Underscore",
+                        ReplaceWith::Nothing => tr("Nothing"),
+                        ReplaceWith::Underscore => tr("Underscore"),
                     };
-                    let row = row![
-                        text!("Replace "),
-                        text(replace_text),
-                        text(" With "),
-                        text(replace_with_text)
-                    ];
-                    column = column.push(row);
+                    column = column.push(text(tr_format(
+                        "Replace {} with {}",
+                        &[&replace_text, &replace_with_text],
+                    )));
                 }
             }
         }
@@ -647,41 +1074,87 @@ impl Layout {
     fn insert_files_selected<'a>(&'a self, app: &'a App) -> Column<'a, Message> {
         let mut column = Column::new();
 
+        let mut previews_by_file_name = BTreeMap::new();
+        let mut preview_error = None;
+        match app.get_rename_previews() {
+            Ok(rename_previews) => {
+                for (preview, sanitized_name) in rename_previews {
+                    previews_by_file_name.insert(preview.get_file_name(), (preview, sanitized_name));
+                }
+            }
+            Err(error) => preview_error = Some(error),
+        }
         let mut path_stack = PathBuf::from(app.get_path());
         for (i, (key, file)) in app.get_files_selected().iter().enumerate() {
             if i == 0 {
                 column = column.push(row![
-                    text_input("New directory name", app.get_new_directory_input())
+                    text_input(tr("New directory name"), app.get_new_directory_input())
                         .on_input(Message::InputNewDirectoryName),
-                    button("Create directory with selected files")
+                    button(tr("Create directory with selected files"))
                         .on_press(Message::CreateDirectoryWithSelectedFiles),
                 ]);
-                column = column.push(button("Just rename").on_press(Message::RenameFiles));
+                let find_duplicates_button = match app.is_finding_duplicates() {
+                    true => button(tr("Finding duplicates...")),
+                    false => button(tr("Find duplicates")).on_press(Message::FindDuplicates),
+                };
+                column = column.push(
+                    row![
+                        button(tr("Just rename")).on_press(Message::RenameFiles),
+                        find_duplicates_button
+                    ]
+                    .spacing(5),
+                );
+                column = column.push(self.insert_similar_image_options(app));
                 column = column.push(self.rules_for_directory(app));
 
-                column = column.push(text("Selected files").size(15));
+                column = column.push(text(tr("Selected files")).size(15));
                 let files_selected_count = app.get_files_selected().len();
                 let formatted_count = format!("{}", files_selected_count);
                 column = column.push(text(formatted_count));
+                match &preview_error {
+                    None if app.get_files_organized().is_empty() => {
+                        column = column.push(
+                            row![
+                                text(tr("Original name")).width(FillPortion(2)),
+                                text(tr("New name")).width(FillPortion(2)),
+                                text(tr("Destination")).width(FillPortion(3)),
+                            ]
+                            .spacing(5),
+                        );
+                    }
+                    None => {}
+                    Some(error) => {
+                        column = column
+                            .push(text(tr_format("Cannot preview new names: {}", &[&error])));
+                    }
+                }
             }
             if let Some(file_name) = key.to_str() {
                 path_stack.push(key);
                 if let Some(metadata) = file.get_metadata() {
                     if let Some(origin_path) = metadata.get_origin_path() {
                         if app.get_files_organized().is_empty() {
-                            column = column.push(
-                                mouse_area(button(file_name).style(file_button_style).on_press(
-                                    Message::SelectFile(FileSelectedLocation::FromFilesSelected(
-                                        origin_path.to_owned(),
-                                    )),
-                                ))
-                                .on_right_press(
-                                    Message::SelectMultipleFiles(
-                                        i,
-                                        FileSelectedLocation::FromFilesSelected(origin_path),
-                                    ),
-                                ),
-                            );
+                            let status = entry_status(app, &origin_path);
+                            let file_button = drag_source(
+                                button(file_name)
+                                    .width(Fill)
+                                    .style(move |theme, _| file_button_style(theme, status)),
+                                &origin_path,
+                            )
+                            .on_right_press(Message::SelectMultipleFiles(
+                                FileSelectedLocation::FromFilesSelected(origin_path),
+                            ));
+                            match previews_by_file_name.remove(key) {
+                                Some((preview, sanitized_name)) => {
+                                    column = column.push(self.insert_rename_preview(
+                                        app,
+                                        file_button,
+                                        preview,
+                                        sanitized_name.as_ref(),
+                                    ));
+                                }
+                                None => column = column.push(file_button),
+                            }
                         } else {
                             column = column.push(button(file_name).style(file_button_style))
                         }
@@ -695,45 +1168,388 @@ impl Layout {
         column
     }
 
-    fn insert_search_bar<'a>(&self, app: &'a App, path: &str) -> Row<'a, Message> {
+    fn insert_rename_preview<'a>(
+        &'a self,
+        app: &'a App,
+        file_button: MouseArea<'a, Message>,
+        preview: &'a RenamePreview,
+        sanitized_name: Option<&'a SanitizedName>,
+    ) -> Container<'a, Message> {
+        let file_name = preview.get_file_name().to_owned();
+        let destination_path = preview.get_destination_path();
+        let destination_path = destination_path
+            .strip_prefix(app.get_path())
+            .unwrap_or(destination_path)
+            .display()
+            .to_string();
+        let mut column = column![row![
+            container(file_button).width(FillPortion(2)),
+            text_input(
+                preview.get_new_file_name(),
+                app.get_rename_override(preview.get_file_name())
+            )
+            .on_input(move |input| Message::RenameOverrideInput(file_name.clone(), input))
+            .width(FillPortion(2)),
+            text(destination_path)
+                .size(13)
+                .width(FillPortion(3)),
+        ]
+        .spacing(5)
+        .align_y(Center)];
+
+        let mut notes = Vec::new();
+        if preview.has_collision() {
+            notes.push(String::from(tr("Same destination as another file")));
+        }
+        if preview.is_empty_name() {
+            notes.push(tr_format(
+                "The rules leave nothing of the name, {} is used",
+                &[&organize_files::FALLBACK_FILE_NAME],
+            ));
+        }
+        if let Some(sanitized_name) = sanitized_name {
+            let profile = app.get_sanitize_options().profile;
+            let problems: Vec<String> = sanitized_name
+                .get_problems()
+                .iter()
+                .map(|problem| problem.to_string())
+                .collect();
+            let sanitized_file_name = sanitized_name
+                .get_destination()
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            notes.push(match app.get_sanitize_options().action {
+                SanitizeAction::Rewrite => tr_format(
+                    "Renamed to {} for {}: {}",
+                    &[&sanitized_file_name, &profile, &problems.join(", ")],
+                ),
+                SanitizeAction::Reject => {
+                    tr_format("Rejected for {}: {}", &[&profile, &problems.join(", ")])
+                }
+            });
+        }
+        for note in notes {
+            column = column.push(text(note).size(12));
+        }
+        let is_problem = preview.has_collision() || preview.is_empty_name();
+        container(column.spacing(2))
+            .padding(2)
+            .style(rename_preview_style(is_problem, sanitized_name.is_some()))
+    }
+
+    fn insert_file_preview<'a>(
+        &'a self,
+        app: &'a App,
+        file_inspection: &'a FileInspection,
+    ) -> Column<'a, Message> {
+        let path_to_file = file_inspection.get_path();
+        let file_name = path_to_file
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let select_button = match app.is_focused_file_selected() {
+            true => button(tr("Unselect")).on_press(Message::SelectFile(
+                FileSelectedLocation::FromFilesSelected(path_to_file.to_owned()),
+            )),
+            false => button(tr("Select")).on_press(Message::SelectFile(
+                FileSelectedLocation::FromDirectory(path_to_file.to_owned()),
+            )),
+        };
+        let mut column = column![row![
+            text(file_name).size(15).width(Fill),
+            select_button.style(directory_button_style),
+            button(tr("Close"))
+                .style(directory_button_style)
+                .on_press(Message::ClosePreview),
+        ]
+        .spacing(5)
+        .align_y(Center)]
+        .spacing(10);
+        match file_inspection.get_content() {
+            PreviewContent::Image => {
+                column = column.push(iced::widget::image(path_to_file).height(300));
+            }
+            PreviewContent::Text(lines) => {
+                column = column.push(
+                    container(text(lines.join("\n")).size(12).font(Font::MONOSPACE))
+                        .padding(5)
+                        .style(container::bordered_box),
+                );
+            }
+            PreviewContent::Unavailable => {}
+        }
+
+        let format_date = |date: Option<DateTime<Local>>| match date {
+            Some(date) => locale::format_date_time(&date.naive_local()),
+            None => String::from("-"),
+        };
+        let details = vec![
+            (String::from(tr("Type")), file_inspection.get_mime_type().to_string()),
+            (String::from(tr("Size")), format_size(file_inspection.get_size() as f64)),
+            (String::from(tr("Created")), format_date(file_inspection.get_created())),
+            (String::from(tr("Accessed")), format_date(file_inspection.get_accessed())),
+            (String::from(tr("Modified")), format_date(file_inspection.get_modified())),
+            (
+                String::from(tr("Permissions")),
+                file_inspection.get_permissions().to_string(),
+            ),
+        ];
+        for (name, value) in details {
+            column = column.push(preview_detail_row(name, text(value).size(12)));
+        }
+        let hash: Element<'a, Message> = match file_inspection.get_hash() {
+            Some(hash) => text(hash).size(12).into(),
+            None if app.is_hashing(path_to_file) => text(tr("Hashing...")).size(12).into(),
+            None => button(text(tr("Hash")).size(12))
+                .style(directory_button_style)
+                .on_press(Message::HashFocusedFile)
+                .into(),
+        };
+        column = column.push(preview_detail_row(String::from("BLAKE3"), hash));
+        let exif_fields = file_inspection.get_exif_fields();
+        if !exif_fields.is_empty() {
+            column = column.push(preview_detail_row(String::from("EXIF"), text("")));
+            for (name, value) in exif_fields {
+                column = column.push(preview_detail_row(name.clone(), text(value).size(12)));
+            }
+        }
+        column
+    }
+
+    fn insert_duplicate_groups<'a>(&'a self, app: &'a App) -> Column<'a, Message> {
+        let mut column = Column::new().spacing(10);
+        column = column.push(
+            row![
+                text(tr("Duplicate files")).size(15),
+                button(tr("Close")).on_press(Message::CloseDuplicates)
+            ]
+            .spacing(5)
+            .align_y(Center),
+        );
+        let actions = [
+            DuplicateAction::Skip,
+            DuplicateAction::Delete,
+            DuplicateAction::HardLink,
+            DuplicateAction::Quarantine,
+        ];
+        for (i, group) in app.get_duplicate_groups().iter().enumerate() {
+            let mut group_column = column![
+                text(format!(
+                    "{}  {}",
+                    format_size(group.get_size() as f64),
+                    &group.get_hash()[..12]
+                ))
+                .size(12),
+                text(tr_format("Keep {}", &[&group.get_original().display()])),
+            ];
+            for duplicate in group.get_duplicates() {
+                group_column = group_column.push(text(format!("  {}", duplicate.display())));
+            }
+            let mut action_row = Row::new().spacing(5);
+            for action in actions {
+                action_row = action_row.push(
+                    button(text(action.to_string()))
+                        .style(file_button_style)
+                        .on_press(Message::ResolveDuplicates(i, action)),
+                );
+            }
+            column = column.push(group_column.push(action_row).spacing(5));
+        }
+        column
+    }
+
+    fn insert_similar_image_options<'a>(&'a self, app: &'a App) -> Row<'a, Message> {
+        let find_button = match app.is_finding_similar_images() {
+            true => button(tr("Finding similar images...")),
+            false => button(tr("Find similar images")).on_press(Message::FindSimilarImages),
+        };
         row![
+            find_button,
+            pick_list(
+                [
+                    PerceptualHashType::Average,
+                    PerceptualHashType::Difference,
+                    PerceptualHashType::Perceptual
+                ],
+                Some(app.get_perceptual_hash_type()),
+                Message::PerceptualHashTypeSelected,
+            ),
+            text(tr("Threshold")),
+            slider(
+                0..=similar_images::MAX_SIMILARITY_THRESHOLD,
+                app.get_similarity_threshold(),
+                Message::SimilarityThresholdChanged
+            )
+            .width(100),
+            text(app.get_similarity_threshold()),
+        ]
+        .spacing(5)
+        .align_y(Center)
+    }
+
+    fn insert_similar_image_groups<'a>(&'a self, app: &'a App) -> Column<'a, Message> {
+        let mut column = Column::new().spacing(10);
+        column = column.push(
+            row![
+                text(tr("Similar images")).size(15),
+                button(tr("Close")).on_press(Message::CloseSimilarImages)
+            ]
+            .spacing(5)
+            .align_y(Center),
+        );
+        for (i, group) in app.get_similar_image_groups().iter().enumerate() {
+            let highest = group.get_highest_resolution().map(|image| image.get_path());
+            let mut images_row = Row::new().spacing(10);
+            for image in group.get_images() {
+                let mut caption = format!(
+                    "{}x{} {}",
+                    image.get_width(),
+                    image.get_height(),
+                    format_size(image.get_size() as f64)
+                );
+                if highest == Some(image.get_path()) {
+                    caption.push_str(tr(" (keep)"));
+                }
+                let file_name = image
+                    .get_path()
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or_default();
+                images_row = images_row.push(column![
+                    iced::widget::image(image.get_path()).width(200),
+                    text(file_name).size(12),
+                    text(caption).size(12),
+                ]);
+            }
+            column = column.push(
+                column![
+                    scrollable(images_row).direction(scrollable::Direction::Horizontal(
+                        scrollable::Scrollbar::default()
+                    )),
+                    button(tr("Keep highest resolution"))
+                        .style(file_button_style)
+                        .on_press(Message::KeepHighestResolution(i)),
+                ]
+                .spacing(5),
+            );
+        }
+        column
+    }
+
+    // Matches of the path completion are listed under the input.
+    fn insert_search_bar<'a>(&self, app: &'a App, path: &str) -> Column<'a, Message> {
+        let mut column = column![row![
             text_input(path, app.get_path_input())
                 .id(app.get_path_input_id())
                 .on_input(Message::TextInput)
                 .on_submit(Message::SearchPath(true)),
-            button("Search")
+            button(tr("Search"))
                 .style(directory_button_style)
                 .on_press(Message::SearchPath(false))
-        ]
+        ]];
+        if let Some(path_completion) = app.get_path_completion() {
+            let mut candidates_column = Column::new();
+            for (i, candidate) in path_completion.get_candidates().iter().enumerate() {
+                let is_selected = path_completion.get_selected() == Some(i);
+                candidates_column = candidates_column.push(
+                    button(text(candidate.as_str()).size(13))
+                        .width(Fill)
+                        .style(move |theme, status| {
+                            directory_button_style(theme, highlight(is_selected, status))
+                        })
+                        .on_press(Message::PathCompletionSelected(i)),
+                );
+            }
+            column = column.push(
+                container(scrollable(candidates_column))
+                    .max_height(200)
+                    .style(container::bordered_box),
+            );
+        }
+        column
     }
 
     fn insert_directory_view_buttons<'a>(&self, app: &'a App) -> Row<'a, Message> {
         row![
-            button("List view")
+            button(tr("List view"))
                 .on_press(Message::SwitchDirectoryView(DirectoryView::List))
                 .style(|theme: &Theme, _| {
                     let status = match app.get_directory_view() {
                         DirectoryView::List => button::Status::Disabled,
-                        DirectoryView::DropDown => button::Status::Active,
+                        _ => button::Status::Active,
                     };
                     directory_button_style(theme, status)
                 }),
-            button("Drop down")
+            button(tr("Drop down"))
                 .on_press(Message::SwitchDirectoryView(DirectoryView::DropDown))
                 .style(|theme: &Theme, _| {
                     let status = match app.get_directory_view() {
-                        DirectoryView::List => button::Status::Active,
                         DirectoryView::DropDown => button::Status::Disabled,
+                        _ => button::Status::Active,
+                    };
+                    directory_button_style(theme, status)
+                }),
+            button(tr("Grid"))
+                .on_press(Message::SwitchDirectoryView(DirectoryView::Grid))
+                .style(|theme: &Theme, _| {
+                    let status = match app.get_directory_view() {
+                        DirectoryView::Grid => button::Status::Disabled,
+                        _ => button::Status::Active,
                     };
                     directory_button_style(theme, status)
                 }),
         ]
     }
 
+    // Clicking a column header sorts by it, clicking it again reverses the
+    // order.
+    fn insert_file_list_controls<'a>(&self, app: &'a App) -> Column<'a, Message> {
+        let file_sort = app.get_file_sort();
+        let mut header_row = Row::new().spacing(5);
+        for sort_column in SORT_COLUMNS {
+            let mut label = sort_column.to_string();
+            if sort_column == file_sort.get_column() {
+                match file_sort.get_order() {
+                    SortOrder::Ascending => label.push_str(" ^"),
+                    SortOrder::Descending => label.push_str(" v"),
+                }
+            }
+            header_row = header_row.push(
+                button(text(label))
+                    .style(directory_button_style)
+                    .on_press(Message::SortFilesBy(sort_column)),
+            );
+        }
+        column![
+            row![
+                text_input(tr("Filter files (name, *.jpg, .png)"), app.get_file_filter_input())
+                    .on_input(Message::FileFilterInput),
+                text_input(
+                    tr("Filter for dropped folders (*.jpg, .png)"),
+                    app.get_drop_filter_input()
+                )
+                .on_input(Message::DropFilterInput),
+            ]
+            .spacing(5),
+            header_row
+        ]
+        .spacing(5)
+    }
+
     fn display_directory_contents<'a>(&'a self, app: &'a App) -> Column<'a, Message> {
         match app.get_directory_view() {
             DirectoryView::List => {
-                column![scrollable(self.display_directory_contents_as_list(app))]
+                column![
+                    self.insert_file_list_controls(app),
+                    scrollable(self.display_directory_contents_as_list(app))
+                ]
+            }
+            DirectoryView::Grid => {
+                column![
+                    self.insert_file_list_controls(app),
+                    scrollable(self.display_directory_contents_as_grid(app))
+                ]
             }
             DirectoryView::DropDown => {
                 let path = PathBuf::from(app.get_path());
@@ -742,12 +1558,15 @@ impl Layout {
                 let mut path_stack = PathBuf::new();
                 skip_prefix_in_path(&mut path_iter, &mut path_stack);
                 let root_dir = app.get_root_directory();
-                return column![scrollable(self.insert_directory_content_as_dropdown(
-                    root_dir,
-                    &mut path_iter,
-                    &mut path_stack,
-                    app
-                ))];
+                return column![
+                    self.insert_file_list_controls(app),
+                    scrollable(self.insert_directory_content_as_dropdown(
+                        root_dir,
+                        &mut path_iter,
+                        &mut path_stack,
+                        app
+                    ))
+                ];
             }
         }
     }
@@ -769,6 +1588,7 @@ impl Layout {
                         path_stack,
                         column,
                         drop_down_icon,
+                        app,
                     );
                     if dir_key == next {
                         if let Some(selected) = directories.get(dir_key) {
@@ -795,6 +1615,7 @@ impl Layout {
                         path_stack,
                         column,
                         drop_down_icon,
+                        app,
                     );
                 }
             }
@@ -812,6 +1633,90 @@ impl Layout {
         column
     }
 
+    // Subdirectories as rows like in the list view, files as thumbnail tiles
+    // in the order of the file list.
+    fn display_directory_contents_as_grid<'a>(&'a self, app: &'a App) -> Column<'a, Message> {
+        let path = app.get_path();
+        let directory = app.get_root_directory().get_directory_by_path(path);
+        let mut column = Column::new().spacing(5);
+        if let Some(directories) = directory.get_directories() {
+            for key in directories.keys() {
+                let path_to_directory = path.join(key);
+                let is_highlighted = app.is_highlighted(&path_to_directory);
+                let directory_row = row![
+                    button(">")
+                        .style(directory_button_style)
+                        .on_press(Message::DropDownDirectory(path_to_directory.to_owned())),
+                    button(text(key.to_string_lossy().to_string()))
+                        .style(move |theme, status| {
+                            directory_button_style(theme, highlight(is_highlighted, status))
+                        })
+                        .width(Fill)
+                        .on_press(Message::SelectDirectory(path_to_directory.to_owned())),
+                ];
+                column = column.push(track_hover(directory_row, &path_to_directory));
+            }
+        }
+        let Some(files) = directory.get_files() else {
+            return column;
+        };
+        let file_names =
+            file_list::visible_file_names(files, app.get_file_sort(), app.get_file_filter());
+        for chunk in file_names.chunks(GRID_COLUMNS) {
+            let mut grid_row = Row::new().spacing(5);
+            for key in chunk {
+                let path_to_file = path.join(key);
+                let status = match app.get_files_organized().is_empty() {
+                    true => entry_status(app, &path_to_file),
+                    false => button::Status::Disabled,
+                };
+                let file_name = key.to_string_lossy().to_string();
+                let tile = button(
+                    column![
+                        self.insert_thumbnail(app, &path_to_file, &file_name),
+                        text(file_name).size(12),
+                    ]
+                    .spacing(5)
+                    .align_x(Center),
+                )
+                .style(move |theme, _| file_button_style(theme, status))
+                .width(THUMBNAIL_TILE_WIDTH);
+                if !app.get_files_organized().is_empty() {
+                    grid_row = grid_row.push(tile);
+                    continue;
+                }
+                grid_row = grid_row.push(
+                    drag_source(tile, &path_to_file)
+                    .on_right_press(Message::SelectMultipleFiles(
+                        FileSelectedLocation::FromDirectory(path_to_file),
+                    )),
+                );
+            }
+            column = column.push(grid_row);
+        }
+        column
+    }
+
+    // Files without a thumbnail show their file type instead.
+    fn insert_thumbnail<'a>(
+        &self,
+        app: &'a App,
+        path_to_file: &PathBuf,
+        file_name: &str,
+    ) -> Container<'a, Message> {
+        let size = thumbnails::THUMBNAIL_SIZE as f32;
+        let placeholder = match app.get_thumbnail(path_to_file) {
+            Some(ThumbnailState::Ready(thumbnail_path)) => {
+                return container(iced::widget::image(thumbnail_path).width(size).height(size));
+            }
+            Some(ThumbnailState::Loading) => String::from("..."),
+            _ => organize_files::get_file_type_from_file_name(file_name)
+                .unwrap_or_default()
+                .to_uppercase(),
+        };
+        container(text(placeholder)).center_x(size).center_y(size)
+    }
+
     fn insert_directory_contents_as_list<'a>(
         &self,
         current_directory: &'a Directory,
@@ -850,47 +1755,49 @@ impl Layout {
             for key in directories.keys() {
                 path_stack.push(key);
                 if let Some(dir_name) = key.to_str() {
+                    let is_highlighted = app.is_highlighted(path_stack);
                     let mut button_row = row![button(">")
                         .style(directory_button_style)
                         .on_press(Message::DropDownDirectory(path_stack.to_owned()))];
                     button_row = button_row.push(
                         button(dir_name)
-                            .style(directory_button_style)
+                            .style(move |theme, status| {
+                                directory_button_style(theme, highlight(is_highlighted, status))
+                            })
                             .width(Fill)
                             .on_press(Message::SelectDirectory(path_stack.to_owned())),
                     );
                     button_row = button_row.width(FillPortion(2));
-                    column = column.push(button_row);
+                    column = column.push(track_hover(button_row, path_stack));
                 }
                 path_stack.pop();
             }
         }
         if let Some(files) = dir.get_files() {
-            for (i, (key, value)) in files.iter().enumerate() {
+            let file_names =
+                file_list::visible_file_names(files, app.get_file_sort(), app.get_file_filter());
+            for key in file_names {
+                let value = &files[key];
                 path_stack.push(key);
                 if let Some(file_name) = key.to_str() {
                     let mut file_information = String::from(file_name);
                     if let Some(metadata) = value.get_metadata() {
                         if let Some(size) = metadata.get_size() {
                             file_information.push(' ');
-                            let (divided_size, postfix) = round_size(size);
-                            let formatted_size = format!("{} {}", divided_size, postfix);
-                            file_information.push_str(&formatted_size);
+                            file_information.push_str(&format_size(size));
                         }
                     }
                     if app.get_files_organized().is_empty() {
+                        let status = entry_status(app, path_stack);
                         column = column.push(
-                            mouse_area(
+                            drag_source(
                                 button(text(file_information))
-                                    .style(file_button_style)
-                                    .width(FillPortion(2))
-                                    .on_press(Message::SelectFile(
-                                        FileSelectedLocation::FromDirectory(path_stack.to_owned()),
-                                    )),
+                                    .style(move |theme, _| file_button_style(theme, status))
+                                    .width(FillPortion(2)),
+                                path_stack,
                             )
                             .on_right_press(
                                 Message::SelectMultipleFiles(
-                                    i,
                                     FileSelectedLocation::FromDirectory(path_stack.to_owned()),
                                 ),
                             ),
@@ -905,6 +1812,45 @@ impl Layout {
         column
     }
 
+    // Every entry jumps to its directory like a searched path.
+    fn insert_sidebar<'a>(&self, app: &'a App) -> Column<'a, Message> {
+        let settings = app.get_settings();
+        let mut column = column![text(tr("Bookmarks")).size(15)].spacing(5);
+        for (i, bookmark) in settings.get_bookmarks().iter().enumerate() {
+            column = column.push(
+                row![
+                    sidebar_button(bookmark).width(Fill),
+                    button("x")
+                        .style(directory_button_style)
+                        .on_press(Message::RemoveBookmark(i)),
+                ]
+                .spacing(2),
+            );
+        }
+        column = column.push(
+            button(tr("Bookmark this directory"))
+                .style(directory_button_style)
+                .on_press_maybe(
+                    (!app.get_path().as_os_str().is_empty()).then_some(Message::AddBookmark),
+                ),
+        );
+        let sections = [
+            (tr("Places"), app.get_user_directories()),
+            (tr("Recent"), settings.get_recent_paths()),
+            (tr("Organized directories"), app.get_organized_directories()),
+        ];
+        for (title, paths) in sections {
+            if paths.is_empty() {
+                continue;
+            }
+            column = column.push(text(title).size(15));
+            for path in paths {
+                column = column.push(sidebar_button(path).width(Fill));
+            }
+        }
+        column.padding(5)
+    }
+
     fn insert_external_storage<'a>(&self, app: &'a App) -> Row<'a, Message> {
         let mut row = Row::new();
         let external_directories: &BTreeSet<OsString> = app.get_external_directories();
@@ -926,10 +1872,12 @@ impl Layout {
         path_stack: &PathBuf,
         mut column: Column<'a, Message>,
         drop_down_icon: &'a str,
+        app: &App,
     ) -> Column<'a, Message> {
         let mut path_stack = PathBuf::from(&path_stack);
 
         path_stack.push(selected_directory_key);
+        let is_highlighted = app.is_highlighted(&path_stack);
 
         if let Some(key) = selected_directory_key.to_str() {
             let mut row = row![button(drop_down_icon)
@@ -938,10 +1886,12 @@ impl Layout {
             row = row.push(
                 button(key)
                     .width(Fill)
-                    .style(directory_button_style)
+                    .style(move |theme, status| {
+                        directory_button_style(theme, highlight(is_highlighted, status))
+                    })
                     .on_press(Message::SelectDirectory(PathBuf::from(&path_stack))),
             );
-            column = column.push(row);
+            column = column.push(track_hover(row, &path_stack));
         }
         column
     }
@@ -954,26 +1904,23 @@ impl Layout {
         app: &'a App,
     ) -> Column<'a, Message> {
         if let Some(files) = selected.get_files() {
-            let mut iterator = 0;
-            for (key, _value) in files {
+            let file_names =
+                file_list::visible_file_names(files, app.get_file_sort(), app.get_file_filter());
+            for key in file_names {
                 if let Some(file_name) = key.to_str() {
                     let mut path_to_file = PathBuf::from(current_path);
                     path_to_file.push(file_name);
                     if app.get_files_organized().is_empty() {
+                        let status = entry_status(app, &path_to_file);
                         column = column.push(
-                            mouse_area(
+                            drag_source(
                                 button(file_name)
-                                    .style(file_button_style)
-                                    .on_press(Message::SelectFile(
-                                        FileSelectedLocation::FromDirectory(
-                                            path_to_file.to_owned(),
-                                        ),
-                                    ))
+                                    .style(move |theme, _| file_button_style(theme, status))
                                     .padding(5),
+                                &path_to_file,
                             )
                             .on_right_press(
                                 Message::SelectMultipleFiles(
-                                    iterator,
                                     FileSelectedLocation::FromDirectory(path_to_file.to_owned()),
                                 ),
                             ),
@@ -982,7 +1929,6 @@ impl Layout {
                         column = column.push(button(file_name).style(file_button_style));
                     }
                 }
-                iterator += 1;
             }
         }
         column
@@ -998,29 +1944,27 @@ impl Layout {
         let mut row = Row::new();
         row = row.push(text(name).width(FillPortion(fill_portion_amount)));
         if let Some(created) = metadata.get_created() {
-            let formatted = created.format("%Y-%m-%d %H:%M:%S").to_string();
+            let formatted = locale::format_date_time(&created.naive_local());
             row = row.push(text(formatted).width(FillPortion(fill_portion_amount)));
         }
 
         if let Some(accessed) = metadata.get_accessed() {
-            let formatted = accessed.format("%Y-%m-%d %H:%M:%S").to_string();
+            let formatted = locale::format_date_time(&accessed.naive_local());
             row = row.push(text(formatted).width(FillPortion(fill_portion_amount)));
         }
         if let Some(modified) = metadata.get_modified() {
-            let formatted = modified.format("%Y-%m-%d %H:%M:%S").to_string();
+            let formatted = locale::format_date_time(&modified.naive_local());
             row = row.push(text(formatted).width(FillPortion(fill_portion_amount)));
         }
 
         if metadata.get_readonly() {
-            row = row.push(text("No permission").width(FillPortion(fill_portion_amount)));
+            row = row.push(text(tr("No permission")).width(FillPortion(fill_portion_amount)));
         } else {
-            row = row.push(text("Allowed").width(FillPortion(fill_portion_amount)));
+            row = row.push(text(tr("Allowed")).width(FillPortion(fill_portion_amount)));
         }
 
         if let Some(size) = metadata.get_size() {
-            let (divided_size, postfix) = round_size(size);
-            let formatted_size = format!("{} {}", divided_size, postfix);
-            row = row.push(text(formatted_size).width(FillPortion(fill_portion_amount)));
+            row = row.push(text(format_size(size)).width(FillPortion(fill_portion_amount)));
         } else {
             row = row.push(text("-").width(FillPortion(fill_portion_amount)));
         }
@@ -1035,113 +1979,150 @@ const TB: f64 = 1_000_000_000_000.0;
 const PB: f64 = 1_000_000_000_000_000.0;
 const EB: f64 = 1_000_000_000_000_000_000.0;
 
-fn round_size(size: f64) -> (f64, String) {
+fn round_size(size: f64) -> (f64, &'static str) {
     let mut divided_size = size;
-    let mut postfix = String::from("B");
+    let mut postfix = "B";
 
     if size > EB {
         divided_size /= EB;
-        postfix = String::from("EB");
+        postfix = "EB";
     } else if size > PB {
         divided_size /= PB;
-        postfix = String::from("PB")
+        postfix = "PB"
     } else if size > TB {
         divided_size /= TB;
-        postfix = String::from("TB");
+        postfix = "TB";
     } else if size > GB {
         divided_size /= GB;
-        postfix = String::from("GB");
+        postfix = "GB";
     } else if size > MB {
         divided_size /= MB;
-        postfix = String::from("MB");
+        postfix = "MB";
     } else if size > KB {
         divided_size /= KB;
-        postfix = String::from("KB");
+        postfix = "KB";
     }
     divided_size = (divided_size * 10.0).ceil() / 10.0;
     (divided_size, postfix)
 }
 
-fn directory_button_style(_: &Theme, status: button::Status) -> button::Style {
-    match status {
-        button::Status::Active => {
-            let mut style = button::Style::default().with_background(Background::Color(
-                get_directory_button_background_color(1.0),
-            ));
-            style.text_color = Color::from_rgba(1.0, 1.0, 1.0, 1.0);
-            style
-        }
-        button::Status::Hovered => {
-            let mut style = button::Style::default().with_background(Background::Color(
-                get_directory_button_background_color(0.7),
-            ));
-            style.text_color = Color::from_rgba(1.0, 1.0, 1.0, 1.0);
-            style
-        }
-        button::Status::Disabled => {
-            let mut style = button::Style::default().with_background(Background::Color(
-                get_directory_button_background_color(0.1),
-            ));
-            style.text_color = Color::from_rgba(1.0, 1.0, 1.0, 1.0);
-            style
-        }
-        button::Status::Pressed => {
-            let mut style = button::Style::default().with_background(Background::Color(
-                get_directory_button_background_color(0.4),
-            ));
-            style.text_color = Color::from_rgba(1.0, 1.0, 1.0, 1.0);
-            style
-        }
-    }
+// The unit is translated too, in Finnish bytes are tavu.
+fn format_size(size: f64) -> String {
+    let (divided_size, postfix) = round_size(size);
+    format!("{} {}", locale::format_number(divided_size), tr(postfix))
 }
 
-fn file_button_style(_: &Theme, status: button::Status) -> button::Style {
-    match status {
-        button::Status::Active => {
-            let mut style = button::Style::default()
-                .with_background(Background::Color(get_file_button_background_color(1.0)));
-            style.text_color = Color::from_rgba(1.0, 1.0, 1.0, 1.0);
-            style
-        }
-        button::Status::Hovered => {
-            let mut style = button::Style::default()
-                .with_background(Background::Color(get_file_button_background_color(0.7)));
-            style.text_color = Color::from_rgba(1.0, 1.0, 1.0, 1.0);
-            style
-        }
-        button::Status::Disabled => {
-            let mut style = button::Style::default()
-                .with_background(Background::Color(get_file_button_background_color(0.1)));
-            style.text_color = Color::from_rgba(1.0, 1.0, 1.0, 1.0);
-            style
-        }
-        button::Status::Pressed => {
-            let mut style = button::Style::default()
-                .with_background(Background::Color(get_file_button_background_color(0.7)));
-            style.text_color = Color::from_rgba(1.0, 1.0, 1.0, 1.0);
-            style
-        }
-    }
+// Entries are named by their last component, the root by its full path.
+fn sidebar_button<'a>(path: &PathBuf) -> Button<'a, Message> {
+    let name = match path.file_name() {
+        Some(file_name) => file_name.to_string_lossy().to_string(),
+        None => path.display().to_string(),
+    };
+    button(text(name).size(13))
+        .style(directory_button_style)
+        .on_press(Message::JumpToPath(PathBuf::from(path)))
 }
 
-fn get_directory_button_background_color(alpha_value: f32) -> Color {
-    Color {
-        r: 0.42,
-        g: 0.53,
-        b: 0.671,
-        a: alpha_value,
+// Focused entries and drop targets are drawn as if hovered.
+fn highlight(is_highlighted: bool, status: button::Status) -> button::Status {
+    match (is_highlighted, status) {
+        (true, button::Status::Active) => button::Status::Hovered,
+        _ => status,
     }
 }
 
-fn get_file_button_background_color(alpha_value: f32) -> Color {
-    Color {
-        r: 0.4,
-        g: 0.4,
-        b: 0.4,
-        a: alpha_value,
+// File buttons leave the press to their mouse area and would be drawn
+// disabled, so their status comes from the hover and drag state instead.
+fn entry_status(app: &App, path: &PathBuf) -> button::Status {
+    let status = match (app.is_dragged(path), app.is_hovered(path)) {
+        (true, _) => button::Status::Pressed,
+        (false, true) => button::Status::Hovered,
+        (false, false) => button::Status::Active,
+    };
+    highlight(app.is_highlighted(path), status)
+}
+
+// Collisions and empty names are drawn with the danger color, names changed
+// for the target filesystem with the secondary color.
+fn rename_preview_style(
+    is_problem: bool,
+    is_sanitized: bool,
+) -> impl Fn(&Theme) -> container::Style {
+    move |theme| match (is_problem, is_sanitized) {
+        (true, _) => container::background(theme.extended_palette().danger.weak.color),
+        (false, true) => container::background(theme.extended_palette().secondary.weak.color),
+        (false, false) => container::Style::default(),
     }
 }
 
+fn preview_detail_row<'a>(
+    name: String,
+    value: impl Into<Element<'a, Message>>,
+) -> Row<'a, Message> {
+    row![
+        text(name).size(12).width(FillPortion(1)),
+        container(value).width(FillPortion(2)),
+    ]
+    .spacing(5)
+}
+
+// Tracks the entry under the mouse, so files can be dragged onto
+// directories.
+fn track_hover<'a>(
+    content: impl Into<Element<'a, Message>>,
+    path: &PathBuf,
+) -> MouseArea<'a, Message> {
+    mouse_area(content)
+        .on_enter(Message::EntryHovered(path.to_owned()))
+        .on_exit(Message::EntryHoverEnded(path.to_owned()))
+        .on_move({
+            let path = path.to_owned();
+            move |_| Message::EntryHovered(path.clone())
+        })
+}
+
+// Files are pressed through their own mouse area, so a drag starts only
+// from the entry under the mouse. Releasing on the same entry clicks it.
+fn drag_source<'a>(
+    content: impl Into<Element<'a, Message>>,
+    path: &PathBuf,
+) -> MouseArea<'a, Message> {
+    track_hover(content, path)
+        .on_press(Message::EntryPressed(path.to_owned()))
+        .on_release(Message::EntryReleased(path.to_owned()))
+}
+
+fn directory_button_style(theme: &Theme, status: button::Status) -> button::Style {
+    let primary = theme.extended_palette().primary;
+    palette_button_style(primary.base, primary.strong, status)
+}
+
+fn file_button_style(theme: &Theme, status: button::Status) -> button::Style {
+    let secondary = theme.extended_palette().secondary;
+    palette_button_style(secondary.base, secondary.strong, status)
+}
+
+// Buttons take their colors from the theme, so the text stays readable on
+// light and dark themes alike.
+fn palette_button_style(
+    base: palette::Pair,
+    strong: palette::Pair,
+    status: button::Status,
+) -> button::Style {
+    let pair = match status {
+        button::Status::Hovered | button::Status::Pressed => strong,
+        _ => base,
+    };
+    let alpha_value = match status {
+        button::Status::Disabled => 0.5,
+        _ => 1.0,
+    };
+    let mut style = button::Style::default()
+        .with_background(Background::Color(pair.color.scale_alpha(alpha_value)));
+    style.text_color = pair.text.scale_alpha(alpha_value);
+    style
+}
+
 fn skip_prefix_in_path(path_iter: &mut Iter<'_>, path_stack: &mut PathBuf) {
     if let Some(root) = path_iter.next() {
         path_stack.push(root);
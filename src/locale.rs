@@ -0,0 +1,388 @@
+use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+// Catalogs are gettext style .po files. The English catalog lists every
+// message id with an empty translation, so the id itself is shown.
+const ENGLISH_CATALOG: &str = include_str!("../locales/en.po");
+const FINNISH_CATALOG: &str = include_str!("../locales/fi.po");
+const PLACEHOLDER: &str = "{}";
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    Finnish,
+}
+
+pub const LANGUAGES: [Language; 2] = [Language::English, Language::Finnish];
+
+// Language names are written in the language itself, so they can be found
+// whatever language is selected.
+impl std::fmt::Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Language::English => "English",
+            Language::Finnish => "Suomi",
+        })
+    }
+}
+
+impl Language {
+    pub fn get_code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Finnish => "fi",
+        }
+    }
+
+    pub fn parse(code: &str) -> Option<Self> {
+        LANGUAGES
+            .into_iter()
+            .find(|language| language.get_code() == code.trim())
+    }
+
+    // The system locale is like fi-FI or fi_FI.UTF-8.
+    pub fn detect() -> Self {
+        match sys_locale::get_locale() {
+            Some(locale) if locale.to_lowercase().starts_with("fi") => Language::Finnish,
+            _ => Language::English,
+        }
+    }
+
+    fn get_catalog(&self) -> &'static BTreeMap<String, String> {
+        static ENGLISH: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+        static FINNISH: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+        match self {
+            Language::English => ENGLISH.get_or_init(|| parse_catalog(ENGLISH_CATALOG)),
+            Language::Finnish => FINNISH.get_or_init(|| parse_catalog(FINNISH_CATALOG)),
+        }
+    }
+}
+
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn get_language() -> Language {
+    match LANGUAGE.load(Ordering::Relaxed) {
+        1 => Language::Finnish,
+        _ => Language::English,
+    }
+}
+
+// Messages missing from the catalog are shown untranslated.
+pub fn tr(id: &'static str) -> &'static str {
+    lookup(get_language(), id).unwrap_or(id)
+}
+
+// Fills the {} placeholders of the translated message in order.
+pub fn tr_format(id: &'static str, arguments: &[&dyn Display]) -> String {
+    fill_placeholders(
+        tr(id),
+        arguments.iter().map(|argument| argument.to_string()),
+    )
+}
+
+// Translates messages that were built at runtime, like errors. Messages with
+// placeholders in the catalog are matched against the whole message and the
+// parts that filled them are carried over.
+pub fn translate(message: &str) -> String {
+    translate_in(get_language(), message)
+}
+
+pub fn format_number(number: f64) -> String {
+    format_number_in(get_language(), number)
+}
+
+// Dates are shown in local time.
+pub fn format_date_time(date: &NaiveDateTime) -> String {
+    format_date_time_in(get_language(), date)
+}
+
+fn translate_in(language: Language, message: &str) -> String {
+    if let Some(translation) = lookup(language, message) {
+        return String::from(translation);
+    }
+    for (id, translation) in language.get_catalog() {
+        if translation.is_empty() || !id.contains(PLACEHOLDER) {
+            continue;
+        }
+        if let Some(arguments) = match_placeholders(id, message) {
+            return fill_placeholders(translation, arguments.into_iter().map(String::from));
+        }
+    }
+    String::from(message)
+}
+
+fn format_number_in(language: Language, number: f64) -> String {
+    let formatted = number.to_string();
+    match language {
+        Language::English => formatted,
+        Language::Finnish => formatted.replace('.', ","),
+    }
+}
+
+fn format_date_time_in(language: Language, date: &NaiveDateTime) -> String {
+    let format = match language {
+        Language::English => "%Y-%m-%d %H:%M:%S",
+        Language::Finnish => "%-d.%-m.%Y %H.%M.%S",
+    };
+    date.format(format).to_string()
+}
+
+fn lookup(language: Language, id: &str) -> Option<&'static str> {
+    language
+        .get_catalog()
+        .get(id)
+        .filter(|translation| !translation.is_empty())
+        .map(|translation| translation.as_str())
+}
+
+fn fill_placeholders(message: &str, arguments: impl Iterator<Item = String>) -> String {
+    let mut filled = String::new();
+    let mut parts = message.split(PLACEHOLDER);
+    if let Some(first) = parts.next() {
+        filled.push_str(first);
+    }
+    let mut arguments = arguments;
+    for part in parts {
+        filled.push_str(&arguments.next().unwrap_or_default());
+        filled.push_str(part);
+    }
+    filled
+}
+
+fn match_placeholders<'a>(id: &str, message: &'a str) -> Option<Vec<&'a str>> {
+    let parts: Vec<&str> = id.split(PLACEHOLDER).collect();
+    let (first, rest) = parts.split_first()?;
+    let (last, middle) = rest.split_last()?;
+    let mut remaining = message.strip_prefix(first)?.strip_suffix(last)?;
+    let mut arguments = Vec::new();
+    for part in middle {
+        let (argument, after) = remaining.split_once(part)?;
+        arguments.push(argument);
+        remaining = after;
+    }
+    arguments.push(remaining);
+    Some(arguments)
+}
+
+// Only msgid and msgstr entries are read. The header entry with an empty
+// msgid is skipped.
+fn parse_catalog(content: &str) -> BTreeMap<String, String> {
+    let mut catalog = BTreeMap::new();
+    let mut id = String::new();
+    let mut translation = String::new();
+    let mut in_translation = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("msgid ") {
+            if !id.is_empty() {
+                catalog.insert(std::mem::take(&mut id), std::mem::take(&mut translation));
+            }
+            id = unquote(value);
+            translation.clear();
+            in_translation = false;
+        } else if let Some(value) = line.strip_prefix("msgstr ") {
+            translation = unquote(value);
+            in_translation = true;
+        } else if line.starts_with('"') {
+            match in_translation {
+                true => translation.push_str(&unquote(line)),
+                false => id.push_str(&unquote(line)),
+            }
+        }
+    }
+    if !id.is_empty() {
+        catalog.insert(id, translation);
+    }
+    catalog
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    let value = value.strip_prefix('"').unwrap_or(value);
+    let value = value.strip_suffix('"').unwrap_or(value);
+    let mut unquoted = String::new();
+    let mut characters = value.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            unquoted.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('n') => unquoted.push('\n'),
+            Some('t') => unquoted.push('\t'),
+            Some(escaped) => unquoted.push(escaped),
+            None => {}
+        }
+    }
+    unquoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_catalog() {
+        let catalog = parse_catalog(
+            "# comment\nmsgid \"\"\nmsgstr \"Language: fi\\n\"\n\n\
+             msgid \"Save\"\nmsgstr \"Tallenna\"\n\n\
+             msgid \"Say \\\"hi\\\"\"\nmsgstr \"\"\n\"Sano \"\n\"\\\"moi\\\"\"\n\n\
+             msgid \"Untranslated\"\nmsgstr \"\"\n",
+        );
+        assert_eq!(catalog.len(), 3);
+        assert_eq!(catalog["Save"], "Tallenna");
+        assert_eq!(catalog["Say \"hi\""], "Sano \"moi\"");
+        assert_eq!(catalog["Untranslated"], "");
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(
+            match_placeholders("{} of {} files", "3 of 10 files"),
+            Some(vec!["3", "10"])
+        );
+        assert_eq!(match_placeholders("{} of {} files", "3 files"), None);
+        assert_eq!(match_placeholders("No {}", "Not this"), None);
+        assert_eq!(
+            fill_placeholders("{} / {}", ["1", "2"].into_iter().map(String::from)),
+            "1 / 2"
+        );
+    }
+
+    // Both catalogs must list the same messages, every message has a Finnish
+    // translation and every translated literal in the views is in the
+    // catalogs.
+    #[test]
+    fn test_catalogs_are_complete() {
+        let english = Language::English.get_catalog();
+        let finnish = Language::Finnish.get_catalog();
+        assert!(english.keys().eq(finnish.keys()));
+        for (id, translation) in finnish {
+            assert!(!translation.is_empty(), "{} is not translated", id);
+            assert_eq!(
+                id.matches(PLACEHOLDER).count(),
+                translation.matches(PLACEHOLDER).count(),
+                "{}",
+                id
+            );
+        }
+        let sources = [
+            include_str!("layouts.rs"),
+            include_str!("settings.rs"),
+            include_str!("file_list.rs"),
+            include_str!("duplicates.rs"),
+            include_str!("keymap.rs"),
+            include_str!("app.rs"),
+        ];
+        for source in sources {
+            for call in ["tr(\"", "tr_format(\""] {
+                for (i, _) in source.match_indices(call) {
+                    let before = source[..i].chars().last().unwrap_or(' ');
+                    if before.is_alphanumeric() || before == '_' {
+                        continue;
+                    }
+                    let literal = &source[i + call.len()..];
+                    let Some(end) = literal.find('"') else {
+                        continue;
+                    };
+                    // Literals continued on the next line with a backslash.
+                    let mut parts = literal[..end].split("\\\n");
+                    let mut id = String::from(parts.next().unwrap_or_default());
+                    for part in parts {
+                        id.push_str(part.trim_start());
+                    }
+                    assert!(english.contains_key(&id), "{} is not in the catalog", id);
+                }
+            }
+        }
+    }
+
+    // Errors are shown in the window, so the message of every error built in
+    // the app or the core library is in the catalogs.
+    #[test]
+    fn test_error_messages_are_in_catalogs() {
+        let english = Language::English.get_catalog();
+        let sources = [
+            include_str!("app.rs"),
+            include_str!("app_util.rs"),
+            include_str!("duplicates.rs"),
+            include_str!("preview.rs"),
+            include_str!("settings.rs"),
+            include_str!("similar_images.rs"),
+            include_str!("thumbnails.rs"),
+            include_str!("watch_folder.rs"),
+            include_str!("../filerganizer-core/src/directory.rs"),
+            include_str!("../filerganizer-core/src/filesystem.rs"),
+            include_str!("../filerganizer-core/src/flatten.rs"),
+            include_str!("../filerganizer-core/src/metadata.rs"),
+            include_str!("../filerganizer-core/src/organize_files.rs"),
+            include_str!("../filerganizer-core/src/plan.rs"),
+            include_str!("../filerganizer-core/src/rules.rs"),
+            include_str!("../filerganizer-core/src/sanitize.rs"),
+            include_str!("../filerganizer-core/src/save_directory.rs"),
+            include_str!("../filerganizer-core/src/trash.rs"),
+            include_str!("../filerganizer-core/src/util.rs"),
+            include_str!("../filerganizer-core/src/vfs.rs"),
+        ];
+        for source in sources {
+            let source = source.split("#[cfg(test)]").next().unwrap_or_default();
+            for (i, _) in source.match_indices("ErrorKind::") {
+                let rest = source[i + "ErrorKind::".len()..]
+                    .trim_start_matches(|c: char| c.is_alphanumeric() || c == '_');
+                let Some(rest) = rest.strip_prefix(',') else {
+                    continue;
+                };
+                let rest = rest.trim_start();
+                let rest = rest.strip_prefix("format!(").unwrap_or(rest).trim_start();
+                let Some(literal) = rest.strip_prefix('"') else {
+                    continue;
+                };
+                let mut id = String::new();
+                let mut characters = literal.chars();
+                while let Some(c) = characters.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => id.extend(characters.next()),
+                        c => id.push(c),
+                    }
+                }
+                assert!(english.contains_key(&id), "{} is not in the catalog", id);
+            }
+        }
+    }
+
+    // The language is passed in instead of selected, since the selected
+    // language is shared by the tests that run in parallel.
+    #[test]
+    fn test_finnish_formatting() {
+        let date =
+            NaiveDateTime::parse_from_str("2025-03-07 09:05:30", "%Y-%m-%d %H:%M:%S").unwrap();
+        let finnish = Language::Finnish;
+        assert_eq!(format_date_time_in(finnish, &date), "7.3.2025 09.05.30");
+        assert_eq!(format_number_in(finnish, 1.5), "1,5");
+        assert_eq!(lookup(finnish, "Save"), Some("Tallenna"));
+        assert_eq!(
+            translate_in(
+                finnish,
+                "3 dropped files have the same name as a selected file."
+            ),
+            "3 pudotetulla tiedostolla on sama nimi kuin valitulla tiedostolla."
+        );
+        assert_eq!(
+            translate_in(finnish, "Not in the catalog"),
+            "Not in the catalog"
+        );
+        let english = Language::English;
+        assert_eq!(format_date_time_in(english, &date), "2025-03-07 09:05:30");
+        assert_eq!(format_number_in(english, 1.5), "1.5");
+        assert_eq!(lookup(english, "Save"), None);
+    }
+}
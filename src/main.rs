@@ -1,23 +1,47 @@
 mod app;
 mod app_util;
-mod directory;
-mod file;
-mod filesystem;
+mod duplicates;
+mod file_list;
+mod keymap;
 mod layouts;
-mod metadata;
-mod organize_files;
-mod save_directory;
+mod locale;
+mod path_completion;
+mod preview;
+mod settings;
+mod similar_images;
 mod subscription;
+mod thumbnails;
+mod watch_folder;
 
 use app::App;
 use iced::Theme;
 
-use crate::directory::system_dir;
+use filerganizer_core::directory::system_dir;
+use settings::Settings;
 const ICON: &str = "icon.png";
 
 fn main() -> iced::Result {
+    let arguments: Vec<String> = std::env::args().collect();
+    if arguments.iter().any(|argument| argument == "--watch") {
+        let result = watch_folder::parse_transfer_mode_argument(&arguments)
+            .and_then(watch_folder::run_headless);
+        if let Err(error) = result {
+            eprintln!("Watch folders stopped: {}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let settings = match system_dir::get_home_directory() {
+        Some(home_directory_path) => settings::read_settings(&home_directory_path),
+        None => Ok(Settings::default()),
+    };
+    let settings = settings.unwrap_or_default();
     let mut window_settings = iced::window::Settings::default();
-    window_settings.size = iced::Size::new(1000.0, 700.0);
+    window_settings.size = settings.get_window_size();
+    if let Some(position) = settings.get_window_position() {
+        window_settings.position = iced::window::Position::Specific(position);
+    }
+    window_settings.exit_on_close_request = false;
     let mut current_dir = system_dir::get_current_dir();
 
     if let Some(current_dir_path) = &mut current_dir {
@@ -36,9 +60,9 @@ fn main() -> iced::Result {
         .window(window_settings)
         .subscription(subscription::subscription)
         .theme(theme)
-        .run()
+        .run_with(App::new)
 }
 
-fn theme(_: &App) -> Theme {
-    Theme::Dark
+fn theme(app: &App) -> Theme {
+    app.get_theme().clone()
 }
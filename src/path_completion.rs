@@ -0,0 +1,261 @@
+use filerganizer_core::{FileSystem, RealFileSystem};
+use std::path::Path;
+
+// Directories matching the last component of the path input, shown under
+// the input. Repeated completions cycle through the candidates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathCompletion {
+    parent: String,
+    candidates: Vec<String>,
+    selected: Option<usize>,
+    // The input as the completion left it. Typing ends the completion.
+    completed_input: String,
+}
+
+impl PathCompletion {
+    pub fn new(parent: String, candidates: Vec<String>, completed_input: String) -> Self {
+        Self {
+            parent,
+            candidates,
+            selected: None,
+            completed_input,
+        }
+    }
+
+    pub fn get_candidates(&self) -> &Vec<String> {
+        &self.candidates
+    }
+
+    pub fn get_selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn get_completed_input(&self) -> &str {
+        &self.completed_input
+    }
+
+    pub fn get_candidate_path(&self, index: usize) -> Option<String> {
+        let candidate = self.candidates.get(index)?;
+        let mut candidate_path = format!("{}{}", self.parent, candidate);
+        candidate_path.push(std::path::MAIN_SEPARATOR);
+        Some(candidate_path)
+    }
+
+    // Returns the path of the next candidate, starting over after the last.
+    pub fn select_next(&mut self) -> Option<String> {
+        if self.candidates.is_empty() {
+            return None;
+        }
+        let next = match self.selected {
+            Some(selected) => (selected + 1) % self.candidates.len(),
+            None => 0,
+        };
+        self.selected = Some(next);
+        self.completed_input = self.get_candidate_path(next)?;
+        Some(self.completed_input.clone())
+    }
+}
+
+pub fn find_candidates(input: &str) -> std::io::Result<(String, Vec<String>)> {
+    find_candidates_with(&RealFileSystem, input)
+}
+
+// Splits the input to the directory it points into and the partial name
+// after it, and lists the subdirectories whose names start with the partial
+// name, ignoring case. Hidden directories are listed only when the partial
+// name starts with a dot.
+pub fn find_candidates_with(
+    file_system: &dyn FileSystem,
+    input: &str,
+) -> std::io::Result<(String, Vec<String>)> {
+    let (parent, partial_name) = match input.rfind(std::path::is_separator) {
+        Some(index) => input.split_at(index + 1),
+        None => ("", input),
+    };
+    if parent.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "No match found.",
+        ));
+    }
+    let partial_name = partial_name.to_lowercase();
+    let mut candidates = Vec::new();
+    for path in file_system.read_dir(Path::new(parent))? {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name.starts_with('.') && !partial_name.starts_with('.') {
+            continue;
+        }
+        if !name.to_lowercase().starts_with(&partial_name) {
+            continue;
+        }
+        if is_directory(file_system, &path) {
+            candidates.push(String::from(name));
+        }
+    }
+    candidates.sort_by_key(|candidate| candidate.to_lowercase());
+    Ok((String::from(parent), candidates))
+}
+
+// Links to directories are completed like directories.
+fn is_directory(file_system: &dyn FileSystem, path: &Path) -> bool {
+    match file_system.metadata(path) {
+        Ok(metadata) if metadata.is_dir() => true,
+        Ok(metadata) if metadata.is_symlink() => file_system.read_dir(path).is_ok(),
+        _ => false,
+    }
+}
+
+// Compares the candidates ignoring case and keeps the case of the first
+// candidate.
+pub fn longest_common_prefix(candidates: &[String]) -> String {
+    let Some((first, rest)) = candidates.split_first() else {
+        return String::new();
+    };
+    let mut prefix_length = first.chars().count();
+    for candidate in rest {
+        let common = first
+            .chars()
+            .zip(candidate.chars())
+            .take_while(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+            .count();
+        prefix_length = prefix_length.min(common);
+    }
+    first.chars().take(prefix_length).collect()
+}
+
+pub fn expand_path(input: &str, home_directory_path: &Path) -> String {
+    expand_path_with(input, home_directory_path, |name| std::env::var(name).ok())
+}
+
+// Expands ~ at the start of the path to the home directory and $NAME or
+// ${NAME} to environment variables. Variables that are not set are left as
+// they are.
+pub fn expand_path_with(
+    input: &str,
+    home_directory_path: &Path,
+    get_variable: impl Fn(&str) -> Option<String>,
+) -> String {
+    let mut expanded = String::new();
+    let mut rest = input;
+    if let Some(after_tilde) = input.strip_prefix('~') {
+        if after_tilde.is_empty() || after_tilde.starts_with(std::path::is_separator) {
+            expanded.push_str(&home_directory_path.display().to_string());
+            rest = after_tilde;
+        }
+    }
+    while let Some(index) = rest.find('$') {
+        expanded.push_str(&rest[..index]);
+        let after_dollar = &rest[index + 1..];
+        let (name, remaining) = match after_dollar.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", after_dollar),
+            },
+            None => {
+                let end = after_dollar
+                    .find(|character: char| !character.is_alphanumeric() && character != '_')
+                    .unwrap_or(after_dollar.len());
+                after_dollar.split_at(end)
+            }
+        };
+        let value = match name.is_empty() {
+            true => None,
+            false => get_variable(name),
+        };
+        match value {
+            Some(value) => expanded.push_str(&value),
+            None => expanded.push_str(&rest[index..rest.len() - remaining.len()]),
+        }
+        rest = remaining;
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filerganizer_core::MemoryFileSystem;
+
+    #[test]
+    fn test_find_candidates() {
+        let file_system = MemoryFileSystem::new();
+        for directory in [
+            "/home/Documents",
+            "/home/documents-old",
+            "/home/Downloads",
+            "/home/.config",
+            "/home/music",
+        ] {
+            file_system.create_dir_all(Path::new(directory));
+        }
+        file_system.write_file(Path::new("/home/doc.txt"), b"");
+        let (parent, candidates) =
+            find_candidates_with(&file_system, "/home/doc").expect("Failed to find candidates");
+        assert_eq!(parent, "/home/");
+        assert_eq!(candidates, vec!["Documents", "documents-old"]);
+        assert_eq!(longest_common_prefix(&candidates), "Documents");
+
+        let (_, candidates) =
+            find_candidates_with(&file_system, "/home/").expect("Failed to find candidates");
+        assert_eq!(
+            candidates,
+            vec!["Documents", "documents-old", "Downloads", "music"]
+        );
+        assert_eq!(longest_common_prefix(&candidates), "");
+        let (_, candidates) =
+            find_candidates_with(&file_system, "/home/.c").expect("Failed to find candidates");
+        assert_eq!(candidates, vec![".config"]);
+        assert!(find_candidates_with(&file_system, "/missing/a").is_err());
+        assert!(find_candidates_with(&file_system, "home").is_err());
+    }
+
+    #[test]
+    fn test_cycle_candidates() {
+        let mut completion = PathCompletion::new(
+            String::from("/home/"),
+            vec![String::from("a"), String::from("b")],
+            String::from("/home/"),
+        );
+        let separator = std::path::MAIN_SEPARATOR;
+        assert_eq!(
+            completion.select_next(),
+            Some(format!("/home/a{}", separator))
+        );
+        assert_eq!(
+            completion.select_next(),
+            Some(format!("/home/b{}", separator))
+        );
+        assert_eq!(
+            completion.select_next(),
+            Some(format!("/home/a{}", separator))
+        );
+        assert_eq!(completion.get_selected(), Some(0));
+        assert_eq!(
+            completion.get_completed_input(),
+            format!("/home/a{}", separator)
+        );
+    }
+
+    #[test]
+    fn test_expand_path() {
+        let home_directory_path = Path::new("/home/verneri");
+        let get_variable = |name: &str| match name {
+            "PHOTOS" => Some(String::from("/mnt/photos")),
+            "USER" => Some(String::from("verneri")),
+            _ => None,
+        };
+        let expand = |input| expand_path_with(input, home_directory_path, get_variable);
+        assert_eq!(expand("~"), "/home/verneri");
+        assert_eq!(expand("~/Pictures"), "/home/verneri/Pictures");
+        assert_eq!(expand("~other/Pictures"), "~other/Pictures");
+        assert_eq!(expand("$PHOTOS/2024"), "/mnt/photos/2024");
+        assert_eq!(expand("/home/${USER}_backup"), "/home/verneri_backup");
+        assert_eq!(expand("/tmp/$UNSET/a"), "/tmp/$UNSET/a");
+        assert_eq!(expand("/tmp/${UNSET}"), "/tmp/${UNSET}");
+        assert_eq!(expand("/tmp/a$"), "/tmp/a$");
+        assert_eq!(expand("/tmp/${open"), "/tmp/${open");
+    }
+}
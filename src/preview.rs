@@ -0,0 +1,242 @@
+use crate::similar_images;
+use chrono::{DateTime, Local};
+use filerganizer_core::metadata;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub const PREVIEW_TEXT_LINES: usize = 20;
+const PREVIEW_HEADER_SIZE: u64 = 16_384;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreviewContent {
+    Image,
+    Text(Vec<String>),
+    Unavailable,
+}
+
+// Everything the preview pane shows of a file, read on a background thread.
+// The hash is only computed when asked for, since it reads the whole file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileInspection {
+    path: PathBuf,
+    content: PreviewContent,
+    created: Option<DateTime<Local>>,
+    accessed: Option<DateTime<Local>>,
+    modified: Option<DateTime<Local>>,
+    size: u64,
+    permissions: String,
+    mime_type: &'static str,
+    exif_fields: Vec<(String, String)>,
+    hash: Option<String>,
+}
+
+impl FileInspection {
+    pub fn get_path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn get_content(&self) -> &PreviewContent {
+        &self.content
+    }
+
+    pub fn get_created(&self) -> Option<DateTime<Local>> {
+        self.created
+    }
+
+    pub fn get_accessed(&self) -> Option<DateTime<Local>> {
+        self.accessed
+    }
+
+    pub fn get_modified(&self) -> Option<DateTime<Local>> {
+        self.modified
+    }
+
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn get_permissions(&self) -> &str {
+        &self.permissions
+    }
+
+    pub fn get_mime_type(&self) -> &'static str {
+        self.mime_type
+    }
+
+    pub fn get_exif_fields(&self) -> &Vec<(String, String)> {
+        &self.exif_fields
+    }
+
+    pub fn get_hash(&self) -> Option<&str> {
+        self.hash.as_deref()
+    }
+
+    pub fn set_hash(&mut self, hash: String) {
+        self.hash = Some(hash);
+    }
+}
+
+pub fn inspect_file(path: &Path) -> std::io::Result<FileInspection> {
+    let file_metadata = std::fs::metadata(path)?;
+    let mut header = Vec::new();
+    std::fs::File::open(path)?
+        .take(PREVIEW_HEADER_SIZE)
+        .read_to_end(&mut header)?;
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mime_type = detect_mime_type(&header);
+    let content = if similar_images::is_image_file(&file_name) {
+        PreviewContent::Image
+    } else if mime_type == "text/plain" {
+        PreviewContent::Text(read_text_lines(&header, PREVIEW_TEXT_LINES))
+    } else {
+        PreviewContent::Unavailable
+    };
+    let exif_fields = match metadata::is_exif_file(&file_name) {
+        true => metadata::read_exif_fields(path),
+        false => Vec::new(),
+    };
+    Ok(FileInspection {
+        path: PathBuf::from(path),
+        content,
+        created: file_metadata.created().ok().map(DateTime::from),
+        accessed: file_metadata.accessed().ok().map(DateTime::from),
+        modified: file_metadata.modified().ok().map(DateTime::from),
+        size: file_metadata.len(),
+        permissions: format_permissions(&file_metadata.permissions()),
+        mime_type,
+        exif_fields,
+        hash: None,
+    })
+}
+
+#[cfg(unix)]
+fn format_permissions(permissions: &std::fs::Permissions) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = permissions.mode();
+    let mut formatted = String::new();
+    for (i, character) in "rwxrwxrwx".chars().enumerate() {
+        match mode & (1 << (8 - i)) {
+            0 => formatted.push('-'),
+            _ => formatted.push(character),
+        }
+    }
+    format!("{} ({:o})", formatted, mode & 0o777)
+}
+
+#[cfg(not(unix))]
+fn format_permissions(permissions: &std::fs::Permissions) -> String {
+    match permissions.readonly() {
+        true => String::from("Read only"),
+        false => String::from("Read and write"),
+    }
+}
+
+// Detects the type from the first bytes of the file, so a renamed file
+// shows what it really is. Content that is valid UTF-8 without null bytes
+// is text.
+pub fn detect_mime_type(header: &[u8]) -> &'static str {
+    const SIGNATURES: [(&[u8], &str); 14] = [
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"BM", "image/bmp"),
+        (b"II*\0", "image/tiff"),
+        (b"MM\0*", "image/tiff"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x1a\x45\xdf\xa3", "video/x-matroska"),
+        (b"ID3", "audio/mpeg"),
+        (b"fLaC", "audio/flac"),
+        (b"OggS", "audio/ogg"),
+    ];
+    for (signature, mime_type) in SIGNATURES {
+        if header.starts_with(signature) {
+            return mime_type;
+        }
+    }
+    if header.starts_with(b"RIFF") && header.len() >= 12 {
+        return match &header[8..12] {
+            b"WEBP" => "image/webp",
+            b"AVI " => "video/x-msvideo",
+            b"WAVE" => "audio/wav",
+            _ => "application/octet-stream",
+        };
+    }
+    if header.len() >= 12 && &header[4..8] == b"ftyp" {
+        return match &header[8..12] {
+            b"heic" | b"heix" | b"mif1" => "image/heic",
+            b"qt  " => "video/quicktime",
+            _ => "video/mp4",
+        };
+    }
+    if is_text(header) {
+        return "text/plain";
+    }
+    "application/octet-stream"
+}
+
+// The header can end in the middle of a character, which is still text.
+fn is_text(header: &[u8]) -> bool {
+    if header.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(header) {
+        Ok(_) => true,
+        Err(error) => error.error_len().is_none(),
+    }
+}
+
+fn read_text_lines(header: &[u8], line_count: usize) -> Vec<String> {
+    String::from_utf8_lossy(header)
+        .lines()
+        .take(line_count)
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_mime_type() {
+        assert_eq!(
+            detect_mime_type(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            "image/jpeg"
+        );
+        assert_eq!(detect_mime_type(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(detect_mime_type(b"\0\0\0\x18ftypmp42"), "video/mp4");
+        assert_eq!(detect_mime_type(b"%PDF-1.7"), "application/pdf");
+        assert_eq!(detect_mime_type("päivää\n".as_bytes()), "text/plain");
+        assert_eq!(detect_mime_type(&"ä".as_bytes()[..1]), "text/plain");
+        assert_eq!(
+            detect_mime_type(b"\x00\x01\x02"),
+            "application/octet-stream"
+        );
+    }
+
+    #[test]
+    fn test_inspect_text_file() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let path = test_directory.path().join("notes.log");
+        let content: String = (1..=30).map(|line| format!("line {}\n", line)).collect();
+        std::fs::write(&path, &content).expect("Failed to write test file");
+
+        let inspection = inspect_file(&path).expect("Failed to inspect file");
+        assert_eq!(inspection.get_mime_type(), "text/plain");
+        assert_eq!(inspection.get_size(), content.len() as u64);
+        assert_eq!(inspection.get_hash(), None);
+        assert!(inspection.get_modified().is_some());
+        assert!(inspection.get_exif_fields().is_empty());
+        let PreviewContent::Text(lines) = inspection.get_content() else {
+            panic!("Text file was not previewed as text");
+        };
+        assert_eq!(lines.len(), PREVIEW_TEXT_LINES);
+        assert_eq!(lines[0], "line 1");
+    }
+}
@@ -0,0 +1,479 @@
+use crate::app_util;
+use crate::keymap::{KeyAction, KeyBinding, Keymap, KEY_ACTIONS};
+use crate::layouts::{DirectoryView, DIRECTORY_VIEWS};
+use crate::locale::{tr, Language};
+use iced::theme::Palette;
+use filerganizer_core::util;
+use iced::{Color, Point, Size, Theme};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::process::Command;
+
+pub const SETTINGS_FILE_NAME: &str = ".filerganizer_settings";
+pub const DEFAULT_WINDOW_SIZE: Size = Size::new(1000.0, 700.0);
+const KEYMAP_PREFIX: &str = "keymap.";
+const THEME: &str = "theme";
+const WINDOW_SIZE: &str = "window_size";
+const WINDOW_POSITION: &str = "window_position";
+const LAST_PATH: &str = "last_path";
+const DIRECTORY_VIEW: &str = "directory_view";
+const LANGUAGE: &str = "language";
+const BOOKMARK: &str = "bookmark";
+const RECENT_PATH: &str = "recent_path";
+pub const MAX_RECENT_PATHS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThemeSetting {
+    System,
+    HighContrast,
+    Iced(Theme),
+}
+
+impl Default for ThemeSetting {
+    fn default() -> Self {
+        ThemeSetting::Iced(Theme::Dark)
+    }
+}
+
+impl std::fmt::Display for ThemeSetting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeSetting::System => f.write_str(tr("System")),
+            ThemeSetting::HighContrast => f.write_str(tr("High contrast")),
+            ThemeSetting::Iced(theme) => write!(f, "{}", theme),
+        }
+    }
+}
+
+impl ThemeSetting {
+    pub fn parse(input: &str) -> Option<Self> {
+        get_theme_settings()
+            .into_iter()
+            .find(|theme_setting| theme_setting.get_setting_name() == input)
+    }
+
+    // The setting name stays the same whatever language is selected.
+    pub fn get_setting_name(&self) -> String {
+        match self {
+            ThemeSetting::System => String::from("System"),
+            ThemeSetting::HighContrast => String::from("High contrast"),
+            ThemeSetting::Iced(theme) => theme.to_string(),
+        }
+    }
+
+    // Finding out the system theme starts a process, so this is only done
+    // at startup, when the setting changes and when the window gets focus.
+    pub fn get_theme(&self) -> Theme {
+        match self {
+            ThemeSetting::System if is_system_theme_dark() => Theme::Dark,
+            ThemeSetting::System => Theme::Light,
+            ThemeSetting::HighContrast => Theme::custom(
+                String::from("High contrast"),
+                Palette {
+                    background: Color::BLACK,
+                    text: Color::WHITE,
+                    primary: Color::from_rgb(1.0, 0.85, 0.0),
+                    success: Color::from_rgb(0.0, 1.0, 0.4),
+                    danger: Color::from_rgb(1.0, 0.35, 0.35),
+                },
+            ),
+            ThemeSetting::Iced(theme) => theme.clone(),
+        }
+    }
+}
+
+pub fn get_theme_settings() -> Vec<ThemeSetting> {
+    let mut theme_settings = vec![ThemeSetting::System, ThemeSetting::HighContrast];
+    theme_settings.extend(Theme::ALL.iter().cloned().map(ThemeSetting::Iced));
+    theme_settings
+}
+
+// Asks the desktop whether it prefers a dark look. Light is used when that
+// cannot be found out.
+fn is_system_theme_dark() -> bool {
+    let output = match std::env::consts::OS {
+        "macos" => Command::new("defaults")
+            .args(["read", "-g", "AppleInterfaceStyle"])
+            .output(),
+        "windows" => Command::new("reg")
+            .args([
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ])
+            .output(),
+        _ => Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+            .output(),
+    };
+    let Ok(output) = output else {
+        return false;
+    };
+    let output = String::from_utf8_lossy(&output.stdout);
+    match std::env::consts::OS {
+        "macos" => output.trim() == "Dark",
+        "windows" => output.contains("0x0"),
+        _ => output.contains("dark"),
+    }
+}
+
+// Settings are stored as setting=value lines in the home directory. Unknown
+// settings and values that cannot be parsed are skipped, so they keep their
+// defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    keymap: Keymap,
+    theme: ThemeSetting,
+    window_size: Size,
+    window_position: Option<Point>,
+    last_path: Option<PathBuf>,
+    directory_view: DirectoryView,
+    language: Language,
+    bookmarks: Vec<PathBuf>,
+    recent_paths: Vec<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            keymap: Keymap::default(),
+            theme: ThemeSetting::default(),
+            window_size: DEFAULT_WINDOW_SIZE,
+            window_position: None,
+            last_path: None,
+            directory_view: DirectoryView::List,
+            language: Language::detect(),
+            bookmarks: Vec::new(),
+            recent_paths: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn get_keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn get_theme(&self) -> &ThemeSetting {
+        &self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: ThemeSetting) {
+        self.theme = theme;
+    }
+
+    pub fn get_window_size(&self) -> Size {
+        self.window_size
+    }
+
+    pub fn set_window_size(&mut self, window_size: Size) {
+        self.window_size = window_size;
+    }
+
+    pub fn get_window_position(&self) -> Option<Point> {
+        self.window_position
+    }
+
+    pub fn set_window_position(&mut self, window_position: Point) {
+        self.window_position = Some(window_position);
+    }
+
+    pub fn get_last_path(&self) -> Option<&PathBuf> {
+        self.last_path.as_ref()
+    }
+
+    pub fn set_last_path(&mut self, last_path: PathBuf) {
+        self.last_path = Some(last_path);
+    }
+
+    pub fn get_directory_view(&self) -> DirectoryView {
+        self.directory_view.clone()
+    }
+
+    pub fn set_directory_view(&mut self, directory_view: DirectoryView) {
+        self.directory_view = directory_view;
+    }
+
+    pub fn get_language(&self) -> Language {
+        self.language
+    }
+
+    pub fn set_language(&mut self, language: Language) {
+        self.language = language;
+    }
+
+    pub fn get_bookmarks(&self) -> &Vec<PathBuf> {
+        &self.bookmarks
+    }
+
+    pub fn add_bookmark(&mut self, bookmark: PathBuf) {
+        if !self.bookmarks.contains(&bookmark) {
+            self.bookmarks.push(bookmark);
+        }
+    }
+
+    pub fn remove_bookmark(&mut self, index: usize) {
+        if index < self.bookmarks.len() {
+            self.bookmarks.remove(index);
+        }
+    }
+
+    pub fn get_recent_paths(&self) -> &Vec<PathBuf> {
+        &self.recent_paths
+    }
+
+    // The most recent path comes first and a path is listed only once.
+    pub fn add_recent_path(&mut self, recent_path: PathBuf) {
+        self.recent_paths.retain(|path| *path != recent_path);
+        self.recent_paths.insert(0, recent_path);
+        self.recent_paths.truncate(MAX_RECENT_PATHS);
+    }
+}
+
+fn get_settings_file_location(home_directory_path: &PathBuf) -> PathBuf {
+    let mut path_to_file = PathBuf::from(home_directory_path);
+    path_to_file.push(SETTINGS_FILE_NAME);
+    path_to_file
+}
+
+pub fn read_settings(home_directory_path: &PathBuf) -> std::io::Result<Settings> {
+    match std::fs::read_to_string(get_settings_file_location(home_directory_path)) {
+        Ok(content) => Ok(parse_settings(&content)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(Settings::default()),
+        Err(error) => Err(error),
+    }
+}
+
+pub fn write_settings(home_directory_path: &PathBuf, settings: &Settings) -> std::io::Result<()> {
+    app_util::write_file_atomically(
+        &get_settings_file_location(home_directory_path),
+        write_settings_to_string(settings).as_bytes(),
+    )
+}
+
+fn write_settings_to_string(settings: &Settings) -> String {
+    let mut file_content = String::new();
+    file_content.push_str(&format!(
+        "{}={}\n",
+        THEME,
+        settings.theme.get_setting_name()
+    ));
+    file_content.push_str(&format!(
+        "{}={}x{}\n",
+        WINDOW_SIZE, settings.window_size.width, settings.window_size.height
+    ));
+    if let Some(position) = settings.window_position {
+        file_content.push_str(&format!(
+            "{}={},{}\n",
+            WINDOW_POSITION, position.x, position.y
+        ));
+    }
+    // Paths that are not UTF-8 are left out, as they could not be read back.
+    if let Some(Ok(last_path)) = settings.last_path.as_ref().map(util::convert_path_to_str) {
+        file_content.push_str(&format!("{}={}\n", LAST_PATH, last_path));
+    }
+    file_content.push_str(&format!(
+        "{}={}\n",
+        DIRECTORY_VIEW,
+        settings.directory_view.get_setting_name()
+    ));
+    file_content.push_str(&format!("{}={}\n", LANGUAGE, settings.language.get_code()));
+    let paths = settings.bookmarks.iter().map(|path| (BOOKMARK, path));
+    let paths = paths.chain(settings.recent_paths.iter().map(|path| (RECENT_PATH, path)));
+    for (name, path) in paths {
+        if let Ok(path) = util::convert_path_to_str(path) {
+            file_content.push_str(&format!("{}={}\n", name, path));
+        }
+    }
+    for action in KEY_ACTIONS {
+        if let Some(binding) = settings.keymap.get_binding(action) {
+            file_content.push_str(&format!(
+                "{}{}={}\n",
+                KEYMAP_PREFIX,
+                action.get_setting_name(),
+                binding
+            ));
+        }
+    }
+    file_content
+}
+
+fn parse_settings(buffer: &str) -> Settings {
+    let mut settings = Settings::default();
+    for line in buffer.lines() {
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        if let Some(action_name) = name.trim().strip_prefix(KEYMAP_PREFIX) {
+            if let (Some(action), Some(binding)) =
+                (KeyAction::parse(action_name), KeyBinding::parse(value))
+            {
+                settings.keymap.set_binding(action, binding);
+            }
+            continue;
+        }
+        match name.trim() {
+            THEME => {
+                if let Some(theme) = ThemeSetting::parse(value) {
+                    settings.theme = theme;
+                }
+            }
+            WINDOW_SIZE => {
+                if let Some((width, height)) = parse_pair(value, 'x') {
+                    if width > 0.0 && height > 0.0 {
+                        settings.window_size = Size::new(width, height);
+                    }
+                }
+            }
+            WINDOW_POSITION => {
+                if let Some((x, y)) = parse_pair(value, ',') {
+                    settings.window_position = Some(Point::new(x, y));
+                }
+            }
+            LAST_PATH if !value.is_empty() => settings.last_path = Some(PathBuf::from(value)),
+            DIRECTORY_VIEW => {
+                if let Some(directory_view) = DIRECTORY_VIEWS
+                    .into_iter()
+                    .find(|directory_view| directory_view.get_setting_name() == value)
+                {
+                    settings.directory_view = directory_view;
+                }
+            }
+            LANGUAGE => {
+                if let Some(language) = Language::parse(value) {
+                    settings.language = language;
+                }
+            }
+            BOOKMARK if !value.is_empty() => settings.add_bookmark(PathBuf::from(value)),
+            RECENT_PATH if !value.is_empty() => {
+                let recent_path = PathBuf::from(value);
+                if !settings.recent_paths.contains(&recent_path)
+                    && settings.recent_paths.len() < MAX_RECENT_PATHS
+                {
+                    settings.recent_paths.push(recent_path);
+                }
+            }
+            _ => {}
+        }
+    }
+    settings
+}
+
+fn parse_pair(value: &str, separator: char) -> Option<(f32, f32)> {
+    let (first, second) = value.split_once(separator)?;
+    let first: f32 = first.trim().parse().ok()?;
+    let second: f32 = second.trim().parse().ok()?;
+    match first.is_finite() && second.is_finite() {
+        true => Some((first, second)),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_settings() {
+        let settings = parse_settings(
+            "keymap.commit=F5\nkeymap.select_all=Hyper+a\nkeymap.unknown=x\nnot a setting\n",
+        );
+        let keymap = settings.get_keymap();
+        assert_eq!(
+            keymap.get_binding(KeyAction::Commit),
+            KeyBinding::parse("F5").as_ref()
+        );
+        assert_eq!(
+            keymap.get_binding(KeyAction::SelectAll),
+            KeyBinding::parse("Ctrl+a").as_ref()
+        );
+        assert_eq!(
+            parse_settings(&write_settings_to_string(&settings)),
+            settings
+        );
+    }
+
+    #[test]
+    fn test_parse_preferences() {
+        let settings = parse_settings(
+            "theme=Tokyo Night\nwindow_size=1280x800\nwindow_position=-20,40.5\n\
+             last_path=/home/user/a=b\ndirectory_view=Grid\nlanguage=fi\n",
+        );
+        assert_eq!(settings.get_theme(), &ThemeSetting::Iced(Theme::TokyoNight));
+        assert_eq!(settings.get_window_size(), Size::new(1280.0, 800.0));
+        assert_eq!(
+            settings.get_window_position(),
+            Some(Point::new(-20.0, 40.5))
+        );
+        assert_eq!(
+            settings.get_last_path(),
+            Some(&PathBuf::from("/home/user/a=b"))
+        );
+        assert_eq!(settings.get_directory_view(), DirectoryView::Grid);
+        assert_eq!(settings.get_language(), Language::Finnish);
+        assert_eq!(
+            parse_settings(&write_settings_to_string(&settings)),
+            settings
+        );
+
+        let settings = parse_settings("theme=Sepia\nwindow_size=0x700\nwindow_position=a,1\n");
+        assert_eq!(settings, Settings::default());
+        assert_eq!(
+            ThemeSetting::parse("High contrast"),
+            Some(ThemeSetting::HighContrast)
+        );
+    }
+
+    #[test]
+    fn test_bookmarks_and_recent_paths() {
+        let mut settings = parse_settings(
+            "bookmark=/home/user/photos\nbookmark=/home/user/photos\nbookmark=\n\
+             recent_path=/tmp\nrecent_path=/home\n",
+        );
+        assert_eq!(
+            settings.get_bookmarks(),
+            &vec![PathBuf::from("/home/user/photos")]
+        );
+        settings.add_recent_path(PathBuf::from("/home"));
+        assert_eq!(
+            settings.get_recent_paths(),
+            &vec![PathBuf::from("/home"), PathBuf::from("/tmp")]
+        );
+        assert_eq!(
+            parse_settings(&write_settings_to_string(&settings)),
+            settings
+        );
+        for i in 0..MAX_RECENT_PATHS {
+            settings.add_recent_path(PathBuf::from(format!("/path/{}", i)));
+        }
+        assert_eq!(settings.get_recent_paths().len(), MAX_RECENT_PATHS);
+        assert_eq!(
+            settings.get_recent_paths()[0],
+            PathBuf::from(format!("/path/{}", MAX_RECENT_PATHS - 1))
+        );
+        settings.remove_bookmark(0);
+        assert!(settings.get_bookmarks().is_empty());
+    }
+
+    // Paths that are not UTF-8 are not saved instead of being saved as some
+    // other path.
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_paths_are_left_out() {
+        use std::os::unix::ffi::OsStringExt;
+        let path = PathBuf::from(std::ffi::OsString::from_vec(b"/home/\xff".to_vec()));
+        let mut settings = Settings::default();
+        settings.set_last_path(PathBuf::from(&path));
+        settings.add_bookmark(PathBuf::from(&path));
+        settings.add_recent_path(PathBuf::from(&path));
+        assert_eq!(
+            parse_settings(&write_settings_to_string(&settings)),
+            Settings::default()
+        );
+    }
+}
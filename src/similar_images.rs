@@ -0,0 +1,319 @@
+use crate::duplicates::{self, PartialFailure};
+use filerganizer_core::organize_files;
+use image::imageops::FilterType;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+pub const IMAGE_FILE_TYPES: [&str; 9] = [
+    "jpg", "jpeg", "png", "gif", "bmp", "webp", "tif", "tiff", "ico",
+];
+pub const DEFAULT_SIMILARITY_THRESHOLD: u32 = 10;
+pub const MAX_SIMILARITY_THRESHOLD: u32 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PerceptualHashType {
+    Average,
+    Difference,
+    Perceptual,
+}
+
+impl std::fmt::Display for PerceptualHashType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PerceptualHashType::Average => "aHash",
+            PerceptualHashType::Difference => "dHash",
+            PerceptualHashType::Perceptual => "pHash",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageInfo {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    size: u64,
+    hash: u64,
+}
+
+impl ImageInfo {
+    pub fn get_path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn get_resolution(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarImageGroup {
+    images: Vec<ImageInfo>,
+}
+
+impl SimilarImageGroup {
+    pub fn get_images(&self) -> &Vec<ImageInfo> {
+        &self.images
+    }
+
+    // The group is built around its highest resolution image, which is first.
+    pub fn get_highest_resolution(&self) -> Option<&ImageInfo> {
+        self.images.first()
+    }
+
+    pub fn remove_images(&mut self, paths: &[PathBuf]) {
+        self.images.retain(|image| !paths.contains(&image.path));
+    }
+}
+
+pub fn is_image_file(file_name: &str) -> bool {
+    match organize_files::get_file_type_from_file_name(file_name) {
+        Some(file_type) => IMAGE_FILE_TYPES.contains(&file_type.as_str()),
+        None => false,
+    }
+}
+
+pub fn hamming_distance(first: u64, second: u64) -> u32 {
+    (first ^ second).count_ones()
+}
+
+fn convert_image_error(error: image::ImageError) -> std::io::Error {
+    std::io::Error::new(ErrorKind::InvalidData, error.to_string())
+}
+
+pub fn compute_hash(image: &image::DynamicImage, hash_type: PerceptualHashType) -> u64 {
+    match hash_type {
+        PerceptualHashType::Average => average_hash(image),
+        PerceptualHashType::Difference => difference_hash(image),
+        PerceptualHashType::Perceptual => perceptual_hash(image),
+    }
+}
+
+fn grayscale_pixels(image: &image::DynamicImage, width: u32, height: u32) -> Vec<f64> {
+    let resized = image.resize_exact(width, height, FilterType::Triangle);
+    resized
+        .to_luma8()
+        .pixels()
+        .map(|pixel| pixel.0[0] as f64)
+        .collect()
+}
+
+fn bits_to_hash(bits: impl Iterator<Item = bool>) -> u64 {
+    let mut hash = 0;
+    for (i, bit) in bits.take(64).enumerate() {
+        if bit {
+            hash |= 1 << i;
+        }
+    }
+    hash
+}
+
+fn average_hash(image: &image::DynamicImage) -> u64 {
+    let pixels = grayscale_pixels(image, 8, 8);
+    let mean = pixels.iter().sum::<f64>() / pixels.len() as f64;
+    bits_to_hash(pixels.iter().map(|pixel| *pixel > mean))
+}
+
+fn difference_hash(image: &image::DynamicImage) -> u64 {
+    let pixels = grayscale_pixels(image, 9, 8);
+    let bits = (0..8).flat_map(|row| {
+        let pixels = &pixels;
+        (0..8).map(move |column| pixels[row * 9 + column] < pixels[row * 9 + column + 1])
+    });
+    bits_to_hash(bits)
+}
+
+// Takes the low frequencies of a 32x32 discrete cosine transform and compares
+// them to their median, which makes the hash robust to scaling and re-encoding.
+fn perceptual_hash(image: &image::DynamicImage) -> u64 {
+    const SIZE: usize = 32;
+    const LOW_FREQUENCIES: usize = 8;
+    let pixels = grayscale_pixels(image, SIZE as u32, SIZE as u32);
+    let mut cosines = [[0.0; SIZE]; SIZE];
+    for (frequency, row) in cosines.iter_mut().enumerate() {
+        for (position, cosine) in row.iter_mut().enumerate() {
+            *cosine = (std::f64::consts::PI / SIZE as f64
+                * (position as f64 + 0.5)
+                * frequency as f64)
+                .cos();
+        }
+    }
+    let mut coefficients = Vec::with_capacity(LOW_FREQUENCIES * LOW_FREQUENCIES);
+    for u in 0..LOW_FREQUENCIES {
+        for v in 0..LOW_FREQUENCIES {
+            let mut sum = 0.0;
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    sum += pixels[y * SIZE + x] * cosines[u][y] * cosines[v][x];
+                }
+            }
+            coefficients.push(sum);
+        }
+    }
+    let mut sorted: Vec<f64> = coefficients[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+    bits_to_hash(coefficients.iter().map(|coefficient| *coefficient > median))
+}
+
+pub fn read_image_info(path: &Path, hash_type: PerceptualHashType) -> std::io::Result<ImageInfo> {
+    let image = image::open(path).map_err(convert_image_error)?;
+    let size = std::fs::metadata(path)?.len();
+    Ok(ImageInfo {
+        path: PathBuf::from(path),
+        width: image.width(),
+        height: image.height(),
+        size,
+        hash: compute_hash(&image, hash_type),
+    })
+}
+
+// Every image is compared to the representative of the group only, so a chain
+// of images that each look like the next one is not grouped together. The
+// highest resolution image that is left represents the next group. Larger
+// file size breaks ties since it is usually the less compressed copy.
+pub fn group_similar_images(mut images: Vec<ImageInfo>, threshold: u32) -> Vec<SimilarImageGroup> {
+    images.sort_by_key(|image| std::cmp::Reverse((image.get_resolution(), image.size)));
+    let mut groups = Vec::new();
+    while !images.is_empty() {
+        let representative = images.remove(0);
+        let (similar, rest): (Vec<ImageInfo>, Vec<ImageInfo>) = images
+            .into_iter()
+            .partition(|image| hamming_distance(representative.hash, image.hash) <= threshold);
+        images = rest;
+        if !similar.is_empty() {
+            let mut group_images = vec![representative];
+            group_images.extend(similar);
+            groups.push(SimilarImageGroup {
+                images: group_images,
+            });
+        }
+    }
+    groups
+}
+
+// Files that cannot be decoded are left out instead of failing the whole pass.
+pub fn find_similar_images(
+    paths: &[PathBuf],
+    hash_type: PerceptualHashType,
+    threshold: u32,
+) -> Vec<SimilarImageGroup> {
+    let images: Vec<ImageInfo> = paths
+        .iter()
+        .filter(|path| match path.file_name().and_then(|name| name.to_str()) {
+            Some(file_name) => is_image_file(file_name),
+            None => false,
+        })
+        .filter_map(|path| read_image_info(path, hash_type).ok())
+        .collect();
+    group_similar_images(images, threshold)
+}
+
+// Moves every image except the highest resolution copy to quarantine and
+// returns the paths that were moved.
+pub fn keep_highest_resolution(
+    group: &SimilarImageGroup,
+    quarantine_directory: &Path,
+) -> Result<Vec<PathBuf>, PartialFailure> {
+    let mut moved = Vec::new();
+    for image in group.images.iter().skip(1) {
+        if let Err(error) = duplicates::move_to_quarantine(&image.path, quarantine_directory) {
+            return Err(PartialFailure {
+                processed: moved,
+                error,
+            });
+        }
+        moved.push(PathBuf::from(&image.path));
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_gradient_image(width: u32, height: u32, inverted: bool) -> image::DynamicImage {
+        let buffer = image::RgbImage::from_fn(width, height, |x, y| {
+            let mut value = ((x * 255 / width + y * 128 / height) % 256) as u8;
+            if x > width / 2 && y < height / 3 {
+                value = 255 - value / 4;
+            }
+            if inverted {
+                value = 255 - value;
+            }
+            image::Rgb([value, value / 2, 255 - value])
+        });
+        image::DynamicImage::ImageRgb8(buffer)
+    }
+
+    #[test]
+    fn test_resized_copies_are_similar() {
+        let original = create_gradient_image(640, 480, false);
+        let resized = original.resize_exact(160, 120, FilterType::Nearest);
+        let different = create_gradient_image(640, 480, true);
+        for hash_type in [
+            PerceptualHashType::Average,
+            PerceptualHashType::Difference,
+            PerceptualHashType::Perceptual,
+        ] {
+            let original_hash = compute_hash(&original, hash_type);
+            let resized_hash = compute_hash(&resized, hash_type);
+            let different_hash = compute_hash(&different, hash_type);
+            assert!(hamming_distance(original_hash, resized_hash) <= DEFAULT_SIMILARITY_THRESHOLD);
+            assert!(hamming_distance(original_hash, different_hash) > DEFAULT_SIMILARITY_THRESHOLD);
+        }
+    }
+
+    #[test]
+    fn test_group_similar_images() {
+        let create_info = |name: &str, width: u32, hash: u64| ImageInfo {
+            path: PathBuf::from(name),
+            width,
+            height: width,
+            size: 100,
+            hash,
+        };
+        // d.jpg is close to a.jpg but not to b.jpg, which the group is built around.
+        let groups = group_similar_images(
+            vec![
+                create_info("a.jpg", 100, 0b1111),
+                create_info("b.jpg", 400, 0b1110),
+                create_info("c.jpg", 200, u64::MAX),
+                create_info("d.jpg", 50, 0b0111),
+            ],
+            1,
+        );
+        assert_eq!(groups.len(), 1);
+        let paths: Vec<&PathBuf> = groups[0]
+            .get_images()
+            .iter()
+            .map(|image| image.get_path())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![&PathBuf::from("b.jpg"), &PathBuf::from("a.jpg")]
+        );
+        let highest = groups[0].get_highest_resolution().expect("Group was empty");
+        assert_eq!(highest.get_path(), &PathBuf::from("b.jpg"));
+    }
+
+    #[test]
+    fn test_is_image_file() {
+        assert!(is_image_file("photo.JPG"));
+        assert!(is_image_file("scan.tiff"));
+        assert!(!is_image_file("notes.txt"));
+        assert!(!is_image_file("jpg"));
+    }
+}
@@ -1,21 +1,39 @@
 use crate::app::Message;
 use crate::app::App;
-use iced::Subscription;
-use iced::keyboard::{on_key_press, Key, Modifiers, key};
+use crate::watch_folder;
+use iced::{event, mouse, window, Event, Subscription};
+use iced::keyboard::{on_key_press, Key, Modifiers};
 
-fn key_press(key: Key, _: Modifiers) -> Option<Message> {
+// Keys are matched against the keymap in App, as the keymap can be changed.
+fn key_press(key: Key, modifiers: Modifiers) -> Option<Message> {
    match key {
-        Key::Named(named) => {
-            match named {
-                key::Named::Tab => Some(Message::TabKeyPressed), 
-                _ => None
-            }
-        },
-        _ => None
+        Key::Unidentified => None,
+        _ => Some(Message::KeyPressed(key, modifiers))
    } 
 }
 
-pub fn subscription(_: &App) -> Subscription<Message> {
-   on_key_press(key_press) 
+// Mouse releases are listened to even when a widget handles them, as
+// dragging ends on top of buttons.
+fn handle_event(event: Event, _: event::Status, _: window::Id) -> Option<Message> {
+   match event {
+      Event::Window(window::Event::FileDropped(path)) => Some(Message::FileDropped(path)),
+      Event::Window(window::Event::Resized(size)) => Some(Message::WindowResized(size)),
+      Event::Window(window::Event::Moved(position)) => Some(Message::WindowMoved(position)),
+      Event::Window(window::Event::Focused) => Some(Message::WindowFocused),
+      // Settings are saved before the window closes
+      Event::Window(window::Event::CloseRequested) => Some(Message::Exit),
+      Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+         Some(Message::MouseReleased)
+      }
+      _ => None,
+   }
+}
+
+pub fn subscription(app: &App) -> Subscription<Message> {
+   let mut subscriptions = vec![on_key_press(key_press), event::listen_with(handle_event)];
+   if app.is_watching() {
+       subscriptions.push(iced::time::every(watch_folder::POLL_INTERVAL).map(|_| Message::WatchTick));
+   }
+   Subscription::batch(subscriptions)
 }
 
@@ -0,0 +1,153 @@
+use crate::similar_images;
+use filerganizer_core::organize_files;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const THUMBNAIL_CACHE_DIRECTORY_NAME: &str = ".filerganizer_thumbnails";
+pub const THUMBNAIL_SIZE: u32 = 128;
+pub const VIDEO_FILE_TYPES: [&str; 7] = ["mp4", "mov", "mkv", "avi", "webm", "m4v", "wmv"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThumbnailState {
+    Loading,
+    Ready(PathBuf),
+    Failed,
+}
+
+pub fn is_video_file(file_name: &str) -> bool {
+    match organize_files::get_file_type_from_file_name(file_name) {
+        Some(file_type) => VIDEO_FILE_TYPES.contains(&file_type.as_str()),
+        None => false,
+    }
+}
+
+pub fn has_thumbnail(file_name: &str) -> bool {
+    similar_images::is_image_file(file_name) || is_video_file(file_name)
+}
+
+pub fn get_cache_directory(home_directory_path: &Path) -> PathBuf {
+    home_directory_path.join(THUMBNAIL_CACHE_DIRECTORY_NAME)
+}
+
+// A file that is modified gets a new key, so stale thumbnails are never
+// shown. Old entries are left in the cache.
+pub fn get_cache_key(path: &Path, modified: SystemTime) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(path.as_os_str().as_encoded_bytes());
+    let modified = modified
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos());
+    hasher.update(&modified.to_le_bytes());
+    format!("{}.png", &hasher.finalize().to_hex()[..32])
+}
+
+// Returns the cached thumbnail of path, creating it first if needed. The
+// thumbnail is written to a temporary file that is renamed into place, so
+// an interrupted write never leaves a broken thumbnail in the cache.
+pub fn create_thumbnail(cache_directory: &Path, path: &Path) -> std::io::Result<PathBuf> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    let thumbnail_path = cache_directory.join(get_cache_key(path, modified));
+    if thumbnail_path.exists() {
+        return Ok(thumbnail_path);
+    }
+    std::fs::create_dir_all(cache_directory)?;
+    let file_name = path
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .unwrap_or_default();
+    let temporary_path = thumbnail_path.with_extension(format!("{}.png", std::process::id()));
+    let result = match is_video_file(file_name) {
+        true => create_video_thumbnail(path, &temporary_path),
+        false => create_image_thumbnail(path, &temporary_path),
+    }
+    .and_then(|()| std::fs::rename(&temporary_path, &thumbnail_path));
+    if let Err(error) = result {
+        let _ = std::fs::remove_file(&temporary_path);
+        return Err(error);
+    }
+    Ok(thumbnail_path)
+}
+
+fn create_image_thumbnail(path: &Path, thumbnail_path: &Path) -> std::io::Result<()> {
+    let image = image::open(path)
+        .map_err(|error| std::io::Error::new(ErrorKind::InvalidData, error.to_string()))?;
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .save(thumbnail_path)
+        .map_err(|error| std::io::Error::other(error.to_string()))
+}
+
+// Videos cannot be decoded here, so a frame is taken with ffmpeg when it is
+// installed. ffmpeg is kept from reading the terminal and writing to it.
+fn create_video_thumbnail(path: &Path, thumbnail_path: &Path) -> std::io::Result<()> {
+    let scale = format!(
+        "scale={0}:{0}:force_original_aspect_ratio=decrease",
+        THUMBNAIL_SIZE
+    );
+    let status = Command::new("ffmpeg")
+        .args(["-nostdin", "-loglevel", "error", "-y", "-ss", "1", "-i"])
+        .arg(path)
+        .args(["-frames:v", "1", "-vf", &scale, "-f", "image2"])
+        .arg(thumbnail_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if !status.success() || !thumbnail_path.exists() {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "Could not read a frame from video.",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_cache_key_changes_with_modified_time() {
+        let path = Path::new("/photos/a.jpg");
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(get_cache_key(path, modified), get_cache_key(path, modified));
+        assert_ne!(
+            get_cache_key(path, modified),
+            get_cache_key(path, modified + Duration::from_secs(1))
+        );
+        assert_ne!(
+            get_cache_key(path, modified),
+            get_cache_key(Path::new("/photos/b.jpg"), modified)
+        );
+        assert!(has_thumbnail("clip.MP4"));
+        assert!(!has_thumbnail("notes.txt"));
+    }
+
+    #[test]
+    fn test_create_thumbnail() {
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let directory = test_directory.path();
+        let path = directory.join("wide.png");
+        image::RgbImage::from_pixel(512, 256, image::Rgb([10, 200, 30]))
+            .save(&path)
+            .expect("Failed to write test image");
+        let cache_directory = directory.join("cache");
+
+        let thumbnail_path =
+            create_thumbnail(&cache_directory, &path).expect("Failed to create thumbnail");
+        let thumbnail = image::open(&thumbnail_path).expect("Failed to open thumbnail");
+        assert_eq!((thumbnail.width(), thumbnail.height()), (128, 64));
+        assert_eq!(
+            create_thumbnail(&cache_directory, &path).expect("Failed to read cache"),
+            thumbnail_path
+        );
+
+        std::fs::write(directory.join("broken.jpg"), b"not an image").unwrap();
+        assert!(create_thumbnail(&cache_directory, &directory.join("broken.jpg")).is_err());
+        // Failed writes leave no temporary files behind.
+        assert_eq!(std::fs::read_dir(&cache_directory).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,646 @@
+use crate::app_util;
+use chrono::Local;
+use filerganizer_core::directory::{self, system_dir, Directory};
+use filerganizer_core::file::File;
+use filerganizer_core::util;
+use filerganizer_core::{Rules, TransferMode};
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsString;
+use std::io::{ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+// Watch folders are stored as setting=value lines, one setting per line, so
+// paths can hold any character but a line break. Each inbox line starts a
+// new watch folder.
+const INBOX: &str = "inbox";
+const ORGANIZED_DIRECTORY: &str = "organized_directory";
+const PAUSED: &str = "paused";
+const SETTLE_SECONDS: &str = "settle_seconds";
+const IGNORE_PATTERN: &str = "ignore_pattern";
+
+pub const WATCH_FILE_NAME: &str = ".watch_folders";
+pub const WATCH_LOG_FILE_NAME: &str = ".watch_folders.log";
+pub const DEFAULT_SETTLE_SECONDS: u64 = 5;
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+pub const DEFAULT_IGNORE_PATTERNS: [&str; 5] = ["*.part", "*.crdownload", "*.tmp", ".*", "~*"];
+pub const MAX_LOG_ENTRIES: usize = 100;
+pub const TRANSFER_MODE_ARGUMENT: &str = "--transfer-mode";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchFolder {
+    inbox: PathBuf,
+    organized_directory: PathBuf,
+    paused: bool,
+    settle_seconds: u64,
+    ignore_patterns: Vec<String>,
+}
+
+impl WatchFolder {
+    pub fn new(
+        inbox: PathBuf,
+        organized_directory: PathBuf,
+        ignore_patterns: Vec<String>,
+    ) -> Self {
+        Self {
+            inbox,
+            organized_directory,
+            paused: false,
+            settle_seconds: DEFAULT_SETTLE_SECONDS,
+            ignore_patterns,
+        }
+    }
+
+    pub fn get_inbox(&self) -> &PathBuf {
+        &self.inbox
+    }
+
+    pub fn get_organized_directory(&self) -> &PathBuf {
+        &self.organized_directory
+    }
+
+    pub fn get_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn get_ignore_patterns(&self) -> &Vec<String> {
+        &self.ignore_patterns
+    }
+
+    pub fn is_ignored(&self, file_name: &str) -> bool {
+        self.ignore_patterns
+            .iter()
+            .any(|pattern| app_util::matches_wildcard(pattern, file_name))
+    }
+}
+
+pub fn parse_ignore_patterns(input: &str) -> Vec<String> {
+    let patterns: Vec<String> = input
+        .split([',', ';'])
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| !pattern.is_empty())
+        .map(String::from)
+        .collect();
+    if patterns.is_empty() {
+        return DEFAULT_IGNORE_PATTERNS.iter().map(|p| String::from(*p)).collect();
+    }
+    patterns
+}
+
+#[derive(Debug, Clone)]
+struct PendingFile {
+    size: u64,
+    modified: Option<SystemTime>,
+    last_change: Instant,
+    handled: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct WatchState {
+    pending: BTreeMap<PathBuf, PendingFile>,
+    last_errors: BTreeMap<PathBuf, String>,
+}
+
+impl WatchState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns true once when the file has kept the same size and modification time
+    // for at least settle time. Files still being written keep resetting the timer,
+    // and a file that failed to move is only retried after it changes.
+    fn observe(
+        &mut self,
+        path: &PathBuf,
+        size: u64,
+        modified: Option<SystemTime>,
+        now: Instant,
+        settle_time: Duration,
+    ) -> bool {
+        match self.pending.get_mut(path) {
+            Some(pending) => {
+                if pending.size != size || pending.modified != modified {
+                    pending.size = size;
+                    pending.modified = modified;
+                    pending.last_change = now;
+                    pending.handled = false;
+                    return false;
+                }
+                if pending.handled || now.duration_since(pending.last_change) < settle_time {
+                    return false;
+                }
+                pending.handled = true;
+                true
+            }
+            None => {
+                self.pending.insert(
+                    PathBuf::from(path),
+                    PendingFile {
+                        size,
+                        modified,
+                        last_change: now,
+                        handled: false,
+                    },
+                );
+                false
+            }
+        }
+    }
+
+    pub fn collect_settled_files(
+        &mut self,
+        watch_folder: &WatchFolder,
+        now: Instant,
+    ) -> std::io::Result<BTreeMap<OsString, File>> {
+        let mut inbox = Directory::new(None);
+        Directory::new(None).read_path(&watch_folder.inbox, &mut inbox)?;
+        let settle_time = Duration::from_secs(watch_folder.settle_seconds);
+        let mut settled_files = BTreeMap::new();
+        let mut present = HashSet::new();
+        if let Some(files) = inbox.get_mut_files().take() {
+            for (key, file) in files {
+                // Names that are not UTF-8 are still routed, so one of them
+                // does not hold back the rest of the inbox.
+                if watch_folder.is_ignored(&key.to_string_lossy()) {
+                    continue;
+                }
+                let mut path_to_file = PathBuf::from(&watch_folder.inbox);
+                path_to_file.push(&key);
+                // Files removed after the inbox was listed are no longer there to route.
+                let metadata = match std::fs::metadata(&path_to_file) {
+                    Ok(metadata) => metadata,
+                    Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                    Err(error) => return Err(error),
+                };
+                if self.observe(
+                    &path_to_file,
+                    metadata.len(),
+                    metadata.modified().ok(),
+                    now,
+                    settle_time,
+                ) {
+                    settled_files.insert(key, file);
+                }
+                present.insert(path_to_file);
+            }
+        }
+        self.pending.retain(|path, _| {
+            path.parent() != Some(watch_folder.inbox.as_path()) || present.contains(path)
+        });
+        Ok(settled_files)
+    }
+
+    fn is_new_error(&mut self, inbox: &PathBuf, error: &str) -> bool {
+        if self.last_errors.get(inbox).map(|e| e.as_str()) == Some(error) {
+            return false;
+        }
+        self.last_errors.insert(PathBuf::from(inbox), String::from(error));
+        true
+    }
+}
+
+pub fn poll_watch_folders(
+    home_directory_path: &PathBuf,
+    watch_folders: &[WatchFolder],
+    state: &mut WatchState,
+    transfer_mode: TransferMode,
+) -> Vec<String> {
+    let mut entries = Vec::new();
+    for watch_folder in watch_folders {
+        if watch_folder.paused {
+            continue;
+        }
+        match state.collect_settled_files(watch_folder, Instant::now()) {
+            Ok(files) => {
+                state.last_errors.remove(&watch_folder.inbox);
+                if !files.is_empty() {
+                    entries.extend(organize_settled_files(
+                        home_directory_path,
+                        watch_folder,
+                        files,
+                        transfer_mode,
+                    ));
+                }
+            }
+            Err(error) => {
+                if state.is_new_error(&watch_folder.inbox, &error.to_string()) {
+                    entries.push(format_log_entry(&format!(
+                        "{}: {}",
+                        watch_folder.inbox.display(),
+                        error
+                    )));
+                }
+            }
+        }
+    }
+    if let Err(error) = append_to_activity_log(home_directory_path, &entries) {
+        entries.push(format_log_entry(&format!(
+            "Could not write activity log: {}",
+            error
+        )));
+    }
+    entries
+}
+
+pub fn organize_settled_files(
+    home_directory_path: &PathBuf,
+    watch_folder: &WatchFolder,
+    files: BTreeMap<OsString, File>,
+    transfer_mode: TransferMode,
+) -> Vec<String> {
+    let mut entries = Vec::new();
+    let rules =
+        match Rules::read_from_save_file(home_directory_path, &watch_folder.organized_directory) {
+            Ok(rules) => rules,
+            Err(error) => {
+                entries.push(format_log_entry(&format!(
+                    "{}: {}",
+                    watch_folder.organized_directory.display(),
+                    error
+                )));
+                return entries;
+            }
+        };
+    let mut organized_directory =
+        match directory::read_directory_recursive(&watch_folder.organized_directory) {
+            Ok(organized_directory) => organized_directory,
+            Err(error) => {
+                entries.push(format_log_entry(&format!(
+                    "{}: {}",
+                    watch_folder.organized_directory.display(),
+                    error
+                )));
+                return entries;
+            }
+        };
+    for (key, file) in files {
+        let mut origin_path = PathBuf::from(&watch_folder.inbox);
+        origin_path.push(&key);
+        match route_file(
+            watch_folder,
+            &rules,
+            &mut organized_directory,
+            key,
+            file,
+            transfer_mode,
+        ) {
+            Ok(destination_path) => entries.push(format_log_entry(&format!(
+                "{} {} -> {}",
+                transfer_mode,
+                origin_path.display(),
+                destination_path.display()
+            ))),
+            Err(error) => entries.push(format_log_entry(&format!(
+                "Failed to {} {}: {}",
+                transfer_mode.get_argument_name(),
+                origin_path.display(),
+                error
+            ))),
+        }
+    }
+    entries
+}
+
+// Files are routed one at a time so a single name clash does not hold back
+// the rest of the inbox.
+fn route_file(
+    watch_folder: &WatchFolder,
+    rules: &Rules,
+    organized_directory: &mut Directory,
+    key: OsString,
+    file: File,
+    transfer_mode: TransferMode,
+) -> std::io::Result<PathBuf> {
+    let mut files_selected = BTreeMap::new();
+    files_selected.insert(key, file);
+    let plan = filerganizer_core::plan(
+        files_selected,
+        rules,
+        &watch_folder.organized_directory,
+        organized_directory,
+    )?;
+    filerganizer_core::apply(&plan, transfer_mode)?;
+    match plan.get_moves().first() {
+        Some(planned_move) => Ok(PathBuf::from(planned_move.get_destination())),
+        None => Err(std::io::Error::new(
+            ErrorKind::NotFound,
+            "Destination path not found.",
+        )),
+    }
+}
+
+fn format_log_entry(message: &str) -> String {
+    format!("{} {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message)
+}
+
+fn get_watch_file_location(home_directory_path: &PathBuf, file_name: &str) -> PathBuf {
+    let mut path_to_file = PathBuf::from(home_directory_path);
+    path_to_file.push(file_name);
+    path_to_file
+}
+
+pub fn read_watch_folders(home_directory_path: &PathBuf) -> std::io::Result<Vec<WatchFolder>> {
+    match std::fs::File::open(get_watch_file_location(
+        home_directory_path,
+        WATCH_FILE_NAME,
+    )) {
+        Ok(mut file) => {
+            let mut buffer = String::new();
+            file.read_to_string(&mut buffer)?;
+            Ok(parse_watch_folders(&buffer))
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(error),
+    }
+}
+
+pub fn write_watch_folders(
+    home_directory_path: &PathBuf,
+    watch_folders: &[WatchFolder],
+) -> std::io::Result<()> {
+    let mut file_content = String::new();
+    for watch_folder in watch_folders {
+        write_watch_folder_to_string(&mut file_content, watch_folder)?;
+    }
+    app_util::write_file_atomically(
+        &get_watch_file_location(home_directory_path, WATCH_FILE_NAME),
+        file_content.as_bytes(),
+    )
+}
+
+fn write_watch_folder_to_string(
+    file_content: &mut String,
+    watch_folder: &WatchFolder,
+) -> std::io::Result<()> {
+    file_content.push_str(&format!(
+        "{}={}\n",
+        INBOX,
+        util::convert_path_to_str(&watch_folder.inbox)?
+    ));
+    file_content.push_str(&format!(
+        "{}={}\n",
+        ORGANIZED_DIRECTORY,
+        util::convert_path_to_str(&watch_folder.organized_directory)?
+    ));
+    file_content.push_str(&format!(
+        "{}={}\n",
+        PAUSED,
+        if watch_folder.paused { "1" } else { "0" }
+    ));
+    file_content.push_str(&format!(
+        "{}={}\n",
+        SETTLE_SECONDS, watch_folder.settle_seconds
+    ));
+    for pattern in &watch_folder.ignore_patterns {
+        file_content.push_str(&format!("{}={}\n", IGNORE_PATTERN, pattern));
+    }
+    file_content.push('\n');
+    Ok(())
+}
+
+// Watch folders without an organized directory are skipped.
+fn parse_watch_folders(buffer: &str) -> Vec<WatchFolder> {
+    let mut watch_folders = Vec::new();
+    let mut current: Option<WatchFolder> = None;
+    for line in buffer.lines() {
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        if name.trim() == INBOX {
+            watch_folders.extend(current.take());
+            current = Some(WatchFolder {
+                inbox: PathBuf::from(value),
+                organized_directory: PathBuf::new(),
+                paused: false,
+                settle_seconds: DEFAULT_SETTLE_SECONDS,
+                ignore_patterns: Vec::new(),
+            });
+            continue;
+        }
+        let Some(watch_folder) = current.as_mut() else {
+            continue;
+        };
+        match name.trim() {
+            ORGANIZED_DIRECTORY => watch_folder.organized_directory = PathBuf::from(value),
+            PAUSED => watch_folder.paused = value.trim() == "1",
+            SETTLE_SECONDS => {
+                watch_folder.settle_seconds =
+                    value.trim().parse().unwrap_or(DEFAULT_SETTLE_SECONDS)
+            }
+            IGNORE_PATTERN => watch_folder.ignore_patterns.push(String::from(value)),
+            _ => {}
+        }
+    }
+    watch_folders.extend(current);
+    watch_folders.retain(|watch_folder| !watch_folder.organized_directory.as_os_str().is_empty());
+    watch_folders
+}
+
+pub fn append_to_activity_log(
+    home_directory_path: &PathBuf,
+    entries: &[String],
+) -> std::io::Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut file = std::fs::File::options()
+        .create(true)
+        .append(true)
+        .open(get_watch_file_location(
+            home_directory_path,
+            WATCH_LOG_FILE_NAME,
+        ))?;
+    for entry in entries {
+        file.write_all(entry.as_bytes())?;
+        file.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+pub fn read_activity_log(home_directory_path: &PathBuf) -> std::io::Result<Vec<String>> {
+    match std::fs::read_to_string(get_watch_file_location(
+        home_directory_path,
+        WATCH_LOG_FILE_NAME,
+    )) {
+        Ok(content) => {
+            let lines: Vec<String> = content.lines().map(String::from).collect();
+            let skip = lines.len().saturating_sub(MAX_LOG_ENTRIES);
+            Ok(lines.into_iter().skip(skip).collect())
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(error),
+    }
+}
+
+// Reads the transfer mode from "--transfer-mode copy" or
+// "--transfer-mode=copy". Files are moved when it is not given.
+pub fn parse_transfer_mode_argument(arguments: &[String]) -> std::io::Result<TransferMode> {
+    let mut value = None;
+    for (i, argument) in arguments.iter().enumerate() {
+        if argument == TRANSFER_MODE_ARGUMENT {
+            value = Some(arguments.get(i + 1).map(String::as_str).unwrap_or_default());
+        } else if let Some(rest) = argument.strip_prefix(TRANSFER_MODE_ARGUMENT) {
+            if let Some(rest) = rest.strip_prefix('=') {
+                value = Some(rest);
+            }
+        }
+    }
+    match value {
+        Some(value) => TransferMode::parse(value).ok_or_else(|| {
+            std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Unknown transfer mode \"{}\", expected copy, move, hardlink, symlink or reflink.",
+                    value
+                ),
+            )
+        }),
+        None => Ok(TransferMode::Move),
+    }
+}
+
+pub fn run_headless(transfer_mode: TransferMode) -> std::io::Result<()> {
+    let home_directory_path = match system_dir::get_home_directory() {
+        Some(home_directory_path) => home_directory_path,
+        None => {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                "Could not find home directory",
+            ))
+        }
+    };
+    let mut state = WatchState::new();
+    println!(
+        "Watching folders listed in {} ({})",
+        get_watch_file_location(&home_directory_path, WATCH_FILE_NAME).display(),
+        transfer_mode
+    );
+    loop {
+        let watch_folders = read_watch_folders(&home_directory_path)?;
+        for entry in poll_watch_folders(
+            &home_directory_path,
+            &watch_folders,
+            &mut state,
+            transfer_mode,
+        ) {
+            println!("{}", entry);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_waits_for_settle_time() {
+        let mut state = WatchState::new();
+        let path = PathBuf::from("/inbox/photo.jpg");
+        let start = Instant::now();
+        let settle = Duration::from_secs(5);
+        assert!(!state.observe(&path, 10, None, start, settle));
+        assert!(!state.observe(&path, 20, None, start + Duration::from_secs(3), settle));
+        assert!(!state.observe(&path, 20, None, start + Duration::from_secs(6), settle));
+        assert!(state.observe(&path, 20, None, start + Duration::from_secs(8), settle));
+        assert!(!state.observe(&path, 20, None, start + Duration::from_secs(10), settle));
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let watch_folder = WatchFolder::new(
+            PathBuf::from("/inbox"),
+            PathBuf::from("/organized"),
+            parse_ignore_patterns(""),
+        );
+        assert!(watch_folder.is_ignored("movie.mkv.part"));
+        assert!(watch_folder.is_ignored(".hidden"));
+        assert!(!watch_folder.is_ignored("photo.jpg"));
+        let watch_folder = WatchFolder::new(
+            PathBuf::from("/inbox"),
+            PathBuf::from("/organized"),
+            parse_ignore_patterns("*.jpg; Thumbs.db"),
+        );
+        assert!(watch_folder.is_ignored("photo.jpg"));
+        assert!(watch_folder.is_ignored("Thumbs.db"));
+        assert!(!watch_folder.is_ignored("movie.mkv.part"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_settled_files_with_non_utf8_name() {
+        use std::os::unix::ffi::OsStringExt;
+        let test_directory = tempfile::tempdir().expect("Failed to create test directory");
+        let non_utf8_name = OsString::from_vec(b"photo\xff.jpg".to_vec());
+        for file_name in [OsString::from("notes.txt"), non_utf8_name.clone()] {
+            std::fs::write(test_directory.path().join(file_name), b"file")
+                .expect("Failed to write test file");
+        }
+        let watch_folder = WatchFolder::new(
+            test_directory.path().to_path_buf(),
+            PathBuf::from("/organized"),
+            parse_ignore_patterns(""),
+        );
+        let mut state = WatchState::new();
+        let start = Instant::now();
+        state
+            .collect_settled_files(&watch_folder, start)
+            .expect("Failed to collect inbox files");
+        let settled_files = state
+            .collect_settled_files(&watch_folder, start + Duration::from_secs(10))
+            .expect("Failed to collect inbox files");
+        let file_names: Vec<&OsString> = settled_files.keys().collect();
+        assert_eq!(file_names, vec![&OsString::from("notes.txt"), &non_utf8_name]);
+    }
+
+    #[test]
+    fn test_parse_transfer_mode_argument() {
+        let parse = |arguments: &[&str]| {
+            let arguments: Vec<String> = arguments.iter().map(|a| String::from(*a)).collect();
+            parse_transfer_mode_argument(&arguments).ok()
+        };
+        assert_eq!(parse(&["filerganizer", "--watch"]), Some(TransferMode::Move));
+        assert_eq!(
+            parse(&["filerganizer", "--watch", "--transfer-mode", "copy"]),
+            Some(TransferMode::Copy)
+        );
+        assert_eq!(
+            parse(&["filerganizer", "--transfer-mode=symlink", "--watch"]),
+            Some(TransferMode::Symlink)
+        );
+        assert_eq!(parse(&["filerganizer", "--watch", "--transfer-mode"]), None);
+        assert_eq!(
+            parse(&["filerganizer", "--watch", "--transfer-mode", "teleport"]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_watch_folders() {
+        let mut watch_folder = WatchFolder::new(
+            PathBuf::from("/home/verneri/Downloads, new"),
+            PathBuf::from("/home/verneri/organized=2025"),
+            vec![String::from("*.part"), String::from(".*")],
+        );
+        watch_folder.set_paused(true);
+        let other_watch_folder = WatchFolder::new(
+            PathBuf::from("/home/verneri/Inbox"),
+            PathBuf::from("/home/verneri/Documents"),
+            Vec::new(),
+        );
+        let mut buffer = String::new();
+        for watch_folder in [&watch_folder, &other_watch_folder] {
+            write_watch_folder_to_string(&mut buffer, watch_folder)
+                .expect("Failed to write watch folder");
+        }
+        buffer.push_str("inbox=/no/organized/directory\n");
+        assert_eq!(
+            parse_watch_folders(&buffer),
+            vec![watch_folder, other_watch_folder]
+        );
+    }
+}